@@ -0,0 +1,76 @@
+use clap::ValueEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::platform::steam_root;
+use crate::error::{Result, SpawnError};
+use crate::utils::find_in_path;
+
+/// Compatibility layers for running Windows builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompatTool {
+    Wine,
+    Proton,
+}
+
+impl CompatTool {
+    pub fn label(self) -> &'static str {
+        match self {
+            CompatTool::Wine => "Wine",
+            CompatTool::Proton => "Proton",
+        }
+    }
+}
+
+/// Returns the configured Proton script, or the newest Proton found in the Steam library.
+pub fn find_proton(config: &Config) -> Option<PathBuf> {
+    if let Some(path) = &config.proton_path {
+        let script = if path.is_dir() { path.join("proton") } else { path.clone() };
+        return script.exists().then_some(script);
+    }
+
+    let common = steam_root().join("steamapps/common");
+    let mut versions: Vec<PathBuf> = fs::read_dir(common).ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("Proton"))
+        .map(|e| e.path().join("proton"))
+        .filter(|p| p.exists())
+        .collect();
+    versions.sort();
+    versions.pop()
+}
+
+/// Picks whichever compatibility layer is available, preferring a system Wine.
+pub fn default_tool(config: &Config) -> Option<CompatTool> {
+    if find_in_path("wine").is_some() {
+        Some(CompatTool::Wine)
+    } else if find_proton(config).is_some() {
+        Some(CompatTool::Proton)
+    } else {
+        None
+    }
+}
+
+/// Builds the command words that must precede a Windows executable.
+pub fn wrapper_command(tool: CompatTool, game_dir: &Path, config: &Config) -> Result<Vec<String>> {
+    match tool {
+        CompatTool::Wine => {
+            if find_in_path("wine").is_none() {
+                return Err(SpawnError::ProgramMissing { label: "Wine", program: "wine" });
+            }
+            Ok(vec!["wine".to_string()])
+        }
+        CompatTool::Proton => {
+            let proton = find_proton(config).ok_or(SpawnError::ProtonNotFound)?;
+            let steam_root = steam_root();
+
+            Ok(vec![
+                format!("STEAM_COMPAT_DATA_PATH={}", game_dir.join(".proton").display()),
+                format!("STEAM_COMPAT_CLIENT_INSTALL_PATH={}", steam_root.display()),
+                proton.to_string_lossy().into_owned(),
+                "run".to_string(),
+            ])
+        }
+    }
+}
@@ -1,46 +1,272 @@
-use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::discovery::SearchScope;
+use crate::error::{IoContext, Result, SpawnError};
+use crate::lock::{FileLock, lock_file, lock_path};
+use crate::report;
+use crate::utils::write_atomic;
+
+/// Places that receive a `.desktop` shortcut.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DesktopTarget {
+    /// The applications menu (~/.local/share/applications)
+    Menu,
+    /// The Desktop folder, if it exists
+    Desktop,
+}
+
+/// What to do when a game's install folder already exists.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// Ask each time (non-interactive runs overwrite)
+    #[default]
+    Ask,
+    /// Replace it without asking
+    Always,
+    /// Keep it and install nothing new
+    Never,
+}
+
+fn default_search_dir() -> PathBuf {
+    dirs_next::download_dir().unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn default_install_dir() -> PathBuf {
+    dirs_next::home_dir().map(|h| h.join("Games")).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Deepest folder level searched for executables and icons
+fn default_discovery_depth() -> usize {
+    3
+}
+
+/// Each extra level can mean walking thousands more files in large games, so deeper
+/// searches are capped.
+pub const MAX_DISCOVERY_DEPTH: usize = 10;
+
+/// Folders discovery never descends into: libraries, docs and engine asset trees
+fn default_discovery_skip_dirs() -> Vec<String> {
+    ["lib", "docs", "locale", "*_Data", "resources", ".git"].map(String::from).to_vec()
+}
+
+/// Launcher scripts in the game root that are used without looking for a binary
+fn default_launcher_names() -> Vec<String> {
+    ["start.sh", "run.sh", "launcher.sh"].map(String::from).to_vec()
+}
+
+fn default_launcher_extensions() -> Vec<String> {
+    vec![".AppImage".to_string()]
+}
+
+fn default_system_install_dir() -> PathBuf {
+    PathBuf::from("/opt/games")
+}
+
+fn default_check_for_updates() -> bool {
+    true
+}
+
+/// Seconds each attempt at the update check may take before it's retried
+fn default_update_timeout_secs() -> u64 {
+    2
+}
+
+/// Hours a successful update check is reused before Spawn asks GitHub again
+fn default_update_check_interval_hours() -> u64 {
+    24
+}
+
+fn default_desktop_targets() -> Vec<DesktopTarget> {
+    vec![DesktopTarget::Menu, DesktopTarget::Desktop]
+}
+
+/// Keys that fall back to a default when missing from an older config file.
+const DEFAULTED_KEYS: &[&str] = &["search_dir", "install_dir", "desktop_targets", "discovery_depth", "discovery_skip_dirs", "system_install_dir", "overwrite_policy", "launcher_names", "launcher_extensions", "update_timeout_secs", "update_check_interval_hours", "check_for_updates"];
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
+    #[serde(default = "default_search_dir")]
     pub search_dir: PathBuf,
+    #[serde(default = "default_install_dir")]
     pub install_dir: PathBuf,
+    /// Where `--system` installs games for every user
+    #[serde(default = "default_system_install_dir")]
+    pub system_install_dir: PathBuf,
+    /// Proton install (or its `proton` script) used for `--compat proton`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proton_path: Option<PathBuf>,
+    /// Other folders `--auto-install-dir` may pick instead of `install_dir`, e.g. on bigger drives
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub install_dir_candidates: Vec<PathBuf>,
+    /// Where shortcuts are created
+    #[serde(default = "default_desktop_targets")]
+    pub desktop_targets: Vec<DesktopTarget>,
+    /// How many folder levels deep discovery looks for the executable and icon
+    #[serde(default = "default_discovery_depth")]
+    pub discovery_depth: usize,
+    /// Folder names (`*` wildcards allowed) that discovery skips entirely
+    #[serde(default = "default_discovery_skip_dirs")]
+    pub discovery_skip_dirs: Vec<String>,
+    /// File names (`*` wildcards allowed) of launchers in the game root, used before any binary
+    #[serde(default = "default_launcher_names")]
+    pub launcher_names: Vec<String>,
+    /// File endings that also mark a launcher in the game root
+    #[serde(default = "default_launcher_extensions")]
+    pub launcher_extensions: Vec<String>,
+    /// Whether an existing install folder is replaced, kept, or asked about
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    /// Whether Spawn may contact GitHub to see if a newer version is out
+    #[serde(default = "default_check_for_updates")]
+    pub check_for_updates: bool,
+    /// How long each attempt at the update check may take, in seconds
+    #[serde(default = "default_update_timeout_secs")]
+    pub update_timeout_secs: u64,
+    /// How often the update check contacts GitHub, in hours; 0 checks on every run
+    #[serde(default = "default_update_check_interval_hours")]
+    pub update_check_interval_hours: u64,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            search_dir: dirs_next::download_dir().unwrap_or_else(|| PathBuf::from(".")),
-            install_dir: dirs_next::home_dir().map(|h| h.join("Games")).unwrap_or_else(|| PathBuf::from(".")),
+            search_dir: default_search_dir(),
+            install_dir: default_install_dir(),
+            system_install_dir: default_system_install_dir(),
+            proton_path: None,
+            install_dir_candidates: Vec::new(),
+            desktop_targets: default_desktop_targets(),
+            discovery_depth: default_discovery_depth(),
+            discovery_skip_dirs: default_discovery_skip_dirs(),
+            launcher_names: default_launcher_names(),
+            launcher_extensions: default_launcher_extensions(),
+            overwrite_policy: OverwritePolicy::default(),
+            check_for_updates: default_check_for_updates(),
+            update_timeout_secs: default_update_timeout_secs(),
+            update_check_interval_hours: default_update_check_interval_hours(),
+        }
+    }
+}
+
+impl Config {
+    pub fn search_scope(&self) -> SearchScope<'_> {
+        SearchScope {
+            depth: self.discovery_depth,
+            skip_dirs: &self.discovery_skip_dirs,
+            launcher_names: &self.launcher_names,
+            launcher_extensions: &self.launcher_extensions,
         }
     }
 }
 
-pub fn get_config_path() -> Result<PathBuf> {
+/// Where the config lives: `custom` (from `--config`) if given, else `~/.config/spawn/config.toml`.
+pub fn get_config_path(custom: Option<&Path>) -> Result<PathBuf> {
+    if let Some(path) = custom {
+        return Ok(path.to_path_buf());
+    }
+
     let config_dir = dirs_next::config_dir()
-        .ok_or_else(|| anyhow!("Could not find config directory"))?
+        .ok_or(SpawnError::NoConfigDir)?
         .join("spawn");
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir)?;
-    }
     Ok(config_dir.join("config.toml"))
 }
 
-pub fn load_config() -> Config {
-    let path = match get_config_path() {
+/// Where the last update check's result is cached: next to the config file.
+pub fn update_cache_path(custom: Option<&Path>) -> Option<PathBuf> {
+    get_config_path(custom).ok().map(|p| p.with_file_name("update-check.json"))
+}
+
+/// Environment variables that override a config key for one run, e.g. in CI or a container.
+/// Precedence, lowest first: defaults, the config file, these variables, command-line flags.
+pub const ENV_OVERRIDES: &[&str] = &["SPAWN_INSTALL_DIR", "SPAWN_SEARCH_DIR"];
+
+/// Loads the config, with `SPAWN_INSTALL_DIR` and `SPAWN_SEARCH_DIR` applied on top.
+pub fn load_config(custom: Option<&Path>) -> Config {
+    with_env_overrides(read_config(custom, true))
+}
+
+/// Loads the config without writing anything, not even newly defaulted keys, for commands
+/// that only report settings. A missing file gives the defaults.
+pub fn peek_config(custom: Option<&Path>) -> Config {
+    with_env_overrides(read_config(custom, false))
+}
+
+/// Loads just what the config file says, without environment overrides, for changing a
+/// setting and saving it back. Hold [`lock_config`] across the load and [`save_config`].
+pub fn load_stored_config(custom: Option<&Path>) -> Config {
+    // Defaulted keys are written along with the change
+    read_config(custom, false)
+}
+
+/// Locks the config so another Spawn instance can't change it between loading and saving.
+pub fn lock_config(custom: Option<&Path>) -> Result<FileLock> {
+    lock_file(&lock_path(&get_config_path(custom)?))
+}
+
+fn with_env_overrides(mut config: Config) -> Config {
+    for (key, field) in ENV_OVERRIDES.iter().zip([&mut config.install_dir, &mut config.search_dir]) {
+        if let Some(value) = std::env::var_os(key).filter(|v| !v.is_empty()) {
+            *field = std::path::absolute(&value).unwrap_or_else(|_| PathBuf::from(&value));
+            log::debug!("{} overrides the config: {:?}", key, field);
+        }
+    }
+    config
+}
+
+fn read_config(custom: Option<&Path>, write_back: bool) -> Config {
+    let path = match get_config_path(custom) {
         Ok(p) => p,
         Err(_) => return Config::default(),
     };
     
-    fs::read_to_string(path)
-        .and_then(|s| toml::from_str(&s).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)))
-        .unwrap_or_else(|_| Config::default())
+    let content = match fs::read_to_string(&path) {
+        Ok(s) => s,
+        Err(_) => {
+            if custom.is_some() {
+                report::warning(format!("Config {:?} does not exist yet, using defaults", path));
+            }
+            return Config::default();
+        }
+    };
+
+    let mut config: Config = match toml::from_str(&content) {
+        Ok(c) => c,
+        Err(e) => {
+            report::warning(format!("Could not read config {:?}, using defaults: {}", path, e.message()));
+            return Config::default();
+        }
+    };
+
+    let depth = config.discovery_depth.clamp(1, MAX_DISCOVERY_DEPTH);
+    if depth != config.discovery_depth {
+        report::warning(format!("discovery_depth must be between 1 and {}, using {}", MAX_DISCOVERY_DEPTH, depth));
+        config.discovery_depth = depth;
+    }
+
+    // Write newly defaulted keys back so the warning only shows once after an upgrade
+    let table: toml::Table = content.parse().unwrap_or_default();
+    let missing = DEFAULTED_KEYS.iter().filter(|k| !table.contains_key(**k)).copied().collect::<Vec<_>>();
+    if write_back && !missing.is_empty() {
+        report::warning(format!("Config is missing {}, using the default", missing.join(", ")));
+        // Only if no other instance changed the file since it was read
+        if let Ok(_lock) = lock_config(custom) && fs::read_to_string(&path).is_ok_and(|now| now == content) {
+            let _ = save_config(&config, custom);
+        }
+    }
+    config
 }
 
-pub fn save_config(config: &Config) -> Result<()> {
-    let path = get_config_path()?;
-    let s = toml::to_string(config).map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
-    fs::write(path, s).context("Failed to write config file")
+/// Writes the config file. Callers changing a setting hold [`lock_config`] from before loading it.
+pub fn save_config(config: &Config, custom: Option<&Path>) -> Result<()> {
+    let path = get_config_path(custom)?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty() && !p.exists()) {
+        fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    let s = toml::to_string(config).map_err(|e| SpawnError::Serialize { what: "config", reason: e.to_string() })?;
+    write_atomic(&path, s).context("Failed to write config file")
 }
@@ -1,65 +1,725 @@
-use anyhow::{Result, anyhow};
+use indicatif::HumanBytes;
+use serde::Deserialize;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::compat::{CompatTool, default_tool};
+use crate::config::Config;
+use crate::error::{IoContext, Result, SpawnError};
+use crate::installation::{ArchiveEntry, LAYOUT_DIRS, is_junk_entry};
+use crate::utils::{find_in_path, is_env_assignment, matches_glob, read_desktop_entry, set_executable_permission};
+use crate::report;
 use std::fs;
+use std::io::Read;
+use std::process::Command;
+use std::time::Duration;
+
+/// Overrides read from a `.spawn` file in the game's root folder.
+#[derive(Deserialize, Debug, Default)]
+pub struct SpawnHints {
+    pub executable: Option<PathBuf>,
+    pub icon: Option<PathBuf>,
+}
+
+/// Loads the `.spawn` hint file, resolving its paths against the game directory.
+pub fn load_spawn_hints(game_dir: &Path) -> Result<Option<SpawnHints>> {
+    let hint_path = game_dir.join(".spawn");
+    if !hint_path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&hint_path).context("Failed to read .spawn file")?;
+    let hints: SpawnHints = toml::from_str(&content)
+        .map_err(|e| SpawnError::InvalidHints { path: hint_path.clone(), reason: e.to_string() })?;
+
+    // The file comes with the archive, so it must not point Spawn (possibly running as root) at files elsewhere
+    let resolve = |path: Option<PathBuf>, kind: &'static str| -> Result<Option<PathBuf>> {
+        match path.map(|p| game_dir.join(p)) {
+            Some(path) if !path.is_file() => Err(SpawnError::MissingHintTarget { kind, path, game_dir: game_dir.to_path_buf() }),
+            Some(path) if !is_inside(&path, game_dir) => Err(SpawnError::HintOutsideGame { kind, path, game_dir: game_dir.to_path_buf() }),
+            other => Ok(other),
+        }
+    };
+
+    Ok(Some(SpawnHints {
+        executable: resolve(hints.executable, "executable")?,
+        icon: resolve(hints.icon, "icon")?,
+    }))
+}
+
+/// Whether `path` resolves, following any symlinks, to somewhere inside `game_dir`. Paths read
+/// from files the archive ships are checked with this before Spawn touches them.
+fn is_inside(path: &Path, game_dir: &Path) -> bool {
+    match (path.canonicalize(), game_dir.canonicalize()) {
+        (Ok(path), Ok(game_dir)) => path.starts_with(game_dir),
+        _ => false,
+    }
+}
+
+/// Launch details read from a `.desktop` file shipped inside the game folder, with paths
+/// rewritten to point into the install.
+#[derive(Debug, Default)]
+pub struct BundledLauncher {
+    pub source: PathBuf,
+    pub name: Option<String>,
+    pub executable: Option<PathBuf>,
+    pub env: Vec<String>,
+    pub args: Vec<String>,
+    pub icon: Option<PathBuf>,
+}
+
+/// Finds and parses the shallowest `.desktop` file in the game's root or its direct subfolders.
+pub fn find_bundled_launcher(game_dir: &Path) -> Option<BundledLauncher> {
+    let source = WalkDir::new(game_dir).max_depth(2).into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "desktop"))
+        .map(|e| e.into_path())
+        .min_by_key(|p| p.components().count())?;
+    let fields = read_desktop_entry(&source)?;
+    let base = source.parent().unwrap_or(game_dir);
+
+    let mut launcher = BundledLauncher {
+        name: fields.get("Name").filter(|n| !n.is_empty()).cloned(),
+        icon: fields.get("Icon").and_then(|icon| resolve_bundled_icon(icon, base, game_dir)),
+        ..Default::default()
+    };
+
+    // Field codes like %f or %U are filled in by the desktop; Spawn has nothing to pass
+    let mut words = fields.get("Exec").and_then(|exec| shlex::split(exec)).unwrap_or_default();
+    words.retain(|w| !(w.len() == 2 && w.starts_with('%') && w != "%%"));
+    if words.first().is_some_and(|w| w == "env") {
+        words.remove(0);
+        let env_count = words.iter().take_while(|w| is_env_assignment(w)).count();
+        launcher.env = words.drain(..env_count).collect();
+    }
+    if !words.is_empty() {
+        let program = words.remove(0);
+        launcher.executable = resolve_bundled_path(&program, base, game_dir);
+        launcher.args = words.into_iter().map(|w| w.replace("%%", "%")).collect();
+    }
+
+    launcher.source = source;
+    log::debug!("Bundled launcher: {:?}", launcher);
+    Some(launcher)
+}
+
+/// Maps a path from a bundled `.desktop` file to a file inside the game folder. Relative paths
+/// are tried next to the `.desktop` file and in the game root; absolute ones (written for a
+/// system install such as `/opt/game/bin/game`) by their trailing components. Paths that lead
+/// out of the game folder, such as `../../usr/bin/x`, are never returned.
+fn resolve_bundled_path(path: &str, base: &Path, game_dir: &Path) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if path.is_relative() {
+        return [base.join(path), game_dir.join(path)].into_iter().find(|p| p.is_file() && is_inside(p, game_dir));
+    }
+
+    let components = path.components().skip(1).collect::<Vec<_>>();
+    (0..components.len())
+        .map(|skip| components[skip..].iter().fold(game_dir.to_path_buf(), |acc, c| acc.join(c)))
+        .find(|p| p.is_file() && is_inside(p, game_dir))
+}
+
+/// Resolves an `Icon=` value, which is either a path or an icon name looked up by file stem.
+fn resolve_bundled_icon(icon: &str, base: &Path, game_dir: &Path) -> Option<PathBuf> {
+    if let Some(path) = resolve_bundled_path(icon, base, game_dir) {
+        return Some(path);
+    }
+    if icon.contains('/') {
+        return None;
+    }
+
+    WalkDir::new(game_dir).max_depth(4).into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.file_stem().is_some_and(|stem| stem == icon) && icon_score(p, &[]).is_some())
+        .min_by_key(|p| p.components().count())
+}
+
+/// How far discovery looks into a game folder.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchScope<'a> {
+    /// Deepest folder level searched
+    pub depth: usize,
+    /// Folder names (wildcards allowed, see [`matches_glob`]) that are never descended into
+    pub skip_dirs: &'a [String],
+    /// File names (wildcards allowed) of launchers that are used as soon as they're found in the game root
+    pub launcher_names: &'a [String],
+    /// File endings, such as `.AppImage`, that also mark a launcher in the game root
+    pub launcher_extensions: &'a [String],
+}
+
+impl SearchScope<'_> {
+    fn skips(&self, dir_name: &str) -> bool {
+        self.skip_dirs.iter().any(|pattern| matches_glob(dir_name, pattern))
+    }
+
+    fn is_launcher(&self, file_name: &str) -> bool {
+        let lower = file_name.to_lowercase();
+        self.launcher_names.iter().any(|pattern| matches_glob(file_name, pattern))
+            || self.launcher_extensions.iter().any(|ext| !ext.is_empty() && lower.ends_with(&ext.to_lowercase()))
+    }
+
+    /// Walks the game folder, pruning skipped folders without reading their contents.
+    fn walk(&self, game_dir: &Path) -> impl Iterator<Item = DirEntry> {
+        WalkDir::new(game_dir)
+            .max_depth(self.depth)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_type().is_dir() || !self.skips(&e.file_name().to_string_lossy()))
+            .filter_map(|e| e.ok())
+    }
+
+    /// Whether an archive path (relative to the game root) is within depth and outside skipped folders.
+    fn includes(&self, relative: &Path) -> bool {
+        let components = relative.components().collect::<Vec<_>>();
+        components.len() <= self.depth
+            && !components.iter().rev().skip(1).any(|c| self.skips(&c.as_os_str().to_string_lossy()))
+    }
+}
+
+/// `expected_names` are what the game is called (archive, folder, `--name`); binaries sharing
+/// one of them are preferred over other candidates.
+pub fn discover_executable(game_dir: &Path, interactive: bool, scope: SearchScope, expected_names: &[String]) -> Result<PathBuf> {
+    if let Some(executable) = load_spawn_hints(game_dir)?.and_then(|h| h.executable) {
+        log::debug!("Using executable from .spawn hints: {:?}", executable);
+        return Ok(executable);
+    }
+
+    if let Some(executable) = find_bundled_launcher(game_dir).and_then(|l| l.executable) {
+        log::debug!("Using executable from bundled .desktop file: {:?}", executable);
+        return Ok(executable);
+    }
 
-pub fn discover_executable(game_dir: &Path) -> Result<PathBuf> {
     let mut candidates = Vec::new();
+    let has = |relative: &Path| game_dir.join(relative).exists();
 
-    for entry in WalkDir::new(game_dir).max_depth(3).into_iter().filter_map(|e| e.ok()) {
+    for entry in scope.walk(game_dir) {
         let path = entry.path();
+        log::trace!("Visiting {:?}", path);
         if path.is_file() {
-            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            
-            // Heuristics:
-            // 1. Common launcher scripts in root or AppImage
-            if path.parent() == Some(game_dir) && (file_name == "start.sh" || file_name == "run.sh" || file_name == "launcher.sh" || file_name.ends_with(".AppImage")) {
-                return Ok(path.to_path_buf());
-            }
-
-            // 2. Ends with .x86_64 or .x86
-            if file_name.ends_with(".x86_64") || file_name.ends_with(".x86") {
-                if is_elf_binary(path) {
-                    candidates.push(path.to_path_buf());
+            let relative = path.strip_prefix(game_dir).unwrap_or(path);
+            match classify_executable(relative, scope, &has, || is_elf_binary(path)) {
+                Some(ExecutableKind::Launcher) => {
+                    log::debug!("Using launcher in game root: {:?}", path);
+                    return Ok(path.to_path_buf());
                 }
-            } else if !file_name.contains('.') {
-                // 3. No extension and is not a common text/data file
-                if !path.to_string_lossy().contains("/lib/") && !path.to_string_lossy().contains("/docs/") {
-                     if is_elf_binary(path) {
-                         candidates.push(path.to_path_buf());
-                     }
+                Some(ExecutableKind::Binary) => {
+                    log::debug!("Candidate executable: {:?}", path);
+                    candidates.push(path.to_path_buf());
                 }
+                None => {}
             }
         }
     }
 
-    candidates.sort_by_key(|p| (p.components().count(), p.file_name().map(|n| n.len()).unwrap_or(0)));
+    rank_executables(&mut candidates, expected_names);
+    candidates.sort_by_key(|p| engine_rank(p.strip_prefix(game_dir).unwrap_or(p), &has));
+    log::debug!("Ranked {} executable candidate(s): {:?}", candidates.len(), candidates);
+
+    if candidates.is_empty() {
+        return Err(SpawnError::NoExecutable(game_dir.to_path_buf()));
+    }
+
+    if interactive && candidates.len() > 1 {
+        report::step(format!("Found {} possible executables:", candidates.len()));
+        let items = candidates.iter().map(|c| {
+            let relative = c.strip_prefix(game_dir).unwrap_or(c);
+            let size = fs::metadata(c).map(|m| m.len()).unwrap_or(0);
+            format!("{} ({})", relative.display(), HumanBytes(size))
+        }).collect::<Vec<_>>();
+
+        let index = report::choose("Enter the number of the game executable (or press Enter for the first):", &items)?.unwrap_or(0);
+        if index >= candidates.len() {
+            return Err(SpawnError::InvalidSelection((index + 1).to_string()));
+        }
+        return Ok(candidates.swap_remove(index));
+    }
+
+    Ok(candidates.swap_remove(0))
+}
+
+/// What to run for an installed game, and how.
+#[derive(Debug, Clone)]
+pub struct LaunchTarget {
+    pub executable: PathBuf,
+    /// The compatibility layer a Windows build runs through
+    pub compat: Option<CompatTool>,
+    pub icon: Option<PathBuf>,
+}
+
+/// Finds the executable and icon to use, offering a compatibility layer when the game is a
+/// Windows build. `compat` forces one; `expected_names` are as for [`discover_executable`].
+pub fn discover_launch_target(game_dir: &Path, compat: Option<CompatTool>, config: &Config, interactive: bool, expected_names: &[String]) -> Result<LaunchTarget> {
+    let (executable, compat) = discover_launch_executable(game_dir, compat, config, interactive, expected_names)?;
+    let mut icon_names = expected_names.to_vec();
+    icon_names.extend(executable.file_name().map(|n| n.to_string_lossy().into_owned()));
+    let icon = discover_icon(game_dir, config.search_scope(), &icon_names);
+    Ok(LaunchTarget { executable, compat, icon })
+}
+
+fn discover_launch_executable(game_dir: &Path, compat: Option<CompatTool>, config: &Config, interactive: bool, expected_names: &[String]) -> Result<(PathBuf, Option<CompatTool>)> {
+    if let Some(tool) = compat {
+        return Ok((discover_windows_executable(game_dir, config.search_scope())?, Some(tool)));
+    }
+
+    // Only a missing executable is worth a second look; a broken .spawn file or a cancelled prompt isn't
+    let err = match discover_executable(game_dir, interactive, config.search_scope(), expected_names) {
+        Ok(executable) => return Ok((executable, None)),
+        Err(e @ SpawnError::NoExecutable(_)) => e,
+        Err(e) => return Err(e),
+    };
 
-    candidates.into_iter().next().ok_or_else(|| anyhow!("No executable found in {:?}\nHint: This archive may not be a Linux build", game_dir))
+    let Ok(windows_exe) = discover_windows_executable(game_dir, config.search_scope()) else {
+        return Err(err);
+    };
+    let Some(tool) = default_tool(config) else {
+        return Err(SpawnError::WindowsOnly(windows_exe.file_name().unwrap_or_default().into()));
+    };
+
+    report::warning(format!("No Linux executable found, but this looks like a Windows build: {:?}", windows_exe.file_name().unwrap_or_default()));
+    if interactive && !report::confirm(&format!("Launch it through {}?", tool.label()), true)? {
+        return Err(err);
+    }
+    Ok((windows_exe, Some(tool)))
 }
 
-pub fn discover_icon(game_dir: &Path) -> Option<PathBuf> {
+enum ExecutableKind {
+    /// A launcher script or AppImage in the game root, used without looking further
+    Launcher,
+    /// A native binary, ranked against the other candidates
+    Binary,
+}
+
+/// Applies the executable heuristics to a path relative to the game root.
+/// `is_elf` is only called for files whose name makes them a candidate; `has` tells whether a
+/// path relative to the game root exists.
+fn classify_executable(relative: &Path, scope: SearchScope, has: &impl Fn(&Path) -> bool, is_elf: impl FnOnce() -> bool) -> Option<ExecutableKind> {
+    let file_name = relative.file_name()?.to_str()?;
+
+    // 1. Launcher scripts or AppImages in root (launcher_names and launcher_extensions)
+    if relative.components().count() == 1 && (scope.is_launcher(file_name) || is_unreal_launcher(file_name, has)) {
+        return Some(ExecutableKind::Launcher);
+    }
+
+    let is_binary = if file_name.ends_with(".x86_64") || file_name.ends_with(".x86") {
+        // 2. Ends with .x86_64 or .x86
+        is_elf()
+    } else if !file_name.contains('.') {
+        // 3. No extension and is an ELF binary
+        is_elf()
+    } else {
+        false
+    };
+    is_binary.then_some(ExecutableKind::Binary)
+}
+
+/// Binaries named after the game first, then shallowest, then shortest file name.
+fn rank_executables(candidates: &mut [PathBuf], expected_names: &[String]) {
+    candidates.sort_by_key(|p| (name_rank(p, expected_names), p.components().count(), p.file_name().map(|n| n.len()).unwrap_or(0)));
+}
+
+/// Unreal packages keep the engine in an `Engine` folder next to a `<Project>.sh` launcher.
+/// `has` tells whether a path relative to the game root exists.
+fn is_unreal_layout(has: &impl Fn(&Path) -> bool) -> bool {
+    has(Path::new("Engine"))
+}
+
+/// The `<Project>.sh` script in an Unreal package's root, which starts `<Project>/Binaries`.
+/// Setup scripts shipped next to it are never taken for it.
+fn is_unreal_launcher(file_name: &str, has: &impl Fn(&Path) -> bool) -> bool {
+    let Some(project) = file_name.strip_suffix(".sh") else { return false };
+    is_unreal_layout(has)
+        && !SETUP_SCRIPTS.contains(&file_name.to_lowercase().as_str())
+        && has(&Path::new(project).join("Binaries"))
+}
+
+/// What the engine layout around a binary (relative to the game root) says about it: 0 for
+/// the engine's main executable, 2 for a tool the engine ships, 1 when the layout says nothing.
+fn engine_rank(relative: &Path, has: &impl Fn(&Path) -> bool) -> u8 {
+    let Some(file_name) = relative.file_name().and_then(|n| n.to_str()) else { return 1 };
+    let parent = relative.parent().unwrap_or(Path::new(""));
+    let stem = file_name.trim_end_matches(".x86_64").trim_end_matches(".x86");
+
+    // Unity: `Game.x86_64` loads `Game_Data/`, with the engine in `UnityPlayer.so`
+    let data_dir = parent.join(format!("{}_Data", stem));
+    if has(&data_dir) && (has(&parent.join("UnityPlayer.so")) || has(&data_dir.join("UnityPlayer.so"))) {
+        log::debug!("Unity player found: {:?}", relative);
+        return 0;
+    }
+    // Godot: the binary loads the `.pck` named like it
+    if has(&parent.join(format!("{}.pck", stem))) {
+        log::debug!("Godot binary found: {:?}", relative);
+        return 0;
+    }
+    // Unreal: the game runs from `<Project>/Binaries/Linux`, crash reporters and such live under `Engine`
+    if is_unreal_layout(has) {
+        if relative.starts_with("Engine") {
+            return 2;
+        }
+        if parent.ends_with("Binaries/Linux") {
+            log::debug!("Unreal binary found: {:?}", relative);
+            return 0;
+        }
+    }
+    1
+}
+
+/// 0 when the file is named exactly like the game, 1 when one name contains the other, 2 otherwise.
+fn name_rank(path: &Path, expected_names: &[String]) -> u8 {
+    name_match(path.file_name().and_then(|n| n.to_str()).unwrap_or(""), expected_names)
+}
+
+/// How closely `name` matches any of the expected names, ranked like [`name_rank`].
+fn name_match(name: &str, expected_names: &[String]) -> u8 {
+    let name = comparable_name(name);
+    if name.len() < 3 {
+        return 2;
+    }
+
+    expected_names.iter()
+        .map(|expected| comparable_name(expected))
+        .filter(|expected| !expected.is_empty())
+        .map(|expected| if expected == name { 0 } else if expected.contains(&name) || name.contains(&expected) { 1 } else { 2 })
+        .min()
+        .unwrap_or(2)
+}
+
+/// Lowercases a name and drops architecture suffixes, separators and punctuation,
+/// so `Celeste_Linux.x86_64` and `celeste-linux` compare equal.
+fn comparable_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    normalize_name(name.trim_end_matches(".x86_64").trim_end_matches(".x86").trim_end_matches(".exe"))
+}
+
+/// Drops separators and punctuation so `Celeste_Linux` and `celeste-linux` compare equal.
+fn normalize_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+/// Installers and runtime helpers that ship next to the real game executable.
+const WINDOWS_HELPERS: &[&str] = &["unins", "setup", "crashhandler", "crashreport", "vcredist", "dxsetup", "dxwebsetup", "redist", "directx"];
+
+pub fn discover_windows_executable(game_dir: &Path, scope: SearchScope) -> Result<PathBuf> {
     let mut candidates = Vec::new();
 
-    for entry in WalkDir::new(game_dir).max_depth(3).into_iter().filter_map(|e| e.ok()) {
+    for entry in scope.walk(game_dir) {
         let path = entry.path();
+        log::trace!("Visiting {:?}", path);
         if path.is_file() {
             let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
-            if file_name.ends_with(".png") || file_name.ends_with(".svg") || file_name.ends_with(".ico") {
-                let score = if file_name.contains("icon") || file_name.contains("logo") {
-                    10
-                } else {
-                    1
-                };
+            if WINDOWS_HELPERS.iter().any(|h| file_name.contains(h)) {
+                continue;
+            }
+
+            if file_name.ends_with(".exe") && is_pe_binary(path) {
+                candidates.push((0, path.to_path_buf()));
+            } else if file_name.ends_with(".bat") {
+                candidates.push((1, path.to_path_buf()));
+            }
+        }
+    }
+
+    candidates.sort_by_key(|(kind, p)| (*kind, p.components().count(), p.file_name().map(|n| n.len()).unwrap_or(0)));
+    log::debug!("Ranked {} Windows executable candidate(s): {:?}", candidates.len(), candidates.iter().map(|(_, p)| p).collect::<Vec<_>>());
+
+    candidates.into_iter().next().map(|(_, p)| p).ok_or_else(|| SpawnError::NoWindowsExecutable(game_dir.to_path_buf()))
+}
+
+/// Scripts some archives expect to be run once after extraction.
+const SETUP_SCRIPTS: &[&str] = &["install.sh", "setup.sh", "postinstall.sh", "post-install.sh"];
+
+/// Finds setup scripts in the game's root folder. These are only ever run after asking the user.
+pub fn discover_setup_scripts(game_dir: &Path) -> Vec<PathBuf> {
+    let mut scripts = Vec::new();
+    if let Ok(entries) = fs::read_dir(game_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            if path.is_file() && SETUP_SCRIPTS.contains(&file_name.as_str()) {
+                scripts.push(path);
+            }
+        }
+    }
+    scripts.sort();
+    scripts
+}
+
+/// Words in a file name that mark a game's secondary programs, with the label their shortcut gets.
+const EXTRA_PROGRAMS: &[(&str, &str)] = &[("editor", "Editor"), ("server", "Server")];
+
+/// Finds secondary programs such as a level editor or dedicated server next to the game's
+/// executable or in its root folder, returning each with its shortcut label.
+pub fn discover_extra_executables(game_dir: &Path, executable: &Path) -> Vec<(String, PathBuf)> {
+    let mut folders = vec![game_dir.to_path_buf()];
+    folders.extend(executable.parent().filter(|p| *p != game_dir).map(Path::to_path_buf));
+
+    let mut extras: Vec<(String, PathBuf)> = Vec::new();
+    for folder in folders {
+        let Ok(entries) = fs::read_dir(&folder) else { continue };
+        let mut paths = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>();
+        paths.sort();
+        for path in paths {
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            let Some((_, label)) = EXTRA_PROGRAMS.iter().find(|(word, _)| file_name.contains(word)) else { continue };
+            let runnable = file_name.ends_with(".sh") || is_elf_binary(&path);
+            // One shortcut per kind of program; the first (shallowest, alphabetical) wins
+            if path != executable && path.is_file() && runnable && !extras.iter().any(|(l, _)| l == label) {
+                extras.push((label.to_string(), path));
+            }
+        }
+    }
+    extras
+}
+
+/// Finds the game's icon. `expected_names` (the game, folder and executable names) pick out
+/// an image named after the game over generic artwork.
+pub fn discover_icon(game_dir: &Path, scope: SearchScope, expected_names: &[String]) -> Option<PathBuf> {
+    if let Ok(Some(SpawnHints { icon: Some(icon), .. })) = load_spawn_hints(game_dir) {
+        return Some(icon);
+    }
+
+    if let Some(icon) = find_bundled_launcher(game_dir).and_then(|l| l.icon) {
+        log::debug!("Using icon from bundled .desktop file: {:?}", icon);
+        return Some(icon);
+    }
+
+    let mut candidates = Vec::new();
+
+    for entry in scope.walk(game_dir) {
+        let path = entry.path();
+        log::trace!("Visiting {:?}", path);
+        if path.is_file() && let Some(score) = icon_score(path, expected_names) {
+            candidates.push((score, path.to_path_buf()));
+        }
+    }
+
+    candidates.sort_by_key(|(s, p)| (-*s, p.components().count()));
+    log::debug!("Ranked {} icon candidate(s): {:?}", candidates.len(), candidates);
+    candidates.into_iter().next().map(|(_, p)| p)
+}
+
+/// Words in image names that mark artwork other than an icon.
+const NON_ICON_WORDS: &[&str] = &["background", "splash", "screenshot", "banner", "wallpaper"];
+
+/// Ranks image files as icon candidates: an image named after the game wins, then names
+/// mentioning "icon" or "logo". Backgrounds, splash screens and the like rank last.
+fn icon_score(path: &Path, expected_names: &[String]) -> Option<i32> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+    if !(file_name.ends_with(".png") || file_name.ends_with(".svg") || file_name.ends_with(".ico")) {
+        return None;
+    }
+
+    let stem = path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+    let mut score = match name_match(stem, expected_names) {
+        0 => 20,
+        1 => 5,
+        _ => 0,
+    };
+    score += if file_name.contains("icon") || file_name.contains("logo") { 10 } else { 1 };
+    if NON_ICON_WORDS.iter().any(|word| file_name.contains(word)) {
+        score -= 30;
+    }
+    Some(score)
+}
+
+/// The folder an archive's files will end up in once wrapper directories are flattened,
+/// mirroring what extraction does.
+pub fn archive_root(entries: &[ArchiveEntry]) -> PathBuf {
+    let mut root = PathBuf::new();
+    loop {
+        let mut firsts = entries.iter().filter_map(|e| {
+            let mut components = e.path.strip_prefix(&root).ok()?.components();
+            let first = components.next().filter(|c| !is_junk_entry(c.as_os_str()))?;
+            // Files directly in the current root mean it isn't a wrapper
+            Some(components.next().map(|_| first.as_os_str().to_os_string()))
+        });
+
+        let Some(Some(first)) = firsts.next() else { break };
+        if !firsts.all(|f| f.as_ref() == Some(&first)) || LAYOUT_DIRS.contains(&first.to_string_lossy().to_lowercase().as_str()) {
+            break;
+        }
+        root.push(first);
+    }
+    root
+}
+
+/// Runs the executable heuristics over an archive listing. Returns a path relative to the archive.
+/// Files whose contents couldn't be read are assumed to be ELF binaries.
+pub fn preview_executable(entries: &[ArchiveEntry], scope: SearchScope, expected_names: &[String]) -> Option<PathBuf> {
+    let root = archive_root(entries);
+    let mut candidates = Vec::new();
+    // Folders are only implied by the files inside them in some listings
+    let listed = entries.iter().flat_map(|e| e.path.ancestors()).collect::<HashSet<_>>();
+    let has = |relative: &Path| listed.contains(root.join(relative).as_path());
+
+    for entry in entries {
+        let Ok(relative) = entry.path.strip_prefix(&root) else { continue };
+        if !scope.includes(relative) {
+            continue;
+        }
+        match classify_executable(relative, scope, &has, || entry.is_elf.unwrap_or(true)) {
+            Some(ExecutableKind::Launcher) => return Some(entry.path.clone()),
+            Some(ExecutableKind::Binary) => candidates.push(entry.path.clone()),
+            None => {}
+        }
+    }
+
+    rank_executables(&mut candidates, expected_names);
+    candidates.sort_by_key(|p| engine_rank(p.strip_prefix(&root).unwrap_or(p), &has));
+    candidates.into_iter().next()
+}
+
+/// First `.exe` in an archive listing that isn't an installer or runtime helper.
+pub fn preview_windows_executable(entries: &[ArchiveEntry], scope: SearchScope, expected_names: &[String]) -> Option<PathBuf> {
+    let root = archive_root(entries);
+    let mut candidates = entries.iter()
+        .map(|e| &e.path)
+        .filter(|p| p.strip_prefix(&root).is_ok_and(|r| scope.includes(r)))
+        .filter(|p| {
+            let file_name = p.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            file_name.ends_with(".exe") && !WINDOWS_HELPERS.iter().any(|h| file_name.contains(h))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    rank_executables(&mut candidates, expected_names);
+    candidates.into_iter().next()
+}
+
+/// Runs the icon heuristics over an archive listing.
+pub fn preview_icon(entries: &[ArchiveEntry], scope: SearchScope, expected_names: &[String]) -> Option<PathBuf> {
+    let root = archive_root(entries);
+    let mut candidates = entries.iter()
+        .filter(|e| e.path.strip_prefix(&root).is_ok_and(|r| scope.includes(r)))
+        .filter_map(|e| icon_score(&e.path, expected_names).map(|score| (score, e.path.clone())))
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(s, p)| (-*s, p.components().count()));
+    candidates.into_iter().next().map(|(_, p)| p)
+}
+
+/// Resource folders that often hold a game's icon next to the binary.
+const RESOURCE_DIRS: &[&str] = &["resources", "res", "icons", "pixmaps", "share/icons", "share/pixmaps", "data", "assets"];
+
+/// Finds an icon carried by the executable itself: an image in a resource folder next to an ELF
+/// binary or, with `run_appimage`, the `.DirIcon` inside an AppImage. Getting that one means
+/// running the AppImage, so it's only done when the user asks for it. The icon is copied into
+/// the game folder so the shortcut keeps pointing at it.
+pub fn extract_embedded_icon(executable: &Path, game_dir: &Path, run_appimage: bool) -> Option<PathBuf> {
+    let is_appimage = executable.extension().is_some_and(|e| e.eq_ignore_ascii_case("appimage"));
+    let (data, source) = if is_appimage {
+        if !run_appimage {
+            return None;
+        }
+        (extract_appimage_icon(executable)?, executable.to_path_buf())
+    } else {
+        let source = find_resource_icon(executable.parent().unwrap_or(game_dir))?;
+        (fs::read(&source).ok()?, source)
+    };
+
+    let extension = image_extension(&data)?;
+    let target = game_dir.join(format!("spawn-icon.{}", extension));
+    fs::write(&target, data).ok()?;
+    log::debug!("Copied embedded icon from {:?} to {:?}", source, target);
+    Some(target)
+}
+
+/// Extracts `.DirIcon` (following it if it's a symlink) using the AppImage's own `--appimage-extract`.
+/// It unpacks into a fresh private folder, so nothing else can plant files where it writes.
+fn extract_appimage_icon(appimage: &Path) -> Option<Vec<u8>> {
+    let work_dir = nix::unistd::mkdtemp(&std::env::temp_dir().join("spawn-icon-XXXXXX")).ok()?;
+    let _ = set_executable_permission(appimage);
+
+    let extract = |pattern: &str| {
+        Command::new(appimage)
+            .arg("--appimage-extract")
+            .arg(pattern)
+            .current_dir(&work_dir)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    };
+
+    let root = work_dir.join("squashfs-root");
+    let dir_icon = root.join(".DirIcon");
+    let data = if extract(".DirIcon") {
+        match fs::read_link(&dir_icon) {
+            Ok(target) if extract(&target.to_string_lossy()) => fs::read(root.join(target)).ok(),
+            Ok(_) => None,
+            Err(_) => fs::read(&dir_icon).ok(),
+        }
+    } else {
+        None
+    };
+
+    let _ = fs::remove_dir_all(&work_dir);
+    data
+}
+
+/// Looks for an image in the resource folders next to a binary, preferring icon/logo names.
+fn find_resource_icon(dir: &Path) -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    for resource_dir in RESOURCE_DIRS.iter().map(|d| dir.join(d)).filter(|d| d.is_dir()) {
+        for entry in WalkDir::new(&resource_dir).max_depth(4).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            if path.is_file() && (file_name.ends_with(".png") || file_name.ends_with(".svg")) {
+                let score = if file_name.contains("icon") || file_name.contains("logo") { 10 } else { 1 };
                 candidates.push((score, path.to_path_buf()));
             }
         }
     }
 
-    candidates.sort_by_key(|(s, p)| (-(*s as i32), p.components().count()));
+    candidates.sort_by_key(|(s, p)| (-*s, p.components().count()));
     candidates.into_iter().next().map(|(_, p)| p)
 }
 
+/// Icons bigger than this aren't downloaded; real ones are a few hundred KB at most.
+const MAX_ICON_DOWNLOAD: u64 = 10 * 1024 * 1024;
+
+/// Downloads an icon for `--icon-url` into the game folder, checking that it really is a
+/// PNG, JPEG or SVG image rather than, say, an HTML error page.
+pub fn download_icon(url: &str, game_dir: &Path) -> Result<PathBuf> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(10))
+        .timeout_read(Duration::from_secs(30))
+        .user_agent(concat!("spawn/", env!("CARGO_PKG_VERSION")))
+        .build();
+    let response = agent.get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, _) => SpawnError::DownloadStatus(code),
+        e => SpawnError::Download(e.to_string()),
+    })?;
+    let content_type = response.content_type().to_string();
+    let too_large = || SpawnError::IconTooLarge { url: url.to_string(), limit_mib: MAX_ICON_DOWNLOAD / (1024 * 1024) };
+    if response.header("Content-Length").and_then(|len| len.parse::<u64>().ok()).is_some_and(|len| len > MAX_ICON_DOWNLOAD) {
+        return Err(too_large());
+    }
+
+    // One byte over the limit tells a cut-off download from one that fit
+    let mut data = Vec::new();
+    response.into_reader().take(MAX_ICON_DOWNLOAD + 1).read_to_end(&mut data).context("Failed to download icon")?;
+    if data.len() as u64 > MAX_ICON_DOWNLOAD {
+        return Err(too_large());
+    }
+    let extension = image_extension(&data).ok_or_else(|| SpawnError::NotAnImage { url: url.to_string(), content_type })?;
+
+    let target = game_dir.join(format!("spawn-icon.{}", extension));
+    fs::write(&target, data).with_context(|| format!("Failed to save icon to {:?}", target))?;
+    log::debug!("Downloaded icon from {} to {:?}", url, target);
+    Ok(target)
+}
+
+/// File extension for PNG, JPEG or SVG image data.
+fn image_extension(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG") {
+        return Some("png");
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("jpg");
+    }
+    let head = String::from_utf8_lossy(&data[..data.len().min(512)]);
+    head.contains("<svg").then_some("svg")
+}
+
 pub fn is_elf_binary(path: &Path) -> bool {
     use std::io::Read;
     let mut file = match fs::File::open(path) {
@@ -72,3 +732,389 @@ pub fn is_elf_binary(path: &Path) -> bool {
     }
     buffer == [0x7F, 0x45, 0x4C, 0x46]
 }
+
+/// CPU architecture an ELF binary is built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfMachine {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    RiscV,
+    Other(u16),
+}
+
+impl ElfMachine {
+    fn from_e_machine(value: u16) -> Self {
+        match value {
+            0x03 => ElfMachine::X86,
+            0x3E => ElfMachine::X86_64,
+            0x28 => ElfMachine::Arm,
+            0xB7 => ElfMachine::Aarch64,
+            0xF3 => ElfMachine::RiscV,
+            other => ElfMachine::Other(other),
+        }
+    }
+
+    /// The architecture Spawn itself was built for.
+    pub fn host() -> Option<Self> {
+        match std::env::consts::ARCH {
+            "x86" => Some(ElfMachine::X86),
+            "x86_64" => Some(ElfMachine::X86_64),
+            "arm" => Some(ElfMachine::Arm),
+            "aarch64" => Some(ElfMachine::Aarch64),
+            "riscv64" => Some(ElfMachine::RiscV),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            ElfMachine::X86 => "x86 (32-bit)".to_string(),
+            ElfMachine::X86_64 => "x86_64".to_string(),
+            ElfMachine::Arm => "ARM (32-bit)".to_string(),
+            ElfMachine::Aarch64 => "ARM64".to_string(),
+            ElfMachine::RiscV => "RISC-V".to_string(),
+            ElfMachine::Other(value) => format!("an unknown architecture (0x{:x})", value),
+        }
+    }
+
+    /// Whether the host runs this architecture natively (x86_64 also runs 32-bit x86).
+    fn runs_on(self, host: ElfMachine) -> bool {
+        self == host || (self == ElfMachine::X86 && host == ElfMachine::X86_64)
+    }
+}
+
+/// Reads the target architecture from an ELF header, or `None` if the file isn't ELF.
+pub fn elf_machine(path: &Path) -> Option<ElfMachine> {
+    use std::io::Read;
+    let mut header = [0u8; 20];
+    fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if header[..4] != [0x7F, 0x45, 0x4C, 0x46] {
+        return None;
+    }
+
+    // e_machine sits at the same offset in 32- and 64-bit headers; byte 5 gives the endianness
+    let bytes = [header[18], header[19]];
+    let value = if header[5] == 2 { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) };
+    Some(ElfMachine::from_e_machine(value))
+}
+
+/// Emulators that run x86 Linux binaries on other architectures.
+const X86_EMULATORS: &[&str] = &["box64", "box86", "FEXInterpreter"];
+
+/// Warns when a native executable is built for a different CPU than this machine.
+/// Nothing is refused, since emulators such as box64 or FEX can often still run it.
+pub fn warn_on_arch_mismatch(executable: &Path) {
+    let (Some(machine), Some(host)) = (elf_machine(executable), ElfMachine::host()) else {
+        return;
+    };
+    if machine.runs_on(host) {
+        return;
+    }
+
+    let mismatch = format!("{:?} is built for {}, but this machine is {}", executable.file_name().unwrap_or_default(), machine.label(), host.label());
+    if !matches!(machine, ElfMachine::X86 | ElfMachine::X86_64) {
+        report::warning(format!("{}\n  It will most likely not start. Look for a build for {}.", mismatch, host.label()));
+        return;
+    }
+
+    match X86_EMULATORS.iter().find(|e| find_in_path(e).is_some()) {
+        Some(emulator) => report::warning(format!("{}\n  {} is installed, so it may still run through emulation.", mismatch, emulator)),
+        None => report::warning(format!("{}\nHint: Install box64 or FEX-Emu to run x86 games on this machine.", mismatch)),
+    }
+}
+
+/// Package that usually provides a shared library, by the start of its soname.
+const LIBRARY_PACKAGES: &[(&str, &str)] = &[
+    ("libSDL2-2.0", "SDL2 (libsdl2-2.0-0 / SDL2 / sdl2)"),
+    ("libSDL2_mixer", "SDL2_mixer (libsdl2-mixer-2.0-0 / SDL2_mixer / sdl2_mixer)"),
+    ("libSDL2_image", "SDL2_image (libsdl2-image-2.0-0 / SDL2_image / sdl2_image)"),
+    ("libSDL2_ttf", "SDL2_ttf (libsdl2-ttf-2.0-0 / SDL2_ttf / sdl2_ttf)"),
+    ("libSDL-1.2", "SDL 1.2 (libsdl1.2debian / SDL / sdl12-compat)"),
+    ("libGL.so", "OpenGL (libgl1 / mesa-libGL / mesa)"),
+    ("libGLU", "GLU (libglu1-mesa / mesa-libGLU / glu)"),
+    ("libvulkan", "the Vulkan loader (libvulkan1 / vulkan-loader / vulkan-icd-loader)"),
+    ("libopenal", "OpenAL (libopenal1 / openal-soft / openal)"),
+    ("libasound", "ALSA (libasound2 / alsa-lib / alsa-lib)"),
+    ("libpulse", "PulseAudio (libpulse0 / pulseaudio-libs / libpulse)"),
+    ("libX", "X11 client libraries (libx11-6, libxrandr2, libxi6... / libX11, libXrandr... / libx11, libxrandr...)"),
+    ("libgtk-3", "GTK 3 (libgtk-3-0 / gtk3 / gtk3)"),
+    ("libgtk-x11-2.0", "GTK 2 (libgtk2.0-0 / gtk2 / gtk2)"),
+    ("libfreetype", "FreeType (libfreetype6 / freetype / freetype2)"),
+    ("libfontconfig", "Fontconfig (libfontconfig1 / fontconfig / fontconfig)"),
+    ("libcurl", "curl (libcurl4 / libcurl / curl)"),
+    ("libssl", "OpenSSL (libssl3 / openssl-libs / openssl)"),
+    ("libcrypto", "OpenSSL (libssl3 / openssl-libs / openssl)"),
+    ("libpng", "libpng (libpng16-16 / libpng / libpng)"),
+    ("libudev", "udev (libudev1 / systemd-libs / systemd-libs)"),
+    ("libstdc++", "the C++ runtime (libstdc++6 / libstdc++ / gcc-libs)"),
+];
+
+/// Folders next to the executable that games commonly load bundled libraries from.
+const BUNDLED_LIB_DIRS: &[&str] = &["", "lib", "lib64", "x86_64", "x86"];
+
+/// Shared libraries the executable needs that the system doesn't have, according to `ldd`.
+/// Libraries bundled next to the executable count as found. `None` when `ldd` isn't available
+/// or the file isn't a native binary.
+pub fn missing_libraries(executable: &Path) -> Option<Vec<String>> {
+    elf_machine(executable)?;
+    find_in_path("ldd")?;
+
+    let exe_dir = executable.parent()?;
+    let mut lib_path = BUNDLED_LIB_DIRS.iter().map(|d| exe_dir.join(d)).filter(|d| d.is_dir()).collect::<Vec<_>>();
+    if let Some(existing) = std::env::var_os("LD_LIBRARY_PATH") {
+        lib_path.extend(std::env::split_paths(&existing));
+    }
+    let output = Command::new("ldd")
+        .arg(executable)
+        .env("LD_LIBRARY_PATH", std::env::join_paths(lib_path).ok()?)
+        .output()
+        .ok()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let missing = stdout.lines()
+        .filter(|line| line.contains("=> not found"))
+        .filter_map(|line| line.split_whitespace().next().map(String::from))
+        .collect();
+    Some(missing)
+}
+
+/// Reports shared libraries the game needs but the system lacks, with the package that usually
+/// provides each. Diagnostics only: the install goes ahead either way.
+pub fn check_dependencies(executable: &Path) {
+    let Some(missing) = missing_libraries(executable) else {
+        report::step(format!("Skipping the dependency check: {:?} is not a native binary, or ldd is not installed", executable.file_name().unwrap_or_default()));
+        return;
+    };
+    if missing.is_empty() {
+        report::success("All shared libraries found");
+        return;
+    }
+
+    let mut message = format!("{} shared librar{} not found; the game may fail to start:", missing.len(), if missing.len() == 1 { "y" } else { "ies" });
+    for library in &missing {
+        match LIBRARY_PACKAGES.iter().find(|(prefix, _)| library.starts_with(prefix)) {
+            Some((_, package)) => message.push_str(&format!("\n  {}: {}", library, package)),
+            None => message.push_str(&format!("\n  {}", library)),
+        }
+    }
+    message.push_str("\nHint: Package names are given as Debian/Ubuntu / Fedora / Arch. 32-bit games need the 32-bit (i386) versions.");
+    report::warning(message);
+}
+
+pub fn is_pe_binary(path: &Path) -> bool {
+    use std::io::Read;
+    let mut file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => return false,
+    };
+    let mut buffer = [0u8; 2];
+    if file.read_exact(&mut buffer).is_err() {
+        return false;
+    }
+    buffer == *b"MZ"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn scope(depth: usize) -> SearchScope<'static> {
+        SearchScope { depth, skip_dirs: &[], launcher_names: &[], launcher_extensions: &[] }
+    }
+
+    /// A file that passes as an x86_64 Linux binary.
+    fn write_elf(dir: &TempDir, relative: &str) -> PathBuf {
+        let mut header = vec![0x7F, b'E', b'L', b'F', 2, 1, 1];
+        header.resize(18, 0);
+        header.extend(0x3Eu16.to_le_bytes());
+        dir.write(relative, header)
+    }
+
+    #[test]
+    fn embedded_icon_never_runs_an_appimage_unasked() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new();
+        let marker = dir.path().join("ran");
+        let appimage = dir.write("Game.AppImage", format!("#!/bin/sh\ntouch {:?}\n", marker));
+        fs::set_permissions(&appimage, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(extract_embedded_icon(&appimage, dir.path(), false), None);
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn deep_binaries_need_a_higher_depth() {
+        let dir = TempDir::new();
+        let binary = write_elf(&dir, "data/app/bin/game");
+        assert!(matches!(discover_executable(dir.path(), false, scope(3), &[]), Err(SpawnError::NoExecutable(_))));
+        assert_eq!(discover_executable(dir.path(), false, scope(4), &[]).unwrap(), binary);
+    }
+
+    #[test]
+    fn skipped_folders_are_never_descended() {
+        let dir = TempDir::new();
+        write_elf(&dir, "lib/helper");
+        write_elf(&dir, "Game_Data/Plugins/crash");
+        let game = write_elf(&dir, "bin/game");
+        let skip_dirs = ["lib".to_string(), "*_Data".to_string()];
+        let scope = SearchScope { skip_dirs: &skip_dirs, ..scope(5) };
+
+        let visited = scope.walk(dir.path()).map(|e| e.into_path()).collect::<Vec<_>>();
+        assert!(visited.iter().all(|p| !p.starts_with(dir.path().join("lib")) && !p.starts_with(dir.path().join("Game_Data"))), "{:?}", visited);
+        assert_eq!(discover_executable(dir.path(), false, scope, &[]).unwrap(), game);
+        assert!(!scope.includes(Path::new("Game_Data/Plugins/crash")));
+        assert!(scope.includes(Path::new("bin/game")));
+    }
+
+    #[test]
+    fn scope_patterns_use_the_shared_glob_matcher() {
+        let skip_dirs = ["*_Data".to_string(), "lib??".to_string()];
+        let launcher_names = ["start*.sh".to_string()];
+        let scope = SearchScope { skip_dirs: &skip_dirs, launcher_names: &launcher_names, ..scope(3) };
+        assert!(scope.skips("Game_Data") && scope.skips("LIB64"));
+        assert!(!scope.skips("lib") && !scope.skips("Data"));
+        assert!(scope.is_launcher("Start_Game.sh") && scope.is_launcher("start.sh"));
+        assert!(!scope.is_launcher("restart.sh"));
+    }
+
+    #[test]
+    fn hints_must_stay_inside_the_game() {
+        let dir = TempDir::new();
+        let game_dir = dir.path().join("Game");
+        write_elf(&dir, "Game/game.x86_64");
+        let outside = write_elf(&dir, "outside/tool");
+
+        dir.write("Game/.spawn", "executable = \"game.x86_64\"\n");
+        assert_eq!(load_spawn_hints(&game_dir).unwrap().unwrap().executable, Some(game_dir.join("game.x86_64")));
+
+        for hint in [outside.display().to_string(), "../outside/tool".to_string()] {
+            dir.write("Game/.spawn", format!("executable = {:?}\n", hint));
+            let error = load_spawn_hints(&game_dir).unwrap_err();
+            assert!(matches!(error, SpawnError::HintOutsideGame { .. }), "{}: {}", hint, error);
+        }
+    }
+
+    #[test]
+    fn bundled_launchers_cant_point_outside_the_game() {
+        let dir = TempDir::new();
+        let game_dir = dir.path().join("Game");
+        write_elf(&dir, "outside/tool");
+        dir.write("outside/icon.png", "");
+        dir.write("Game/game.desktop", "[Desktop Entry]\nName=Game\nExec=../outside/tool --fullscreen\nIcon=../../outside/icon.png\n");
+        let launcher = find_bundled_launcher(&game_dir).unwrap();
+        assert_eq!((launcher.executable, launcher.icon), (None, None));
+
+        let game = write_elf(&dir, "Game/bin/game");
+        dir.write("Game/game.desktop", "[Desktop Entry]\nName=Game\nExec=/opt/../../outside/tool\n");
+        assert_eq!(find_bundled_launcher(&game_dir).unwrap().executable, None);
+        dir.write("Game/game.desktop", "[Desktop Entry]\nName=Game\nExec=/opt/game/bin/game\n");
+        assert_eq!(find_bundled_launcher(&game_dir).unwrap().executable, Some(game));
+    }
+
+    #[test]
+    fn finds_the_unity_player() {
+        let dir = TempDir::new();
+        write_elf(&dir, "tool");
+        write_elf(&dir, "UnityCrashHandler64");
+        let game = write_elf(&dir, "Game.x86_64");
+        dir.write("Game_Data/globalgamemanagers", "");
+        dir.write("UnityPlayer.so", "");
+        assert_eq!(discover_executable(dir.path(), false, scope(3), &[]).unwrap(), game);
+    }
+
+    #[test]
+    fn finds_the_godot_binary_next_to_its_pck() {
+        let dir = TempDir::new();
+        write_elf(&dir, "tool");
+        let game = write_elf(&dir, "Game.x86_64");
+        dir.write("Game.pck", "");
+        assert_eq!(discover_executable(dir.path(), false, scope(3), &[]).unwrap(), game);
+    }
+
+    #[test]
+    fn finds_the_unreal_launcher_script() {
+        let dir = TempDir::new();
+        write_elf(&dir, "Engine/Binaries/Linux/CrashReportClient");
+        let shipping = write_elf(&dir, "MyProject/Binaries/Linux/MyProject-Linux-Shipping");
+        dir.write("install.sh", "#!/bin/sh\n");
+        dir.write("Other.sh", "#!/bin/sh\n");
+        // Without its script, the project's binary still wins over the engine's tools
+        assert_eq!(discover_executable(dir.path(), false, scope(5), &[]).unwrap(), shipping);
+
+        let script = dir.write("MyProject.sh", "#!/bin/sh\n");
+        assert_eq!(discover_executable(dir.path(), false, scope(5), &[]).unwrap(), script);
+
+        let listing = ["Engine/Binaries/Linux/CrashReportClient", "MyProject/Binaries/Linux/MyProject-Linux-Shipping", "install.sh", "Other.sh", "MyProject.sh"]
+            .map(|path| ArchiveEntry { path: Path::new("Game").join(path), is_elf: None, size: None });
+        assert_eq!(preview_executable(&listing, scope(5), &[]), Some(PathBuf::from("Game/MyProject.sh")));
+        assert_eq!(preview_executable(&listing[..4], scope(5), &[]), Some(PathBuf::from("Game/MyProject/Binaries/Linux/MyProject-Linux-Shipping")));
+    }
+
+    #[test]
+    fn a_binary_named_after_the_game_wins_over_depth_and_length() {
+        let dir = TempDir::new();
+        let monitor = write_elf(&dir, "monitor");
+        let celeste = write_elf(&dir, "bin/Celeste.x86_64");
+        assert_eq!(discover_executable(dir.path(), false, scope(3), &[]).unwrap(), monitor);
+        assert_eq!(discover_executable(dir.path(), false, scope(3), &["Celeste".to_string()]).unwrap(), celeste);
+        assert_eq!(name_match("Celeste_Linux.x86_64", &["celeste-linux".to_string()]), 0);
+        assert_eq!(name_match("CelesteEditor", &["Celeste".to_string()]), 1);
+    }
+
+    #[test]
+    fn reads_the_machine_from_elf_headers() {
+        let dir = TempDir::new();
+        let header = |class: u8, big_endian: bool, machine: u16| {
+            let mut header = vec![0x7F, b'E', b'L', b'F', class, if big_endian { 2 } else { 1 }, 1];
+            header.resize(18, 0);
+            header.extend(if big_endian { machine.to_be_bytes() } else { machine.to_le_bytes() });
+            header
+        };
+        assert_eq!(elf_machine(&dir.write("x86_64", header(2, false, 0x3E))), Some(ElfMachine::X86_64));
+        assert_eq!(elf_machine(&dir.write("aarch64", header(2, false, 0xB7))), Some(ElfMachine::Aarch64));
+        assert_eq!(elf_machine(&dir.write("x86", header(1, false, 0x03))), Some(ElfMachine::X86));
+        assert_eq!(elf_machine(&dir.write("big-endian", header(2, true, 0xB7))), Some(ElfMachine::Aarch64));
+        assert_eq!(elf_machine(&dir.write("script.sh", "#!/bin/sh\nexec ./game \"$@\"\n")), None);
+        assert_eq!(elf_machine(&dir.write("truncated", [0x7F, b'E', b'L', b'F'])), None);
+
+        assert!(ElfMachine::X86.runs_on(ElfMachine::X86_64));
+        assert!(!ElfMachine::X86_64.runs_on(ElfMachine::Aarch64));
+        assert!(!ElfMachine::Aarch64.runs_on(ElfMachine::X86_64));
+    }
+
+    #[test]
+    fn custom_launcher_patterns_win_over_binaries() {
+        let dir = TempDir::new();
+        let game = write_elf(&dir, "game");
+        let play = dir.write("play.sh", "#!/bin/sh\n");
+        let installer = dir.write("Game.run", "#!/bin/sh\n");
+        dir.write("bin/play-server.sh", "#!/bin/sh\n");
+        assert_eq!(discover_executable(dir.path(), false, scope(3), &[]).unwrap(), game);
+
+        let names = ["play*.sh".to_string()];
+        assert_eq!(discover_executable(dir.path(), false, SearchScope { launcher_names: &names, ..scope(3) }, &[]).unwrap(), play);
+        let extensions = [".RUN".to_string()];
+        assert_eq!(discover_executable(dir.path(), false, SearchScope { launcher_extensions: &extensions, ..scope(3) }, &[]).unwrap(), installer);
+    }
+
+    #[test]
+    fn icons_named_after_the_game_beat_generic_artwork() {
+        let dir = TempDir::new();
+        for name in ["menu_bg.png", "splash.png", "Celeste_banner.png", "screenshots/screenshot1.png", "Celeste.png", "data/icon.png", "readme.txt"] {
+            dir.write(name, "");
+        }
+        let names = ["Celeste".to_string()];
+        assert_eq!(discover_icon(dir.path(), scope(3), &names), Some(dir.path().join("Celeste.png")));
+        assert_eq!(discover_icon(dir.path(), scope(3), &[]), Some(dir.path().join("data/icon.png")));
+
+        let score = |name: &str| icon_score(Path::new(name), &names);
+        assert!(score("Celeste.png") > score("icon.png"));
+        assert!(score("icon.png") > score("menu_bg.png"));
+        assert!(score("menu_bg.png") > score("Celeste_banner.png"));
+        assert!(score("menu_bg.png") > score("splash.png"));
+        assert_eq!(score("readme.txt"), None);
+    }
+}
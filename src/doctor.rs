@@ -0,0 +1,127 @@
+use colored::*;
+use nix::unistd::{AccessFlags, access};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::Args;
+use spawn::config::{Config, get_config_path};
+use spawn::error::{Result, SpawnError};
+use spawn::manifest::data_dir_path;
+use spawn::platform::steam_root;
+use spawn::report::{step, success};
+use spawn::update::ping_update_server;
+use spawn::utils::find_in_path;
+
+/// Programs Spawn runs, what they're for, and whether installs commonly fail without them.
+const TOOLS: &[(&str, &str, bool)] = &[
+    ("tar", "extracting .tar.gz, .tar.xz, .tar.bz2 and .tar.zst archives", true),
+    ("unzip", "extracting .zip archives", false),
+    ("xz", "extracting .tar.xz archives", false),
+    ("zstd", "extracting .tar.zst archives", false),
+    ("git", "--update from a source checkout", false),
+    ("cargo", "--update from a source checkout", false),
+];
+
+enum Outcome {
+    Pass(String),
+    Warn(String),
+    Fail(String),
+}
+
+/// Answers `--doctor`: checks the programs, folders and network Spawn depends on, so problems
+/// show up before an install fails halfway. Fails when something would stop every install.
+pub fn run_doctor(args: &Args, config: &Config) -> Result<()> {
+    step("Checking what Spawn needs...");
+    let mut checks: Vec<(String, Outcome)> = Vec::new();
+
+    for &(tool, purpose, required) in TOOLS {
+        let outcome = match find_in_path(tool) {
+            Some(path) => Outcome::Pass(path.display().to_string()),
+            None if required => Outcome::Fail(format!("not installed; needed for {}", purpose)),
+            None => Outcome::Warn(format!("not installed; needed for {}", purpose)),
+        };
+        checks.push((tool.to_string(), outcome));
+    }
+    // 7z stands in for unrar when extracting .rar archives
+    let rar = match (find_in_path("unrar"), find_in_path("7z")) {
+        (Some(path), _) => Outcome::Pass(path.display().to_string()),
+        (None, Some(path)) => Outcome::Pass(format!("not installed, but 7z ({}) is used instead", path.display())),
+        (None, None) => Outcome::Warn("neither unrar nor 7z is installed; needed for .rar archives".to_string()),
+    };
+    checks.push(("unrar".to_string(), rar));
+
+    let userdata = steam_root().join("userdata");
+    let steam = if userdata.is_dir() {
+        Outcome::Pass(steam_root().display().to_string())
+    } else {
+        Outcome::Warn(format!("not found at {:?}; only needed for --steam", steam_root()))
+    };
+    checks.push(("Steam".to_string(), steam));
+
+    match get_config_path(args.config.as_deref()) {
+        Ok(path) => checks.push(("Config folder".to_string(), writable_dir(path.parent().unwrap_or(Path::new("."))))),
+        Err(e) => checks.push(("Config folder".to_string(), Outcome::Fail(e.to_string()))),
+    }
+    match data_dir_path() {
+        Ok(path) => checks.push(("Data folder".to_string(), writable_dir(&path))),
+        Err(e) => checks.push(("Data folder".to_string(), Outcome::Fail(e.to_string()))),
+    }
+    checks.push(("Install folder".to_string(), writable_dir(&config.install_dir)));
+    let search = if config.search_dir.is_dir() {
+        Outcome::Pass(config.search_dir.display().to_string())
+    } else {
+        Outcome::Warn(format!("{:?} does not exist; pass full paths or change it with --set-search-dir", config.search_dir))
+    };
+    checks.push(("Search folder".to_string(), search));
+
+    let timeout = Duration::from_secs(args.update_timeout.unwrap_or(config.update_timeout_secs));
+    let network = match ping_update_server(timeout) {
+        Ok(()) => Outcome::Pass("reachable".to_string()),
+        Err(e) => Outcome::Warn(format!("{}; update checks and installs from URLs may fail", e)),
+    };
+    checks.push(("Update server".to_string(), network));
+
+    let (mut warnings, mut failed) = (0, 0);
+    for (label, outcome) in &checks {
+        match outcome {
+            Outcome::Pass(detail) => eprintln!("  {} {}: {}", "✔".green(), label, detail),
+            Outcome::Warn(detail) => {
+                warnings += 1;
+                eprintln!("  {} {}: {}", "⚠".yellow(), label, detail);
+            }
+            Outcome::Fail(detail) => {
+                failed += 1;
+                eprintln!("  {} {}: {}", "✖".red(), label, detail);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(SpawnError::DoctorFailed { failed, total: checks.len() });
+    }
+    match warnings {
+        0 => success(format!("All {} checks passed", checks.len())),
+        _ => success(format!("Ready to install games ({} warning(s) for optional features)", warnings)),
+    }
+    Ok(())
+}
+
+/// A folder Spawn writes to: fine if it's writable, or if it can be created because its nearest
+/// existing parent is.
+fn writable_dir(path: &Path) -> Outcome {
+    let is_writable = |p: &Path| access(p, AccessFlags::W_OK).is_ok();
+    if path.is_dir() {
+        return match is_writable(path) {
+            true => Outcome::Pass(path.display().to_string()),
+            false => Outcome::Fail(format!("{:?} is not writable", path)),
+        };
+    }
+    if path.exists() {
+        return Outcome::Fail(format!("{:?} is a file, not a folder", path));
+    }
+    match path.ancestors().find(|p| p.exists()) {
+        Some(parent) if is_writable(parent) => Outcome::Pass(format!("{} (created on first use)", path.display())),
+        Some(parent) => Outcome::Fail(format!("{:?} does not exist and {:?} is not writable", path, parent)),
+        None => Outcome::Fail(format!("{:?} does not exist", path)),
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// The ways Spawn can fail. Messages are plain text, with a `Hint:` line where there's
+/// something to try; the command-line tool adds the colors.
+#[derive(Debug, Error)]
+pub enum SpawnError {
+    #[error("No executable found in {0:?}\nHint: This archive may not be a Linux build")]
+    NoExecutable(PathBuf),
+
+    #[error("No Windows executable found in {0:?}")]
+    NoWindowsExecutable(PathBuf),
+
+    #[error("Only a Windows executable was found: {0:?}\nHint: Install Wine or Proton, then re-run with '--compat wine' or '--compat proton'")]
+    WindowsOnly(PathBuf),
+
+    #[error("No path provided\nHint: Use 'spawn <PATH>' or 'spawn <PARTIAL_NAME>'")]
+    NoPath,
+
+    #[error("Path does not exist: {0:?}\nHint: Ensure the path is correct and accessible")]
+    PathNotFound(PathBuf),
+
+    #[error("No file or directory found matching \"{query}\" in {dir:?}")]
+    NoMatch { query: String, dir: PathBuf },
+
+    #[error("--batch needs a directory of archives, got {0:?}")]
+    BatchNeedsDir(PathBuf),
+
+    #[error("{failed} of {total} installs failed")]
+    BatchFailed { failed: usize, total: usize },
+
+    #[error("Invalid .spawn file at {path:?}: {reason}")]
+    InvalidHints { path: PathBuf, reason: String },
+
+    #[error("The .spawn file points to a missing {kind}: {path:?}\nHint: Paths in .spawn are relative to {game_dir:?}")]
+    MissingHintTarget { kind: &'static str, path: PathBuf, game_dir: PathBuf },
+
+    #[error("The .spawn file points outside the game folder: {kind} {path:?}\nHint: Paths in .spawn must stay inside {game_dir:?}")]
+    HintOutsideGame { kind: &'static str, path: PathBuf, game_dir: PathBuf },
+
+    #[error("Checksum mismatch for {file:?}\n  Expected: {expected}\n  Actual:   {actual}\nHint: The download may be corrupted or incomplete. Try downloading it again.")]
+    ChecksumMismatch { file: PathBuf, expected: String, actual: String },
+
+    #[error("Download failed: server returned HTTP {0}\nHint: Check that the link is a direct download and hasn't expired.")]
+    DownloadStatus(u16),
+
+    #[error("Download failed: {0}\nHint: Check your internet connection.")]
+    Download(String),
+
+    #[error("Installing from stdin needs --name, as there's no file name to go by\nHint: curl -L <URL> | spawn - --name \"My Game\"")]
+    StdinNeedsName,
+
+    #[error("Nothing was piped to Spawn\nHint: Pipe an archive in, e.g. 'curl -L <URL> | spawn - --name \"My Game\"'")]
+    NothingPiped,
+
+    #[error("Stdin doesn't hold an archive or AppImage Spawn recognizes\nHint: Spawn reads zip, tar.gz, tar.xz, tar.bz2, tar.zst, 7z and rar archives and AppImages")]
+    UnrecognizedStdin,
+
+    #[error("{0:?} already exists\nHint: Install it with 'spawn {0:?}', or move it out of the way first")]
+    StdinTargetExists(PathBuf),
+
+    #[error("{url} is not a PNG, JPEG or SVG image (the server sent {content_type})")]
+    NotAnImage { url: String, content_type: String },
+
+    #[error("Invalid game name {name:?}: {reason}\nHint: Pass a plain name, e.g. --name \"My Game\"")]
+    InvalidName { name: String, reason: &'static str },
+
+    #[error("The icon at {url} is larger than {limit_mib} MiB, so it wasn't downloaded\nHint: Download a smaller image and pass it with --icon")]
+    IconTooLarge { url: String, limit_mib: u64 },
+
+    #[error("Invalid file name: {0:?}")]
+    InvalidFileName(PathBuf),
+
+    #[error("Unrecognized archive format")]
+    UnknownArchive,
+
+    /// Listing or extracting an archive went wrong; `hint` is empty or starts with a newline
+    #[error("Extraction failed: {reason}{hint}")]
+    Extraction { reason: String, hint: &'static str },
+
+    #[error("{path:?} {reason}")]
+    InvalidPackage { path: PathBuf, reason: &'static str },
+
+    #[error("The package's files use {compression} compression, which Spawn can't unpack\nHint: Extract it with 'dpkg-deb -x {path:?} <folder>' and run Spawn on that folder.")]
+    UnsupportedCompression { compression: String, path: PathBuf },
+
+    #[error("{0:?} is password-protected\nHint: Pass --password <PASS>, or leave out --yes to be asked for it")]
+    PasswordRequired(PathBuf),
+
+    #[error("Wrong password for {0:?}")]
+    WrongPassword(PathBuf),
+
+    #[error("Can't copy {from:?} to {target:?}, as one is inside the other\nHint: Pass --output-dir to copy it somewhere else, or leave out --copy-dir to use the folder in place")]
+    CopyIntoItself { from: PathBuf, target: PathBuf },
+
+    #[error("Can't find your home folder, so there's nowhere to put the menu shortcut\nHint: Set $HOME or $XDG_DATA_HOME, or pass --no-desktop to install without shortcuts")]
+    NoHomeDir,
+
+    #[error("System-wide installs need write access to {0:?}\nHint: Re-run the same command with sudo, e.g. 'sudo spawn --system <PATH>'")]
+    NeedsRoot(PathBuf),
+
+    #[error("Could not find the config directory")]
+    NoConfigDir,
+
+    #[error("Could not find the data directory")]
+    NoDataDir,
+
+    #[error("Failed to serialize {what}: {reason}")]
+    Serialize { what: &'static str, reason: String },
+
+    #[error("The list of installed games in {path:?} is damaged: {reason}\nHint: Fix the file by hand or move it away; Spawn won't overwrite it")]
+    CorruptManifest { path: PathBuf, reason: String },
+
+    #[error("'{0}' is not installed")]
+    ToolMissing(&'static str),
+
+    #[error("{label} is not installed\nHint: Install '{program}' from your distribution's package manager")]
+    ProgramMissing { label: &'static str, program: &'static str },
+
+    #[error("Could not find a Proton installation\nHint: Install Proton through Steam or set 'proton_path' in your config")]
+    ProtonNotFound,
+
+    #[error("No installed game matches {0}\nHint: Run 'spawn --list' to see installed games")]
+    NotInstalled(String),
+
+    #[error("A game named {0} is already installed")]
+    NameTaken(String),
+
+    #[error("{0:?} already exists")]
+    AlreadyExists(PathBuf),
+
+    #[error("{failed} of {total} install checks failed\nHint: Pass --icon, or add a .spawn file naming the right executable and icon, then reinstall.")]
+    VerifyFailed { failed: usize, total: usize },
+
+    #[error("{failed} of {total} checks failed\nHint: Install the missing programs or fix the folders above, then run 'spawn --doctor' again.")]
+    DoctorFailed { failed: usize, total: usize },
+
+    #[error("Invalid launch options: {0:?}\nHint: Check for unbalanced quotes")]
+    InvalidLaunchOptions(String),
+
+    #[error("Invalid launch settings: {0}\nHint: Nothing was changed. Run 'spawn --edit' again to fix it.")]
+    InvalidLaunchSettings(String),
+
+    #[error("Invalid editor command: {0:?}")]
+    InvalidEditor(String),
+
+    #[error("Failed to start editor '{editor}': {error}\nHint: Set $EDITOR to your preferred editor, e.g. 'export EDITOR=nano'")]
+    EditorFailed { editor: String, error: std::io::Error },
+
+    #[error("Editor exited with {0}; nothing was changed")]
+    EditorExited(std::process::ExitStatus),
+
+    #[error("Failed to launch {executable:?}: {error}\nHint: Check the executable's permissions or try running it from a terminal.")]
+    LaunchFailed { executable: PathBuf, error: std::io::Error },
+
+    #[error("{name} crashed on start ({status})\nHint: Check the game's output above, or pass --launch-options to adjust how it starts.")]
+    GameCrashed { name: String, status: std::process::ExitStatus },
+
+    #[error("{0} failed")]
+    CommandFailed(&'static str),
+
+    #[error("{url} is unreachable: {reason}")]
+    Unreachable { url: &'static str, reason: String },
+
+    #[error("Failed to fetch the latest release: {0}\nHint: Check your internet connection, or download a release manually from GitHub.")]
+    ReleaseFetch(String),
+
+    #[error("No prebuilt binary for {arch}-linux in release {tag}\nHint: Build from source with 'cargo install --git https://github.com/Anayo-Anyafulu/Spawn'.")]
+    NoReleaseBinary { arch: &'static str, tag: String },
+
+    #[error("Release has no published checksum for {0}, so the download can't be verified\nHint: Pass --allow-unverified to install it anyway, or build from source with 'cargo install --git https://github.com/Anayo-Anyafulu/Spawn'.")]
+    UnverifiedRelease(String),
+
+    #[error("Release archive does not contain a 'spawn' binary")]
+    NoBinaryInRelease,
+
+    #[error("Failed to replace {exe:?}: {reason}\nHint: If Spawn is installed system-wide, re-run the update with sudo.")]
+    ReplaceFailed { exe: PathBuf, reason: String },
+
+    #[error("Failed to restore {path:?}: {error}\nHint: Your save data is safe in {stash:?}; copy it back by hand.")]
+    SaveRestore { path: PathBuf, error: std::io::Error, stash: PathBuf },
+
+    #[error("Steam userdata directory not found at {0:?}")]
+    SteamNotFound(PathBuf),
+
+    #[error("Steam user {id} not found in {dir:?}")]
+    SteamUserNotFound { id: String, dir: PathBuf },
+
+    #[error("Steam user {0} has no shortcuts.vdf\nHint: Add any non-Steam game once from Steam to create it")]
+    NoSteamShortcuts(String),
+
+    #[error("Could not find shortcuts.vdf in {0:?}")]
+    ShortcutsNotFound(PathBuf),
+
+    #[error("Failed to parse shortcuts.vdf: {0}")]
+    InvalidShortcuts(String),
+
+    #[error("Operation cancelled by user")]
+    Cancelled,
+
+    #[error("Invalid selection: {0:?}")]
+    InvalidSelection(String),
+
+    #[error("{context}: {error}")]
+    Io { context: String, error: std::io::Error },
+}
+
+pub type Result<T, E = SpawnError> = std::result::Result<T, E>;
+
+/// Says what Spawn was doing when an I/O call failed, like `anyhow::Context`.
+pub trait IoContext<T> {
+    fn context(self, context: &str) -> Result<T>;
+    fn with_context(self, context: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T> IoContext<T> for std::io::Result<T> {
+    fn context(self, context: &str) -> Result<T> {
+        self.map_err(|error| SpawnError::Io { context: context.to_string(), error })
+    }
+
+    fn with_context(self, context: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|error| SpawnError::Io { context: context(), error })
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Local};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::{IoContext, Result};
+use crate::manifest::data_dir_path;
+
+/// One line of the install history.
+pub struct HistoryRecord {
+    pub time: DateTime<Local>,
+    pub name: String,
+    pub source: PathBuf,
+    pub install_dir: PathBuf,
+    pub steam: bool,
+}
+
+/// The history log, next to the manifest: per user, or shared for `--system`.
+pub fn get_history_path() -> Result<PathBuf> {
+    let data_dir = data_dir_path()?;
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).with_context(|| format!("Failed to create {:?}", data_dir))?;
+    }
+    Ok(data_dir.join("history.log"))
+}
+
+/// Appends an install to the history log. The log is tab-separated so it stays
+/// readable with `cat` and is never rewritten or pruned.
+pub fn append_history(name: &str, source: &Path, install_dir: &Path, steam: bool) -> Result<()> {
+    let line = format!(
+        "{}\t{}\t{}\t{}\tsteam={}\n",
+        Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        name,
+        source.display(),
+        install_dir.display(),
+        if steam { "yes" } else { "no" }
+    );
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_history_path()?)
+        .context("Failed to open history log")?;
+    file.write_all(line.as_bytes()).context("Failed to write history log")
+}
+
+pub fn load_history() -> Vec<HistoryRecord> {
+    let Ok(content) = get_history_path().and_then(|p| fs::read_to_string(p).context("Failed to read history log")) else {
+        return Vec::new();
+    };
+
+    content.lines().filter_map(|line| {
+        let mut fields = line.split('\t');
+        Some(HistoryRecord {
+            time: DateTime::parse_from_rfc3339(fields.next()?).ok()?.with_timezone(&Local),
+            name: fields.next()?.to_string(),
+            source: PathBuf::from(fields.next()?),
+            install_dir: PathBuf::from(fields.next()?),
+            steam: fields.next() == Some("steam=yes"),
+        })
+    }).collect()
+}
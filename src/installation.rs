@@ -1,142 +1,1684 @@
-use anyhow::{Context, Result, anyhow};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Duration;
-use indicatif::{ProgressBar, ProgressStyle};
-use colored::*;
+use clap::ValueEnum;
+use flate2::read::GzDecoder;
+use xz2::read::XzDecoder;
+use bzip2::read::BzDecoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 
-pub fn extract_archive(archive_path: &Path, install_dir: &Path, dry_run: bool) -> Result<PathBuf> {
-    let stem = archive_path.file_stem().ok_or_else(|| anyhow!("Invalid file name"))?;
+use crate::compat::CompatTool;
+use crate::config::{Config, OverwritePolicy};
+use crate::discovery::{LaunchTarget, discover_launch_target};
+use crate::error::{IoContext, Result, SpawnError};
+use crate::report::{self, Task};
+use crate::utils::matches_glob;
+use crate::zip;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveKind {
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "tar.xz")]
+    TarXz,
+    #[serde(rename = "tar.bz2")]
+    TarBz2,
+    #[serde(rename = "tar.zst")]
+    TarZst,
+    #[serde(rename = "7z")]
+    SevenZip,
+    Rar,
+    Unknown,
+}
+
+impl ArchiveKind {
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") || bytes.starts_with(b"PK\x07\x08") {
+            ArchiveKind::Zip
+        } else if bytes.starts_with(&[0x1F, 0x8B]) {
+            ArchiveKind::TarGz
+        } else if bytes.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+            ArchiveKind::TarXz
+        } else if bytes.starts_with(b"BZh") {
+            ArchiveKind::TarBz2
+        } else if bytes.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            ArchiveKind::TarZst
+        } else if bytes.starts_with(&[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C]) {
+            ArchiveKind::SevenZip
+        } else if bytes.starts_with(b"Rar!\x1A\x07") {
+            ArchiveKind::Rar
+        } else {
+            ArchiveKind::Unknown
+        }
+    }
+
+    /// The file ending an archive of this kind is usually saved with.
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            ArchiveKind::Zip => Some(".zip"),
+            ArchiveKind::TarGz => Some(".tar.gz"),
+            ArchiveKind::TarXz => Some(".tar.xz"),
+            ArchiveKind::TarBz2 => Some(".tar.bz2"),
+            ArchiveKind::TarZst => Some(".tar.zst"),
+            ArchiveKind::SevenZip => Some(".7z"),
+            ArchiveKind::Rar => Some(".rar"),
+            ArchiveKind::Unknown => None,
+        }
+    }
+
+    pub fn from_extension(path: &Path) -> Self {
+        let name = path.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            ArchiveKind::Zip
+        } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+            ArchiveKind::TarGz
+        } else if name.ends_with(".xz") || name.ends_with(".txz") {
+            ArchiveKind::TarXz
+        } else if name.ends_with(".bz2") || name.ends_with(".tbz2") {
+            ArchiveKind::TarBz2
+        } else if name.ends_with(".zst") || name.ends_with(".tzst") {
+            ArchiveKind::TarZst
+        } else if name.ends_with(".7z") {
+            ArchiveKind::SevenZip
+        } else if name.ends_with(".rar") {
+            ArchiveKind::Rar
+        } else {
+            ArchiveKind::Unknown
+        }
+    }
+}
+
+/// Identifies an archive by its leading bytes, falling back to the extension
+/// only when the header is inconclusive (e.g. an uncompressed tar).
+pub fn detect_archive_kind(path: &Path) -> ArchiveKind {
+    let mut buffer = Vec::with_capacity(8);
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.take(8).read_to_end(&mut buffer);
+    }
+
+    match ArchiveKind::from_magic(&buffer) {
+        ArchiveKind::Unknown => ArchiveKind::from_extension(path),
+        kind => kind,
+    }
+}
+
+/// Returns the expected SHA-256 for an archive: the explicit value if given,
+/// otherwise the first field of a sibling `<archive>.sha256` file.
+pub fn expected_checksum(archive_path: &Path, explicit: Option<&str>) -> Option<String> {
+    if let Some(hash) = explicit {
+        return Some(hash.trim().to_lowercase());
+    }
+
+    let mut sidecar = archive_path.as_os_str().to_os_string();
+    sidecar.push(".sha256");
+    let content = fs::read_to_string(sidecar).ok()?;
+    content.split_whitespace().next().map(|h| h.to_lowercase())
+}
+
+/// SHA-256 of a file, without any progress output.
+pub fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).context("Failed to open archive for checksum")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Failed to read archive for checksum")?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn verify_checksum(archive_path: &Path, expected: &str) -> Result<()> {
+    let file = fs::File::open(archive_path).context("Failed to open archive for checksum")?;
+    let total = file.metadata().map(|m| m.len()).ok();
+
+    let task = Task::start("Verifying checksum...", total);
+    let mut hasher = Sha256::new();
+    let result = std::io::copy(&mut task.wrap_read(file), &mut hasher);
+    drop(task);
+    result.context("Failed to read archive for checksum")?;
+
+    let actual = format!("{:x}", hasher.finalize());
+    if actual != expected {
+        let file = PathBuf::from(archive_path.file_name().unwrap_or_default());
+        return Err(SpawnError::ChecksumMismatch { file, expected: expected.to_string(), actual });
+    }
+
+    report::success("Checksum verified (SHA-256)");
+    Ok(())
+}
+
+/// True when the install argument is an `http(s)://` URL rather than a local path.
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// The path argument that reads the archive from stdin, e.g. `curl -L <URL> | spawn - --name Game`.
+pub const STDIN_INPUT: &str = "-";
+
+/// Saves an archive or AppImage piped to Spawn into `download_dir` as `<file_stem>` plus the
+/// extension its leading bytes call for, since the rest of the install goes by the file name.
+pub fn save_stdin(download_dir: &Path, file_stem: &str) -> Result<PathBuf> {
+    use std::io::IsTerminal;
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err(SpawnError::NothingPiped);
+    }
+
+    fs::create_dir_all(download_dir).context("Failed to create download directory")?;
+    let partial = download_dir.join(format!(".{}.stdin.part", file_stem));
+    let result = (|| -> Result<PathBuf> {
+        let mut writer = fs::File::create(&partial).context("Failed to create file for stdin")?;
+        let task = Task::start("Reading archive from stdin...", None);
+        let copied = std::io::copy(&mut task.wrap_read(stdin.lock()), &mut writer).context("Failed to read stdin");
+        drop(task);
+        if copied? == 0 {
+            return Err(SpawnError::NothingPiped);
+        }
+        writer.sync_all().context("Failed to flush file read from stdin")?;
+
+        let mut head = Vec::with_capacity(12);
+        fs::File::open(&partial).and_then(|f| f.take(12).read_to_end(&mut head)).context("Failed to read file from stdin")?;
+        let is_appimage = head.starts_with(b"\x7FELF") && head.get(8..11) == Some(b"AI\x02");
+        let extension = if is_appimage { ".AppImage" } else { ArchiveKind::from_magic(&head).extension().ok_or(SpawnError::UnrecognizedStdin)? };
+
+        let target = download_dir.join(format!("{}{}", file_stem, extension));
+        if target.exists() {
+            return Err(SpawnError::StdinTargetExists(target));
+        }
+        fs::rename(&partial, &target).context("Failed to move file read from stdin into place")?;
+        Ok(target)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    let target = result?;
+    report::success(format!("Saved stdin as {:?}", target));
+    Ok(target)
+}
+
+/// File name to save a download under: the last URL path segment, without any query string.
+pub fn download_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or("").replace("%20", " ");
+    if name.is_empty() { "download".to_string() } else { name }
+}
+
+/// Streams `url` into `download_dir`. Returns the file and whether Spawn created it
+/// (an already-present file with the same name is reused and must not be cleaned up).
+pub fn download_game(url: &str, download_dir: &Path, dry_run: bool) -> Result<Option<(PathBuf, bool)>> {
+    let target = download_dir.join(download_file_name(url));
+
+    if target.exists() {
+        report::success(format!("Using existing download: {:?}", target));
+        return Ok(Some((target, false)));
+    }
+
+    if dry_run {
+        report::step(format!("Would download {} to {:?}", url, target));
+        return Ok(None);
+    }
+
+    fs::create_dir_all(download_dir).context("Failed to create download directory")?;
+    report::step(format!("Downloading {}", url));
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(10))
+        .user_agent(concat!("spawn/", env!("CARGO_PKG_VERSION")))
+        .build();
+    let response = agent.get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, _) => SpawnError::DownloadStatus(code),
+        e => SpawnError::Download(e.to_string()),
+    })?;
+    let total = response.header("Content-Length").and_then(|l| l.parse().ok());
+
+    let mut partial = target.as_os_str().to_os_string();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let task = Task::start("Downloading...", total);
+    let result = (|| -> Result<()> {
+        let mut writer = fs::File::create(&partial).context("Failed to create download file")?;
+        std::io::copy(&mut task.wrap_read(response.into_reader()), &mut writer).context("Failed to download file")?;
+        writer.sync_all().context("Failed to flush downloaded file")?;
+        fs::rename(&partial, &target).context("Failed to move download into place")
+    })();
+    drop(task);
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result?;
+
+    report::success(format!("Downloaded {:?}", target.file_name().unwrap_or_default()));
+    Ok(Some((target, true)))
+}
+
+/// The folder an archive or AppImage is installed into: its name without the archive
+/// extensions or volume suffix.
+pub fn install_target(path: &Path, install_dir: &Path) -> Result<PathBuf> {
+    // A folder copied with --copy-dir keeps its own name
+    if path.is_dir() {
+        let name = path.file_name().ok_or_else(|| SpawnError::InvalidFileName(path.to_path_buf()))?;
+        return Ok(install_dir.join(name));
+    }
+    let path = &first_volume(path);
+    let stem = path.file_stem().ok_or_else(|| SpawnError::InvalidFileName(path.to_path_buf()))?;
     let stem_str = stem.to_string_lossy();
-    
+
     let dir_name = if stem_str.ends_with(".tar") {
-        Path::new(stem_str.as_ref()).file_stem().ok_or_else(|| anyhow!("Invalid tar archive name"))?
+        Path::new(stem_str.as_ref()).file_stem().ok_or_else(|| SpawnError::InvalidFileName(path.to_path_buf()))?.to_os_string()
+    } else if let Some(base) = stem_str.strip_suffix(".x86_64").or_else(|| stem_str.strip_suffix(".x86"))
+        && single_compressed_file(path, detect_archive_kind(path)).is_some()
+    {
+        // A lone compressed binary, e.g. `game.x86_64.gz`, is named after the game rather than its architecture
+        base.into()
+    } else if let Some(volume) = Volume::parse(path) {
+        volume.base.into()
     } else {
-        stem
+        stem.to_os_string()
     };
-    
-    let target_dir = install_dir.join(dir_name);
+    Ok(install_dir.join(dir_name))
+}
+
+/// How [`install_archive`] treats the disk and the user.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstallOptions<'a> {
+    /// Report what would happen without writing anything
+    pub dry_run: bool,
+    /// Ask questions through the reporter; when `false` the default answer is used
+    pub interactive: bool,
+    /// Set for a reinstall: an existing install is replaced without asking and its save data,
+    /// plus paths matching these patterns, is carried over
+    pub preserve: Option<&'a [String]>,
+    /// Link an AppImage where it is instead of copying it; archives are always extracted
+    pub symlink: bool,
+    /// What to do when the install folder already exists
+    pub overwrite: OverwritePolicy,
+    /// Name for the install folder; by default it's named after the archive
+    pub dir_name: Option<&'a str>,
+    /// Password for an encrypted zip; asked for when interactive and missing
+    pub password: Option<&'a str>,
+    /// Move an AppImage into the install folder instead of copying it
+    pub move_appimage: bool,
+    /// What the user calls the game, so a binary with the same name is preferred
+    pub name: Option<&'a str>,
+    /// Launch through this compatibility layer; otherwise one is offered for Windows builds
+    pub compat: Option<CompatTool>,
+}
+
+/// Extracts an archive into its own folder under `install_dir`.
+pub fn extract_archive(archive_path: &Path, install_dir: &Path, options: &InstallOptions) -> Result<PathBuf> {
+    let InstallOptions { dry_run, preserve, .. } = *options;
+    let archive_path = &first_volume(archive_path);
+    let target_dir = match options.dir_name {
+        Some(name) => install_dir.join(name),
+        None => install_target(archive_path, install_dir)?,
+    };
+    let dir_name = target_dir.file_name().unwrap_or_default().to_os_string();
+
+    // Asked before anything is removed, so a missing password doesn't cost the old install
+    let mut password = options.password.map(str::to_string);
+    if password.is_none() && !dry_run && detect_archive_kind(archive_path) == ArchiveKind::Zip && zip::is_encrypted(archive_path) {
+        if !options.interactive {
+            return Err(SpawnError::PasswordRequired(archive_path.to_path_buf()));
+        }
+        report::step(format!("{:?} is password-protected", archive_path.file_name().unwrap_or_default()));
+        password = Some(report::password("Password:")?.ok_or_else(|| SpawnError::PasswordRequired(archive_path.to_path_buf()))?);
+    }
+
+    let mut stash = None;
     if target_dir.exists() {
-        println!("{} {:?} is already installed.", "⚠".yellow().bold(), dir_name);
-        println!("  Do you want to overwrite it? [y/N]");
-        
-        let mut confirm = String::new();
-        std::io::stdin().read_line(&mut confirm).context("Failed to read input")?;
-        if confirm.trim().to_lowercase() != "y" {
-            println!("{} Using existing directory.", "✔".green());
+        report::warning(format!("{:?} is already installed.", dir_name));
+        if let Some(patterns) = preserve {
+            stash = SaveStash::collect(&target_dir, patterns, dry_run)?;
+        } else if !confirm_overwrite(options.overwrite, options.interactive)? {
+            report::success("Using existing directory.");
             return Ok(flatten_if_needed(target_dir));
         }
+        remove_existing_install(&target_dir, dry_run)?;
+    }
 
-        if !dry_run {
-            fs::remove_dir_all(&target_dir).context("Failed to remove existing directory")?;
-        } else {
-            println!("{} Would remove existing directory", "▶".cyan());
+    if dry_run {
+        report::step(format!("Would extract {:?} to {:?}", archive_path, target_dir));
+        return Ok(target_dir);
+    }
+
+    // Saves are put back even when extraction fails, so a bad download never costs progress
+    let result = fs::create_dir_all(&target_dir)
+        .context("Failed to create extraction directory")
+        .and_then(|_| unpack_archive(archive_path, &target_dir, password.as_deref()));
+    // Nothing usable was unpacked, so don't leave a folder that looks like an install
+    if matches!(result, Err(SpawnError::WrongPassword(_))) && stash.is_none() {
+        let _ = fs::remove_dir_all(&target_dir);
+    }
+    if let Some(stash) = stash {
+        stash.restore(&target_dir)?;
+    }
+    result?;
+
+    report::success("Extracted game files");
+
+    Ok(flatten_if_needed(target_dir))
+}
+
+fn unpack_archive(archive_path: &Path, target_dir: &Path, password: Option<&str>) -> Result<()> {
+    report::step(format!("Extracting {:?}...", archive_path.file_name().unwrap_or_default()));
+
+    let kind = detect_archive_kind(archive_path);
+    log::debug!("Archive kind for {:?}: {:?}", archive_path, kind);
+
+    if matches!(kind, ArchiveKind::Zip | ArchiveKind::SevenZip | ArchiveKind::Rar) {
+        let task = Task::start("Extracting files...", None);
+        let result = match kind {
+            // Decrypted here rather than by `unzip -P`, which would show the password to anyone running `ps`
+            ArchiveKind::Zip => match password {
+                Some(password) => zip::extract_with_password(archive_path, target_dir, password),
+                None => run_unzip(archive_path, target_dir),
+            },
+            ArchiveKind::SevenZip => open_7z(archive_path)
+                .and_then(|reader| sevenz_rust::decompress(reader, target_dir).map_err(seven_zip_error)),
+            _ => run_unrar(archive_path, target_dir),
+        };
+        drop(task);
+        result?;
+    } else {
+        let total = fs::metadata(archive_path).map(|m| m.len()).ok();
+        let task = Task::start("Extracting files...", total);
+        let result = match single_compressed_file(archive_path, kind) {
+            Some(name) => unpack_single_file(archive_path, kind, &target_dir.join(name), &task),
+            None => extract_tar_native(archive_path, kind, target_dir, &task).or_else(|e| {
+                report::warning(format!("Native extraction failed ({}), falling back to system tar", e));
+                run_tar(archive_path, kind, target_dir)
+            }),
+        };
+        drop(task);
+        result?;
+    }
+    Ok(())
+}
+
+/// Decompresses a tarball in-process, so extraction works on systems without `tar`, `xz`, `bzip2`
+/// or a `tar` built with zstd support.
+fn tar_decoder(reader: impl Read + 'static, kind: ArchiveKind) -> std::io::Result<Box<dyn Read>> {
+    Ok(match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(reader)),
+        ArchiveKind::TarXz => Box::new(XzDecoder::new(reader)),
+        ArchiveKind::TarBz2 => Box::new(BzDecoder::new(reader)),
+        ArchiveKind::TarZst => Box::new(ZstdDecoder::new(reader)?),
+        _ => Box::new(reader),
+    })
+}
+
+fn extract_tar_native(archive_path: &Path, kind: ArchiveKind, target_dir: &Path, task: &Task) -> Result<()> {
+    let file = fs::File::open(archive_path).context("Failed to open archive")?;
+    let decoder = tar_decoder(task.wrap_read(file), kind).context("Failed to start decompressing")?;
+    tar::Archive::new(decoder).unpack(target_dir).context("Failed to unpack tar archive")
+}
+
+/// The file name a lone compressed file such as `game.x86_64.gz` unpacks to, or `None` for a
+/// compressed tarball. The stream decides, not the name: a tar starts with a header that has
+/// `ustar` at byte 257.
+fn single_compressed_file(archive_path: &Path, kind: ArchiveKind) -> Option<PathBuf> {
+    if !matches!(kind, ArchiveKind::TarGz | ArchiveKind::TarXz | ArchiveKind::TarBz2 | ArchiveKind::TarZst) {
+        return None;
+    }
+    let file = fs::File::open(archive_path).ok()?;
+    let mut header = Vec::with_capacity(512);
+    tar_decoder(file, kind).ok()?.take(512).read_to_end(&mut header).ok()?;
+    if header.get(257..262) == Some(b"ustar".as_slice()) {
+        return None;
+    }
+    archive_path.file_stem().map(PathBuf::from)
+}
+
+/// Decompresses a lone file to `target`, marking it executable since it's almost always the game itself.
+fn unpack_single_file(archive_path: &Path, kind: ArchiveKind, target: &Path, task: &Task) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    log::debug!("{:?} holds a single compressed file, unpacking it to {:?}", archive_path, target);
+    let file = fs::File::open(archive_path).context("Failed to open archive")?;
+    let mut decoder = tar_decoder(task.wrap_read(file), kind).context("Failed to start decompressing")?;
+    let mut output = fs::File::create(target).context("Failed to create the unpacked file")?;
+    std::io::copy(&mut decoder, &mut output).context("Failed to decompress the file")?;
+    fs::set_permissions(target, fs::Permissions::from_mode(0o755)).context("Failed to set executable permissions")
+}
+
+/// A package that turned out to be the whole content of an extracted archive.
+enum NestedPackage {
+    Deb(PathBuf),
+    AppImage(PathBuf),
+}
+
+/// Files that ship next to a package without being part of the game.
+fn is_documentation(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+    ["readme", "license", "licence", "changelog"].iter().any(|p| name.starts_with(p))
+        || [".txt", ".md", ".pdf", ".html"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// Finds a `.deb` or AppImage that is the only real file in an extracted archive.
+fn find_nested_package(game_dir: &Path, depth: usize) -> Option<NestedPackage> {
+    let mut files = walkdir::WalkDir::new(game_dir).max_depth(depth).into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && !is_documentation(e.path()));
+
+    let (Some(file), None) = (files.next(), files.next()) else { return None };
+    let name = file.file_name().to_string_lossy().to_lowercase();
+    if name.ends_with(".deb") {
+        Some(NestedPackage::Deb(file.into_path()))
+    } else if name.ends_with(".appimage") {
+        Some(NestedPackage::AppImage(file.into_path()))
+    } else {
+        None
+    }
+}
+
+/// Handles archives that only wrap another package: a `.deb` is unpacked into the game folder
+/// (nothing is installed system-wide) and a lone AppImage is installed as if it had been
+/// downloaded directly. Returns the folder the game now runs from.
+pub fn unwrap_nested_package(game_dir: PathBuf, install_dir: &Path, depth: usize, options: &InstallOptions) -> Result<PathBuf> {
+    let interactive = options.interactive;
+    match find_nested_package(&game_dir, depth) {
+        Some(NestedPackage::Deb(deb)) => {
+            report::step(format!("This archive contains a Debian package: {:?}", deb.file_name().unwrap_or_default()));
+            if interactive && !report::confirm("Unpack its files into the game folder? Nothing is installed system-wide.", true)? {
+                return Ok(game_dir);
+            }
+
+            unpack_deb(&deb, &game_dir)?;
+            fs::remove_file(&deb).context("Failed to remove unpacked .deb")?;
+            report::success(format!("Unpacked the package's files into {:?}", game_dir));
+            Ok(deb_game_dir(game_dir))
+        }
+        Some(NestedPackage::AppImage(appimage)) => {
+            report::step(format!("This archive only wraps an AppImage: {:?}", appimage.file_name().unwrap_or_default()));
+
+            // Move it out before the extracted folder goes, since it may share the AppImage's name
+            let extracted_root = game_dir.strip_prefix(install_dir).ok()
+                .and_then(|rel| rel.components().next())
+                .map(|c| install_dir.join(c))
+                .unwrap_or(game_dir);
+            let staging_dir = install_dir.join(".spawn-nested");
+            let staged = staging_dir.join(appimage.file_name().unwrap_or_default());
+            fs::create_dir_all(&staging_dir).context("Failed to create staging directory")?;
+            fs::rename(&appimage, &staged).context("Failed to move nested AppImage")?;
+            fs::remove_dir_all(&extracted_root).context("Failed to remove extracted folder")?;
+
+            let options = InstallOptions { dry_run: false, preserve: None, symlink: false, ..*options };
+            let result = install_appimage(&staged, install_dir, &options);
+            let _ = fs::remove_dir_all(&staging_dir);
+            result
         }
+        None => Ok(game_dir),
     }
+}
+
+/// Where an installed archive or AppImage ended up.
+#[derive(Debug, Clone)]
+pub struct InstalledArchive {
+    /// The folder the game runs from
+    pub game_dir: PathBuf,
+    /// The folder Spawn created under the install directory, which `game_dir` may be nested in
+    pub install_root: Option<PathBuf>,
+    /// What to run; `None` in a dry run, as there's nothing on disk to search
+    pub launch: Option<LaunchTarget>,
+}
 
-    if !dry_run {
-        fs::create_dir_all(&target_dir).context("Failed to create extraction directory")?;
+/// Installs an archive, AppImage or (for `--copy-dir`) a game folder into its own folder under `install_dir`, creating the folder
+/// and unwrapping packages nested inside the archive, then finds the executable and icon to use.
+/// In a dry run nothing is written and `game_dir` is where the game would go.
+pub fn install_archive(path: &Path, install_dir: &Path, config: &Config, options: &InstallOptions) -> Result<InstalledArchive> {
+    let dry_run = options.dry_run;
+    if !dry_run && !install_dir.exists() {
+        fs::create_dir_all(install_dir).context("Failed to create install directory")?;
     }
 
+    let game_dir = if path.is_dir() {
+        copy_game_dir(path, install_dir, options)?
+    } else if path.to_string_lossy().ends_with(".AppImage") {
+        install_appimage(path, install_dir, options)?
+    } else {
+        let game_dir = extract_archive(path, install_dir, options)?;
+        if dry_run {
+            game_dir
+        } else {
+            unwrap_nested_package(game_dir, install_dir, config.discovery_depth, options)?
+        }
+    };
+
+    let install_root = game_dir.strip_prefix(install_dir).ok()
+        .and_then(|rel| rel.components().next())
+        .map(|c| install_dir.join(c));
     if dry_run {
-        println!("{} Would extract {:?} to {:?}", "▶".cyan(), archive_path, target_dir);
-        return Ok(target_dir);
+        return Ok(InstalledArchive { game_dir, install_root, launch: None });
     }
 
-    println!("{} Extracting {:?}...", "▶".cyan(), archive_path.file_name().unwrap_or_default());
-    
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(ProgressStyle::default_spinner()
-        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈")
-        .template("{spinner:.cyan} {msg}")?);
-    pb.set_message("Extracting files...");
-    pb.enable_steady_tick(Duration::from_millis(100));
-
-    let is_zip = archive_path.to_string_lossy().ends_with(".zip");
-    
-    let status = if is_zip {
-        Command::new("unzip")
-            .arg("-q")
-            .arg(archive_path)
-            .arg("-d")
-            .arg(&target_dir)
-            .status()
-            .context("Failed to execute unzip command. Hint: Ensure 'unzip' is installed.")?
-    } else {
-        Command::new("tar")
-            .arg("-xf")
+    let mut expected_names = options.name.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    expected_names.extend([install_root.as_deref(), Some(game_dir.as_path())].into_iter()
+        .flatten()
+        .filter_map(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned())));
+    let launch = discover_launch_target(&game_dir, options.compat, config, options.interactive, &expected_names)?;
+    Ok(InstalledArchive { game_dir, install_root, launch: Some(launch) })
+}
+
+/// Extracts the `data.tar` member of a `.deb` (an `ar` archive) into `target_dir`.
+fn unpack_deb(deb: &Path, target_dir: &Path) -> Result<()> {
+    let mut file = std::io::BufReader::new(fs::File::open(deb).context("Failed to open .deb")?);
+    let mut magic = [0u8; 8];
+    if file.read_exact(&mut magic).is_err() || &magic != b"!<arch>\n" {
+        return Err(SpawnError::InvalidPackage { path: deb.to_path_buf(), reason: "is not a valid Debian package" });
+    }
+
+    let mut header = [0u8; 60];
+    while file.read_exact(&mut header).is_ok() {
+        let name = String::from_utf8_lossy(&header[..16]).trim_end().trim_end_matches('/').to_string();
+        let size: u64 = String::from_utf8_lossy(&header[48..58]).trim().parse()
+            .map_err(|_| SpawnError::InvalidPackage { path: deb.to_path_buf(), reason: "has a corrupted header" })?;
+
+        if let Some(compression) = name.strip_prefix("data.tar") {
+            let kind = match compression {
+                ".gz" => ArchiveKind::TarGz,
+                ".xz" => ArchiveKind::TarXz,
+                ".bz2" => ArchiveKind::TarBz2,
+                ".zst" => ArchiveKind::TarZst,
+                // An uncompressed tar passes through the decoder untouched
+                "" => ArchiveKind::Unknown,
+                other => return Err(SpawnError::UnsupportedCompression { compression: other.trim_start_matches('.').to_string(), path: deb.to_path_buf() }),
+            };
+            let decoder = tar_decoder(file.take(size), kind).context("Failed to read the package's files")?;
+            return tar::Archive::new(decoder)
+                .unpack(target_dir)
+                .context("Failed to unpack the package's files");
+        }
+
+        // Members are padded to an even length
+        std::io::copy(&mut (&mut file).take(size + size % 2), &mut std::io::sink()).context("Failed to read .deb")?;
+    }
+    Err(SpawnError::InvalidPackage { path: deb.to_path_buf(), reason: "contains no files to unpack" })
+}
+
+/// Packaged games usually live in a single folder under `/opt`; run from there when they do.
+fn deb_game_dir(root: PathBuf) -> PathBuf {
+    let entries = fs::read_dir(root.join("opt"))
+        .map(|e| e.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [only] if only.is_dir() => only.clone(),
+        _ => root,
+    }
+}
+
+/// A file inside an archive, listed without extracting it.
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    /// Whether the file starts with the ELF magic, when the listing can read file contents
+    pub is_elf: Option<bool>,
+    /// Unpacked size in bytes, when the listing reports it
+    pub size: Option<u64>,
+}
+
+/// Lists the files inside an archive without extracting anything, for `--dry-run`.
+pub fn list_archive(archive_path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let archive_path = &first_volume(archive_path);
+    match detect_archive_kind(archive_path) {
+        ArchiveKind::Zip => list_with_command("unzip", "-Z1", archive_path),
+        ArchiveKind::Rar => list_with_command("unrar", "lb", archive_path),
+        ArchiveKind::SevenZip => {
+            let mut reader = open_7z(archive_path)?;
+            let len = reader.get_ref().len;
+            let archive = sevenz_rust::Archive::read(&mut reader, len, &[]).map_err(seven_zip_error)?;
+            Ok(archive.files.iter()
+                .filter(|f| !f.is_directory)
+                .map(|f| ArchiveEntry { path: PathBuf::from(&f.name), is_elf: None, size: Some(f.size) })
+                .collect())
+        }
+        ArchiveKind::Unknown => Err(SpawnError::UnknownArchive),
+        kind => match single_compressed_file(archive_path, kind) {
+            Some(name) => Ok(vec![ArchiveEntry { path: name, is_elf: None, size: None }]),
+            None => list_tar(archive_path, kind),
+        },
+    }
+}
+
+fn list_tar(archive_path: &Path, kind: ArchiveKind) -> Result<Vec<ArchiveEntry>> {
+    let file = fs::File::open(archive_path).context("Failed to open archive")?;
+    let mut archive = tar::Archive::new(tar_decoder(file, kind).context("Failed to read tar archive")?);
+
+    let mut entries = Vec::new();
+    for entry in archive.entries().context("Failed to read tar archive")? {
+        let mut entry = entry.context("Failed to read tar archive")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().context("Invalid path in tar archive")?.into_owned();
+        let size = entry.header().size().ok();
+        let mut magic = [0u8; 4];
+        let is_elf = entry.read_exact(&mut magic).is_ok() && magic == [0x7F, 0x45, 0x4C, 0x46];
+        entries.push(ArchiveEntry { path, is_elf: Some(is_elf), size });
+    }
+    Ok(entries)
+}
+
+/// Roughly how much space the archive takes once unpacked. Falls back to the archive's own
+/// size when the format doesn't say, which underestimates compressed archives.
+pub fn unpacked_size(archive_path: &Path) -> Option<u64> {
+    let archive_path = &first_volume(archive_path);
+    let packed = fs::metadata(archive_path).ok()?.len();
+    if archive_path.to_string_lossy().ends_with(".AppImage") {
+        return Some(packed);
+    }
+
+    let unpacked = match detect_archive_kind(archive_path) {
+        // The last line of `unzip -Zt` reads "N files, X bytes uncompressed, ..."
+        ArchiveKind::Zip => Command::new("unzip").arg("-Zt").arg(archive_path).output().ok()
+            .and_then(|output| {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                stdout.split(", ").find_map(|part| part.strip_suffix(" bytes uncompressed")?.trim().parse().ok())
+            }),
+        ArchiveKind::Rar | ArchiveKind::Unknown => None,
+        _ => list_archive(archive_path).ok()
+            .and_then(|entries| entries.iter().map(|e| e.size).sum()),
+    };
+    Some(unpacked.unwrap_or(packed))
+}
+
+/// Free space in bytes on the filesystem holding `path`, or the nearest existing parent
+/// for a folder that hasn't been created yet.
+pub fn free_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let stats = nix::sys::statvfs::statvfs(existing).ok()?;
+    Some(stats.blocks_available() as u64 * stats.fragment_size() as u64)
+}
+
+/// Picks the candidate with the most free space, if any has room for `needed` bytes.
+/// Returns it with its free space.
+pub fn pick_install_dir(candidates: &[PathBuf], needed: u64) -> Option<(PathBuf, u64)> {
+    candidates.iter()
+        .filter_map(|dir| free_space(dir).map(|free| (dir.clone(), free)))
+        .filter(|(_, free)| *free >= needed)
+        .max_by_key(|(_, free)| *free)
+}
+
+fn list_with_command(program: &'static str, list_arg: &str, archive_path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let output = Command::new(program)
+        .arg(list_arg)
+        .arg(archive_path)
+        .output()
+        .map_err(|_| SpawnError::ToolMissing(program))?;
+
+    if !output.status.success() {
+        return Err(SpawnError::Extraction { reason: format!("'{}' could not list the archive", program), hint: "" });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).lines()
+        .filter(|line| !line.is_empty() && !line.ends_with('/'))
+        .map(|line| ArchiveEntry { path: PathBuf::from(line), is_elf: None, size: None })
+        .collect())
+}
+
+fn run_tar(archive_path: &Path, kind: ArchiveKind, target_dir: &Path) -> Result<()> {
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(archive_path)
+        .arg("-C")
+        .arg(target_dir)
+        .status()
+        .context("Failed to execute tar command")?;
+
+    if !status.success() {
+        let hint = match kind {
+            ArchiveKind::TarXz => "\nHint: This is a .xz archive. Ensure you have 'xz-utils' or 'xz' installed.",
+            ArchiveKind::TarZst => "\nHint: This is a .zst archive. Ensure you have 'zstd' installed and a tar that supports it (GNU tar 1.31+).",
+            _ => "\nHint: Ensure tar is installed and the archive is valid.",
+        };
+        return Err(SpawnError::Extraction { reason: format!("exit code {:?}", status.code()), hint });
+    }
+    Ok(())
+}
+
+fn run_unzip(archive_path: &Path, target_dir: &Path) -> Result<()> {
+    let status = Command::new("unzip")
+        .arg("-q")
+        .arg(archive_path)
+        .arg("-d")
+        .arg(target_dir)
+        .status()
+        .context("Failed to execute unzip command. Hint: Ensure 'unzip' is installed.")?;
+
+    if !status.success() {
+        return Err(SpawnError::Extraction { reason: format!("exit code {:?}", status.code()), hint: "\nHint: Ensure 'unzip' is installed and the archive is valid." });
+    }
+    Ok(())
+}
+
+/// Shells out to `unrar`, falling back to `7z` when it isn't installed.
+fn run_unrar(archive_path: &Path, target_dir: &Path) -> Result<()> {
+    let hint = "\nHint: This is a .rar archive. Ensure you have 'unrar' or '7z' (p7zip) installed.";
+
+    let status = match Command::new("unrar").arg("x").arg("-o+").arg("-idq").arg(archive_path).arg(target_dir).status() {
+        Ok(status) => status,
+        Err(_) => Command::new("7z")
+            .arg("x")
+            .arg("-y")
+            .arg(format!("-o{}", target_dir.display()))
             .arg(archive_path)
-            .arg("-C")
-            .arg(&target_dir)
             .status()
-            .context("Failed to execute tar command")?
+            .map_err(|_| SpawnError::Extraction { reason: "no .rar extractor found".to_string(), hint })?,
     };
 
-    pb.finish_and_clear();
-
     if !status.success() {
-        let hint = if archive_path.to_string_lossy().ends_with(".xz") {
-            "\nHint: This is a .xz archive. Ensure you have 'xz-utils' or 'xz' installed."
-        } else if is_zip {
-            "\nHint: Ensure 'unzip' is installed and the archive is valid."
-        } else {
-            "\nHint: Ensure tar is installed and the archive is valid."
+        return Err(SpawnError::Extraction { reason: format!("exit code {:?}", status.code()), hint });
+    }
+    Ok(())
+}
+
+/// Opens a `.7z`, or every volume of one split into `game.7z.001`, `game.7z.002`, ...
+fn open_7z(archive_path: &Path) -> Result<std::io::BufReader<SplitVolumes>> {
+    let volumes = match Volume::parse(archive_path) {
+        Some(_) => archive_volumes(archive_path),
+        None => vec![archive_path.to_path_buf()],
+    };
+    // A gap would only show up as a corrupted stream halfway through extraction
+    for (index, volume) in volumes.iter().enumerate() {
+        if Volume::parse(volume).is_some_and(|v| v.number.parse() != Ok(index + 1)) {
+            return Err(SpawnError::Extraction {
+                reason: format!("volume {} of {:?} is missing", index + 1, archive_path.file_name().unwrap_or_default()),
+                hint: "\nHint: Download every part of the archive into the same folder.",
+            });
+        }
+    }
+    let reader = SplitVolumes::open(&volumes).context("Failed to open archive")?;
+    Ok(std::io::BufReader::new(reader))
+}
+
+fn seven_zip_error(error: sevenz_rust::Error) -> SpawnError {
+    SpawnError::Extraction { reason: error.to_string(), hint: "\nHint: The .7z archive may be corrupted or use an unsupported method." }
+}
+
+/// The volumes of a split archive read back to back, as if they were still one file.
+struct SplitVolumes {
+    volumes: Vec<(fs::File, u64)>,
+    len: u64,
+    position: u64,
+}
+
+impl SplitVolumes {
+    fn open(paths: &[PathBuf]) -> std::io::Result<Self> {
+        let volumes = paths.iter()
+            .map(|path| {
+                let file = fs::File::open(path)?;
+                let len = file.metadata()?.len();
+                Ok((file, len))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let len = volumes.iter().map(|(_, len)| len).sum();
+        Ok(SplitVolumes { volumes, len, position: 0 })
+    }
+}
+
+impl Read for SplitVolumes {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::{Seek, SeekFrom};
+        let mut start = 0;
+        for (file, len) in &mut self.volumes {
+            if self.position < start + *len {
+                file.seek(SeekFrom::Start(self.position - start))?;
+                let read = file.read(buf)?;
+                self.position += read as u64;
+                return Ok(read);
+            }
+            start += *len;
+        }
+        Ok(0)
+    }
+}
+
+impl std::io::Seek for SplitVolumes {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        use std::io::SeekFrom;
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
         };
-        return Err(anyhow!("{} Extraction failed (exit code: {:?}){}", "✖".red(), status.code(), hint));
+        self.position = position.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before the start of the archive"))?;
+        Ok(self.position)
     }
+}
 
-    println!("{} Extracted game files", "✔".green());
+/// One volume of a multi-part archive: `game.part2.rar`, or `game.7z.002` for a 7z split
+/// into numbered pieces.
+struct Volume<'a> {
+    /// The name every volume shares, `game` in both examples
+    base: &'a str,
+    /// The volume number as written, zero padding included
+    number: &'a str,
+    extension: &'a str,
+    /// Whether the number is the file extension (`game.7z.002`) rather than a `.partN` suffix
+    numbered_extension: bool,
+}
 
-    Ok(flatten_if_needed(target_dir))
+impl<'a> Volume<'a> {
+    fn parse(path: &'a Path) -> Option<Self> {
+        let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let (stem, extension) = path.file_name()?.to_str()?.rsplit_once('.')?;
+        if is_number(extension) && let Some((base, inner)) = stem.rsplit_once('.') && inner.eq_ignore_ascii_case("7z") {
+            return Some(Volume { base, number: extension, extension: inner, numbered_extension: true });
+        }
+        let index = stem.to_lowercase().rfind(".part")?;
+        let number = &stem[index + 5..];
+        is_number(number).then_some(Volume { base: &stem[..index], number, extension, numbered_extension: false })
+    }
+
+    /// The file name of volume `number`, padded like this one.
+    fn file_name(&self, number: usize) -> String {
+        let width = self.number.len();
+        match self.numbered_extension {
+            true => format!("{}.{}.{:0width$}", self.base, self.extension, number),
+            false => format!("{}.part{:0width$}.{}", self.base, number, self.extension),
+        }
+    }
+
+    /// Whether `other` belongs to the same archive.
+    fn same_archive(&self, other: &Volume) -> bool {
+        (self.base, self.extension, self.numbered_extension) == (other.base, other.extension, other.numbered_extension)
+    }
+}
+
+/// Decides whether to replace an existing install, asking only under [`OverwritePolicy::Ask`].
+/// Non-interactive runs (`--yes`/`--force`) overwrite.
+fn confirm_overwrite(policy: OverwritePolicy, interactive: bool) -> Result<bool> {
+    match policy {
+        OverwritePolicy::Always => return Ok(true),
+        OverwritePolicy::Never => return Ok(false),
+        OverwritePolicy::Ask if !interactive => return Ok(true),
+        OverwritePolicy::Ask => {}
+    }
+
+    report::confirm("Do you want to overwrite it?", false)
+}
+
+/// Folders that commonly hold save games inside a game's own directory.
+const SAVE_DIRS: &[&str] = &["saves", "savegames", "savedata", "save", "userdata"];
+
+/// Save data moved aside while a game folder is replaced.
+struct SaveStash {
+    dir: PathBuf,
+    /// Kept paths, relative to the game folder
+    paths: Vec<PathBuf>,
+}
+
+impl SaveStash {
+    /// Moves known save folders, AppImage portable homes and anything matching `patterns` out of
+    /// `game_dir`. Returns `None` when there is nothing to keep (or nothing was moved in a dry run).
+    fn collect(game_dir: &Path, patterns: &[String], dry_run: bool) -> Result<Option<Self>> {
+        let mut keep = Vec::new();
+        let mut walker = walkdir::WalkDir::new(game_dir).min_depth(1).into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else { continue };
+            let relative = entry.path().strip_prefix(game_dir).unwrap_or(entry.path()).to_path_buf();
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            let is_save_dir = entry.file_type().is_dir()
+                && (SAVE_DIRS.contains(&name.as_str()) || name.ends_with(".appimage.home") || name.ends_with(".appimage.config"));
+
+            if is_save_dir || patterns.iter().any(|p| matches_glob(&relative.to_string_lossy(), p)) {
+                if entry.file_type().is_dir() {
+                    walker.skip_current_dir();
+                }
+                keep.push(relative);
+            }
+        }
+
+        if keep.is_empty() {
+            report::step("No save data found to keep");
+            return Ok(None);
+        }
+        let names = keep.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ");
+        if dry_run {
+            report::step(format!("Would keep: {}", names));
+            return Ok(None);
+        }
+
+        let dir = game_dir.with_file_name(format!(".spawn-reinstall-{}", game_dir.file_name().unwrap_or_default().to_string_lossy()));
+        for relative in &keep {
+            let stashed = dir.join(relative);
+            if let Some(parent) = stashed.parent() {
+                fs::create_dir_all(parent).context("Failed to create save backup folder")?;
+            }
+            fs::rename(game_dir.join(relative), &stashed)
+                .with_context(|| format!("Failed to back up {:?}", relative))?;
+        }
+        report::success(format!("Keeping save data: {}", names));
+        Ok(Some(Self { dir, paths: keep }))
+    }
+
+    /// Moves the kept data back, replacing anything the new version shipped in its place.
+    fn restore(self, game_dir: &Path) -> Result<()> {
+        for relative in &self.paths {
+            let target = game_dir.join(relative);
+            if target.is_dir() {
+                fs::remove_dir_all(&target).context("Failed to make room for save data")?;
+            } else if target.exists() {
+                fs::remove_file(&target).context("Failed to make room for save data")?;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).context("Failed to restore save data")?;
+            }
+            fs::rename(self.dir.join(relative), &target).map_err(|error| SpawnError::SaveRestore {
+                path: relative.clone(),
+                error,
+                stash: self.dir.clone(),
+            })?;
+        }
+        let _ = fs::remove_dir_all(&self.dir);
+        report::success("Restored save data");
+        Ok(())
+    }
+}
+
+fn remove_existing_install(target_dir: &Path, dry_run: bool) -> Result<()> {
+    if dry_run {
+        report::step(format!("Would overwrite existing directory: {:?}", target_dir));
+        return Ok(());
+    }
+    report::step(format!("Removing existing directory: {:?}", target_dir));
+    fs::remove_dir_all(target_dir).context("Failed to remove existing directory")
+}
+
+/// Archives and AppImages directly inside `dir`, for `--batch`. Only the first volume
+/// of a multi-part archive is listed.
+pub fn find_batch_archives(dir: &Path) -> Vec<PathBuf> {
+    let mut archives = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.is_file() && first_volume(p) == *p)
+        .filter(|p| {
+            let name = p.to_string_lossy().to_lowercase();
+            if [".part", ".aria2", ".tmp", ".sha256"].iter().any(|ext| name.ends_with(ext)) {
+                return false;
+            }
+            name.ends_with(".appimage") || detect_archive_kind(p) != ArchiveKind::Unknown
+        })
+        .collect::<Vec<_>>();
+    archives.sort();
+    archives
+}
+
+/// What `--cleanup-archive` does with an archive once it's installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ArchiveCleanup {
+    /// Move it into an `installed` folder next to it
+    Move,
+    /// Delete it
+    Delete,
+}
+
+/// Every file that makes up an archive: all volumes of a multi-part archive, plus a
+/// `.sha256` file kept next to it.
+pub fn archive_files(archive_path: &Path) -> Vec<PathBuf> {
+    let archive_path = first_volume(archive_path);
+    let mut files = archive_volumes(&archive_path);
+    let mut sidecar = archive_path.into_os_string();
+    sidecar.push(".sha256");
+    files.extend(Some(PathBuf::from(sidecar)).filter(|p| p.is_file()));
+    files
+}
+
+/// Every volume of a multi-part archive present next to `archive_path`, in order; just the
+/// archive itself when it isn't split.
+fn archive_volumes(archive_path: &Path) -> Vec<PathBuf> {
+    let Some(volume) = Volume::parse(archive_path) else {
+        return vec![archive_path.to_path_buf()];
+    };
+    let mut parts = fs::read_dir(archive_path.parent().unwrap_or(Path::new(".")))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    parts.retain(|p| p.is_file() && Volume::parse(p).is_some_and(|v| v.same_archive(&volume)));
+    parts.sort();
+    parts
+}
+
+/// Deletes an installed archive, or moves it into an `installed` folder beside it, to reclaim
+/// the space it takes in the downloads folder.
+pub fn cleanup_archive(archive_path: &Path, cleanup: ArchiveCleanup, dry_run: bool) -> Result<()> {
+    let files = archive_files(archive_path);
+    let done_dir = archive_path.parent().unwrap_or(Path::new(".")).join("installed");
+    for file in files.iter().filter(|f| f.exists()) {
+        let name = file.file_name().unwrap_or_default();
+        match (cleanup, dry_run) {
+            (ArchiveCleanup::Delete, true) => report::step(format!("Would delete {:?}", name)),
+            (ArchiveCleanup::Move, true) => report::step(format!("Would move {:?} to {:?}", name, done_dir)),
+            (ArchiveCleanup::Delete, false) => {
+                fs::remove_file(file).with_context(|| format!("Failed to delete {:?}", file))?;
+                report::success(format!("Deleted {:?}", name));
+            }
+            (ArchiveCleanup::Move, false) => {
+                fs::create_dir_all(&done_dir).with_context(|| format!("Failed to create {:?}", done_dir))?;
+                move_file(file, &done_dir.join(name))?;
+                report::success(format!("Moved {:?} to {:?}", name, done_dir));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// For multi-part archives like `game.part3.rar` or `game.7z.003`, returns the first volume if
+/// it exists, since extractors must be pointed at part one.
+fn first_volume(archive_path: &Path) -> PathBuf {
+    Volume::parse(archive_path)
+        .map(|volume| archive_path.with_file_name(volume.file_name(1)))
+        .filter(|first| first.exists())
+        .unwrap_or_else(|| archive_path.to_path_buf())
 }
 
-pub fn install_appimage(appimage_path: &Path, install_dir: &Path, dry_run: bool) -> Result<PathBuf> {
-    let file_name = appimage_path.file_name().ok_or_else(|| anyhow!("Invalid AppImage path"))?;
-    let stem = appimage_path.file_stem().ok_or_else(|| anyhow!("Invalid file name"))?;
+/// Offers to run bundled setup scripts. `policy` comes from `--run-setup`/`--no-run-setup`;
+/// without it the user is always asked, and non-interactive runs skip the scripts.
+pub fn run_setup_scripts(game_dir: &Path, scripts: &[PathBuf], policy: Option<bool>, interactive: bool) -> Result<()> {
+    for script in scripts {
+        let relative = script.strip_prefix(game_dir).unwrap_or(script);
+        report::warning(format!("Found setup script: {:?}", relative));
+
+        let run = match policy {
+            Some(run) => run,
+            None if !interactive => {
+                report::step(format!("Skipped {:?} (pass --run-setup to run it non-interactively)", relative));
+                continue;
+            }
+            None => confirm_setup_script(script)?,
+        };
+
+        if !run {
+            report::step(format!("Skipped {:?}", relative));
+            continue;
+        }
+
+        report::step(format!("Running {:?}...", relative));
+        let status = Command::new("sh")
+            .arg(script)
+            .current_dir(game_dir)
+            .status()
+            .context("Failed to execute setup script")?;
+
+        if status.success() {
+            report::success("Setup script finished");
+        } else {
+            report::warning(format!("Setup script exited with code {:?}; the game may not work correctly", status.code()));
+        }
+    }
+    Ok(())
+}
+
+fn confirm_setup_script(script: &Path) -> Result<bool> {
+    let choices = ["Run it".to_string(), "Skip it".to_string(), "Show the script first".to_string()];
+    loop {
+        match report::choose("Run it? This executes code from the archive. (Enter to skip)", &choices)? {
+            Some(0) => return Ok(true),
+            Some(2) => report::emit(report::Event::Listing(fs::read_to_string(script).context("Failed to read setup script")?)),
+            _ => return Ok(false),
+        }
+    }
+}
+
+pub fn install_appimage(appimage_path: &Path, install_dir: &Path, options: &InstallOptions) -> Result<PathBuf> {
+    let InstallOptions { dry_run, preserve, symlink, move_appimage, .. } = *options;
+    let invalid = || SpawnError::InvalidFileName(appimage_path.to_path_buf());
+    let file_name = appimage_path.file_name().ok_or_else(invalid)?;
+    let stem = appimage_path.file_stem().ok_or_else(invalid)?;
     
-    let target_dir = install_dir.join(stem);
+    let target_dir = install_dir.join(options.dir_name.map(std::ffi::OsStr::new).unwrap_or(stem));
+    let mut stash = None;
     if target_dir.exists() {
-        println!("{} {:?} is already installed.", "⚠".yellow().bold(), stem);
-        println!("  Do you want to overwrite it? [y/N]");
-        
-        let mut confirm = String::new();
-        std::io::stdin().read_line(&mut confirm).context("Failed to read input")?;
-        if confirm.trim().to_lowercase() != "y" {
-            println!("{} Using existing directory.", "✔".green());
+        report::warning(format!("{:?} is already installed.", target_dir.file_name().unwrap_or(stem)));
+        if let Some(patterns) = preserve {
+            stash = SaveStash::collect(&target_dir, patterns, dry_run)?;
+        } else if !confirm_overwrite(options.overwrite, options.interactive)? {
+            report::success("Using existing directory.");
             return Ok(target_dir);
         }
-
-        if !dry_run {
-            fs::remove_dir_all(&target_dir).context("Failed to remove existing directory")?;
-        }
+        remove_existing_install(&target_dir, dry_run)?;
     }
 
     if dry_run {
-        println!("{} Would move {:?} to {:?}", "▶".cyan(), appimage_path, target_dir);
+        let verb = if symlink { "link" } else if move_appimage { "move" } else { "copy" };
+        report::step(format!("Would {} {:?} to {:?}", verb, appimage_path, target_dir));
         return Ok(target_dir);
     }
 
-    fs::create_dir_all(&target_dir).context("Failed to create install directory")?;
     let target_path = target_dir.join(file_name);
-    fs::copy(appimage_path, &target_path).context("Failed to copy AppImage")?;
+    let result = fs::create_dir_all(&target_dir)
+        .context("Failed to create install directory")
+        .and_then(|_| if symlink {
+            link_appimage(appimage_path, &target_path)
+        } else if move_appimage {
+            move_file(appimage_path, &target_path)
+        } else {
+            copy_with_progress(appimage_path, &target_path)
+        });
+    if let Some(stash) = stash {
+        stash.restore(&target_dir)?;
+    }
+    result?;
     
-    println!("{} Installed AppImage to {:?}", "✔".green(), target_path);
+    if symlink {
+        report::success(format!("Linked AppImage into {:?}", target_dir));
+        report::warning(format!("The shortcut points at {:?}; moving or deleting it will break the game", appimage_path));
+    } else if move_appimage {
+        report::success(format!("Moved AppImage to {:?}", target_path));
+    } else {
+        report::success(format!("Installed AppImage to {:?}", target_path));
+    }
     
     Ok(target_dir)
 }
 
+/// Copies a game folder into its own folder under `install_dir` for `--copy-dir`, so the
+/// original can be deleted. Permissions and symlinks are kept. The copy is made under a
+/// temporary name and renamed into place once complete.
+pub fn copy_game_dir(source: &Path, install_dir: &Path, options: &InstallOptions) -> Result<PathBuf> {
+    let InstallOptions { dry_run, preserve, .. } = *options;
+    let name = source.file_name().ok_or_else(|| SpawnError::InvalidFileName(source.to_path_buf()))?;
+    let target_dir = install_dir.join(options.dir_name.map(std::ffi::OsStr::new).unwrap_or(name));
+    // Replacing the target must not delete the source, nor may the copy land inside what it copies
+    let source_abs = std::path::absolute(source).context("Failed to resolve game folder")?;
+    let target_abs = std::path::absolute(&target_dir).context("Failed to resolve install folder")?;
+    if target_abs.starts_with(&source_abs) || source_abs.starts_with(&target_abs) {
+        return Err(SpawnError::CopyIntoItself { from: source.to_path_buf(), target: target_dir });
+    }
+
+    let mut stash = None;
+    if target_dir.exists() {
+        report::warning(format!("{:?} is already installed.", target_dir.file_name().unwrap_or(name)));
+        if let Some(patterns) = preserve {
+            stash = SaveStash::collect(&target_dir, patterns, dry_run)?;
+        } else if !confirm_overwrite(options.overwrite, options.interactive)? {
+            report::success("Using existing directory.");
+            return Ok(target_dir);
+        }
+        remove_existing_install(&target_dir, dry_run)?;
+    }
+
+    if dry_run {
+        report::step(format!("Would copy {:?} to {:?}", source, target_dir));
+        return Ok(target_dir);
+    }
+
+    let mut staging = target_dir.as_os_str().to_os_string();
+    staging.push(".part");
+    let staging = PathBuf::from(staging);
+    let _ = fs::remove_dir_all(&staging);
+    let result = copy_tree(source, &staging).and_then(|_| fs::rename(&staging, &target_dir).context("Failed to move copied folder into place"));
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    if let Some(stash) = stash {
+        stash.restore(&target_dir)?;
+    }
+    result?;
+
+    report::success(format!("Copied {:?} to {:?}", source, target_dir));
+    Ok(target_dir)
+}
+
+/// Recursively copies `source` to `target` with one progress bar over all the bytes.
+fn copy_tree(source: &Path, target: &Path) -> Result<()> {
+    let entries = walkdir::WalkDir::new(source).into_iter().collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| SpawnError::Io { context: "Failed to read game folder".to_string(), error: e.into() })?;
+    let total = entries.iter().filter(|e| e.file_type().is_file()).filter_map(|e| e.metadata().ok()).map(|m| m.len()).sum();
+
+    let task = Task::start("Copying game folder...", Some(total));
+    let result = (|| -> Result<()> {
+        for entry in &entries {
+            let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+            let destination = target.join(relative);
+            let file_type = entry.file_type();
+            if file_type.is_symlink() {
+                let link = fs::read_link(entry.path()).with_context(|| format!("Failed to read link {:?}", entry.path()))?;
+                std::os::unix::fs::symlink(link, &destination).with_context(|| format!("Failed to create link {:?}", destination))?;
+            } else if file_type.is_dir() {
+                fs::create_dir_all(&destination).with_context(|| format!("Failed to create {:?}", destination))?;
+            } else {
+                let mut reader = task.wrap_read(fs::File::open(entry.path()).with_context(|| format!("Failed to open {:?}", entry.path()))?);
+                let mut writer = fs::File::create(&destination).with_context(|| format!("Failed to create {:?}", destination))?;
+                std::io::copy(&mut reader, &mut writer).with_context(|| format!("Failed to copy {:?}", entry.path()))?;
+            }
+            if !file_type.is_symlink() {
+                let permissions = entry.metadata().map_err(std::io::Error::from).context("Failed to read permissions")?.permissions();
+                fs::set_permissions(&destination, permissions).with_context(|| format!("Failed to set permissions on {:?}", destination))?;
+            }
+        }
+        Ok(())
+    })();
+    drop(task);
+    result
+}
+
+/// Links `target` to the AppImage where it is, for `--symlink`. Uninstalling removes the link
+/// along with the install folder but never touches the original.
+fn link_appimage(appimage_path: &Path, target: &Path) -> Result<()> {
+    let original = std::path::absolute(appimage_path).context("Failed to resolve AppImage path")?;
+    std::os::unix::fs::symlink(&original, target).context("Failed to link AppImage into the install directory")
+}
+
+/// Renames a file into place, copying it and removing the original when it's on another filesystem.
+fn move_file(source: &Path, target: &Path) -> Result<()> {
+    match fs::rename(source, target) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_with_progress(source, target)?;
+            fs::remove_file(source).with_context(|| format!("Failed to remove {:?} after copying it", source))
+        }
+        result => result.with_context(|| format!("Failed to move {:?}", source)),
+    }
+}
+
+/// Copies a file with a byte progress bar. Data goes to a `.part` file that is renamed into place
+/// once complete, so an interrupted copy never leaves a truncated file under the real name.
+fn copy_with_progress(source: &Path, target: &Path) -> Result<()> {
+    let total = fs::metadata(source).map(|m| m.len()).ok();
+    let mut partial = target.as_os_str().to_os_string();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let task = Task::start("Copying files...", total);
+    let result = (|| -> Result<()> {
+        let mut reader = task.wrap_read(fs::File::open(source).context("Failed to open source file")?);
+        let mut writer = fs::File::create(&partial).context("Failed to create target file")?;
+        std::io::copy(&mut reader, &mut writer).context("Failed to copy file")?;
+        writer.sync_all().context("Failed to flush copied file")?;
+        fs::metadata(source).and_then(|m| fs::set_permissions(&partial, m.permissions())).context("Failed to copy file permissions")?;
+        fs::rename(&partial, target).context("Failed to move copied file into place")
+    })();
+    drop(task);
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Folder names that are part of a game's own layout rather than a redundant wrapper.
+pub const LAYOUT_DIRS: &[&str] = &["bin", "bin32", "bin64", "lib", "lib32", "lib64", "data", "assets", "resources"];
+
+/// Files and folders that archivers and file managers leave behind, which say nothing about
+/// the game's layout: macOS resource forks and Finder metadata, and Windows or KDE thumbnail caches.
+pub const JUNK_ENTRIES: &[&str] = &["__MACOSX", ".DS_Store", "Thumbs.db", "desktop.ini", ".directory"];
+
+/// Whether an archive entry is junk such as `__MACOSX` or an AppleDouble `._Game` file.
+pub fn is_junk_entry(name: &std::ffi::OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with("._") || JUNK_ENTRIES.iter().any(|junk| junk.eq_ignore_ascii_case(&name))
+}
+
+/// Descends through wrapper folders (`game/game-1.0/linux/...`) while each level holds a single
+/// directory, not counting junk entries such as a `__MACOSX` sibling.
 fn flatten_if_needed(dir: PathBuf) -> PathBuf {
-    let entries = match fs::read_dir(&dir) {
-        Ok(e) => e.filter_map(|e| e.ok()).collect::<Vec<_>>(),
-        Err(_) => return dir,
-    };
+    let mut current = dir.clone();
 
-    if entries.len() == 1 && entries[0].path().is_dir() {
-        let inner_dir = entries[0].path();
-        println!("✔ Detected nested directory, using: {:?}", inner_dir);
-        inner_dir
-    } else {
-        dir
+    loop {
+        let entries = match fs::read_dir(&current) {
+            Ok(e) => e.filter_map(|e| e.ok()).filter(|e| !is_junk_entry(&e.file_name())).collect::<Vec<_>>(),
+            Err(_) => break,
+        };
+
+        if entries.len() != 1 || !entries[0].path().is_dir() {
+            break;
+        }
+
+        let name = entries[0].file_name().to_string_lossy().to_lowercase();
+        if LAYOUT_DIRS.contains(&name.as_str()) {
+            break;
+        }
+        current = entries[0].path();
+    }
+
+    if current != dir {
+        report::success(format!("Detected nested directory, using: {:?}", current));
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    /// The start of an x86_64 ELF header, enough for discovery to take a file for a game binary.
+    const ELF: &[u8] = b"\x7fELF\x02\x01\x01\0";
+
+    /// A tarball holding `Game/game.x86_64` and `Game/data/level.dat`, run through `compress`.
+    fn tarball<W: std::io::Write>(compress: W) -> W {
+        let mut builder = tar::Builder::new(compress);
+        for (path, contents) in [("Game/game.x86_64", ELF), ("Game/data/level.dat", b"level")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            builder.append_data(&mut header, path, contents).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn assert_installed(archive: &Path, install_dir: &Path) {
+        let installed = install_archive(archive, install_dir, &Config::default(), &InstallOptions::default()).unwrap();
+        assert_eq!(installed.install_root, Some(install_dir.join("Game")));
+        assert_eq!(installed.game_dir, install_dir.join("Game/Game"));
+        assert_eq!(fs::read(installed.game_dir.join("game.x86_64")).unwrap(), ELF);
+        assert_eq!(fs::read(installed.game_dir.join("data/level.dat")).unwrap(), b"level");
+        assert_eq!(installed.launch.unwrap().executable, installed.game_dir.join("game.x86_64"));
+    }
+
+    #[test]
+    fn installs_tar_gz_natively() {
+        let dir = TempDir::new();
+        let gz = tarball(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast())).finish().unwrap();
+        let archive = dir.write("Game.tar.gz", gz);
+        assert_installed(&archive, &dir.path().join("games"));
+    }
+
+    #[test]
+    fn installs_tar_xz_natively() {
+        let dir = TempDir::new();
+        let xz = tarball(xz2::write::XzEncoder::new(Vec::new(), 1)).finish().unwrap();
+        let archive = dir.write("Game.tar.xz", xz);
+        assert_installed(&archive, &dir.path().join("games"));
+    }
+
+    /// A `Game.7z` of a folder holding `game.x86_64` and `data/level.dat`.
+    fn seven_zip(dir: &TempDir) -> Vec<u8> {
+        dir.write("src/game.x86_64", ELF);
+        dir.write("src/data/level.dat", "level");
+        let archive = dir.path().join("Game.7z");
+        sevenz_rust::compress_to_path(dir.path().join("src"), &archive).unwrap();
+        fs::read(archive).unwrap()
+    }
+
+    #[test]
+    fn installs_7z() {
+        let dir = TempDir::new();
+        seven_zip(&dir);
+        let installed = install_archive(&dir.path().join("Game.7z"), &dir.path().join("games"), &Config::default(), &InstallOptions::default()).unwrap();
+        assert_eq!(installed.game_dir, dir.path().join("games/Game"));
+        assert_eq!(fs::read(installed.game_dir.join("data/level.dat")).unwrap(), b"level");
+    }
+
+    #[test]
+    fn installs_split_7z_from_any_volume() {
+        let dir = TempDir::new();
+        let data = seven_zip(&dir);
+        for (index, chunk) in data.chunks(data.len() / 3 + 1).enumerate() {
+            dir.write(&format!("downloads/Game.7z.{:03}", index + 1), chunk);
+        }
+
+        let second = dir.path().join("downloads/Game.7z.002");
+        assert_eq!(first_volume(&second), dir.path().join("downloads/Game.7z.001"));
+        assert_eq!(archive_files(&second).len(), 3);
+        let installed = install_archive(&second, &dir.path().join("games"), &Config::default(), &InstallOptions::default()).unwrap();
+        assert_eq!(installed.game_dir, dir.path().join("games/Game"));
+        assert_eq!(fs::read(installed.game_dir.join("game.x86_64")).unwrap(), ELF);
+        assert_eq!(list_archive(&second).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn split_7z_with_a_missing_volume_fails_before_extracting() {
+        let dir = TempDir::new();
+        let data = seven_zip(&dir);
+        let chunks = data.chunks(data.len() / 3 + 1).collect::<Vec<_>>();
+        dir.write("Game.7z.001", chunks[0]);
+        dir.write("Game.7z.003", chunks[2]);
+
+        let error = install_archive(&dir.path().join("Game.7z.001"), &dir.path().join("games"), &Config::default(), &InstallOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("volume 2"), "{}", error);
+    }
+
+    #[test]
+    fn names_rar_and_7z_volumes() {
+        let rar = Volume::parse(Path::new("Game.part07.rar")).unwrap();
+        assert_eq!((rar.base, rar.number), ("Game", "07"));
+        assert_eq!(rar.file_name(1), "Game.part01.rar");
+        let seven = Volume::parse(Path::new("Game.v2.7z.002")).unwrap();
+        assert_eq!((seven.base, seven.number), ("Game.v2", "002"));
+        assert_eq!(seven.file_name(1), "Game.v2.7z.001");
+        assert!(!rar.same_archive(&seven));
+        assert!(Volume::parse(Path::new("Game.7z")).is_none());
+        assert!(Volume::parse(Path::new("Game.tar.001")).is_none());
+        assert_eq!(install_target(Path::new("Game.part1.rar"), Path::new("games")).unwrap(), Path::new("games/Game"));
+    }
+
+    /// A RAR 4 archive storing `game.x86_64` uncompressed. Each header starts with the low
+    /// 16 bits of the CRC-32 of the rest of it.
+    fn stored_rar(contents: &[u8]) -> Vec<u8> {
+        let crc32 = |bytes: &[u8]| {
+            let mut crc = flate2::Crc::new();
+            crc.update(bytes);
+            crc.sum()
+        };
+        let block = |body: Vec<u8>| {
+            let mut block = (crc32(&body) as u16).to_le_bytes().to_vec();
+            block.extend(body);
+            block
+        };
+        let name = b"game.x86_64";
+        let mut file = vec![0x74];
+        file.extend(0x8000u16.to_le_bytes());
+        file.extend((32 + name.len() as u16).to_le_bytes());
+        file.extend((contents.len() as u32).to_le_bytes());
+        file.extend((contents.len() as u32).to_le_bytes());
+        file.push(3);
+        file.extend(crc32(contents).to_le_bytes());
+        file.extend(0x5A21_0000u32.to_le_bytes());
+        file.extend([20, 0x30]);
+        file.extend((name.len() as u16).to_le_bytes());
+        file.extend(0o100755u32.to_le_bytes());
+        file.extend(name);
+
+        let mut rar = b"Rar!\x1A\x07\x00".to_vec();
+        rar.extend(block(vec![0x73, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0]));
+        rar.extend(block(file));
+        rar.extend(contents);
+        rar.extend(block(vec![0x7B, 0x00, 0x40, 0x07, 0x00]));
+        rar
+    }
+
+    #[test]
+    fn installs_rar() {
+        let rar = stored_rar(ELF);
+        assert_eq!(&rar[rar.len() - 7..], [0xC4, 0x3D, 0x7B, 0x00, 0x40, 0x07, 0x00]);
+        if crate::utils::find_in_path("unrar").is_none() && crate::utils::find_in_path("7z").is_none() {
+            eprintln!("skipping: neither unrar nor 7z is installed");
+            return;
+        }
+
+        let dir = TempDir::new();
+        let archive = dir.write("Game.rar", rar);
+        assert_eq!(detect_archive_kind(&archive), ArchiveKind::Rar);
+        let installed = install_archive(&archive, &dir.path().join("games"), &Config::default(), &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(installed.game_dir.join("game.x86_64")).unwrap(), ELF);
+    }
+
+    /// Zips a `Game` folder holding `game.x86_64` and `data/level.dat` with the system `zip`,
+    /// passing `zip_args` before the names. `None` when `zip` isn't installed.
+    fn system_zip(dir: &TempDir, zip_args: &[&str]) -> Option<PathBuf> {
+        crate::utils::find_in_path("zip")?;
+        dir.write("src/Game/game.x86_64", ELF);
+        dir.write("src/Game/data/level.dat", "level".repeat(100));
+        let status = Command::new("zip").arg("-qr").args(zip_args).arg("../Game.zip").arg("Game").current_dir(dir.path().join("src")).status().unwrap();
+        assert!(status.success());
+        Some(dir.path().join("Game.zip"))
+    }
+
+    #[test]
+    fn installs_an_encrypted_zip_with_the_right_password() {
+        let dir = TempDir::new();
+        let Some(archive) = system_zip(&dir, &["-P", "secret"]) else {
+            eprintln!("skipping: zip is not installed");
+            return;
+        };
+        assert!(zip::is_encrypted(&archive));
+
+        let options = InstallOptions { password: Some("secret"), ..InstallOptions::default() };
+        let installed = install_archive(&archive, &dir.path().join("games"), &Config::default(), &options).unwrap();
+        assert_eq!(fs::read(installed.game_dir.join("game.x86_64")).unwrap(), ELF);
+        assert_eq!(fs::read_to_string(installed.game_dir.join("data/level.dat")).unwrap(), "level".repeat(100));
+        assert_eq!(installed.launch.unwrap().executable, installed.game_dir.join("game.x86_64"));
+    }
+
+    #[test]
+    fn installs_an_encrypted_zip64_archive() {
+        let dir = TempDir::new();
+        // Forces the ZIP64 records archives over 4 GiB need
+        let Some(archive) = system_zip(&dir, &["-fz", "-P", "secret"]) else {
+            eprintln!("skipping: zip is not installed");
+            return;
+        };
+        assert!(zip::is_encrypted(&archive));
+
+        let options = InstallOptions { password: Some("secret"), ..InstallOptions::default() };
+        let installed = install_archive(&archive, &dir.path().join("games"), &Config::default(), &options).unwrap();
+        assert_eq!(fs::read_to_string(installed.game_dir.join("data/level.dat")).unwrap(), "level".repeat(100));
+    }
+
+    #[test]
+    fn a_wrong_zip_password_leaves_nothing_behind() {
+        let dir = TempDir::new();
+        let Some(archive) = system_zip(&dir, &["-P", "secret"]) else {
+            eprintln!("skipping: zip is not installed");
+            return;
+        };
+
+        let options = InstallOptions { password: Some("guess"), ..InstallOptions::default() };
+        let error = install_archive(&archive, &dir.path().join("games"), &Config::default(), &options).unwrap_err();
+        assert!(matches!(error, SpawnError::WrongPassword(_)), "{}", error);
+        assert!(!dir.path().join("games/Game").exists());
+    }
+
+    #[test]
+    fn plain_zips_are_not_encrypted() {
+        let dir = TempDir::new();
+        let Some(archive) = system_zip(&dir, &[]) else {
+            eprintln!("skipping: zip is not installed");
+            return;
+        };
+        assert!(!zip::is_encrypted(&archive));
+        assert!(!zip::is_encrypted(&dir.path().join("missing.zip")));
+    }
+
+    #[test]
+    fn detects_archive_kind_from_magic_bytes() {
+        let cases: &[(&[u8], ArchiveKind)] = &[
+            (b"PK\x03\x04rest", ArchiveKind::Zip),
+            (b"PK\x05\x06", ArchiveKind::Zip),
+            (&[0x1F, 0x8B, 0x08], ArchiveKind::TarGz),
+            (&[0xFD, b'7', b'z', b'X', b'Z', 0x00], ArchiveKind::TarXz),
+            (b"BZh91AY", ArchiveKind::TarBz2),
+            (&[0x28, 0xB5, 0x2F, 0xFD], ArchiveKind::TarZst),
+            (&[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C, 0x00], ArchiveKind::SevenZip),
+            (b"Rar!\x1A\x07\x01\x00", ArchiveKind::Rar),
+            (b"\x7FELF", ArchiveKind::Unknown),
+            (b"", ArchiveKind::Unknown),
+        ];
+        for (bytes, kind) in cases {
+            assert_eq!(ArchiveKind::from_magic(bytes), *kind, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn magic_bytes_win_over_the_extension() {
+        let dir = TempDir::new();
+        let renamed_zip = dir.write("game.bin", b"PK\x03\x04");
+        let gz_without_extension = dir.write("game", [0x1F, 0x8B, 0x08, 0x00]);
+        let plain_tar = dir.write("game.tar.xz", b"not compressed");
+        assert_eq!(detect_archive_kind(&renamed_zip), ArchiveKind::Zip);
+        assert_eq!(detect_archive_kind(&gz_without_extension), ArchiveKind::TarGz);
+        // An inconclusive header falls back to the extension
+        assert_eq!(detect_archive_kind(&plain_tar), ArchiveKind::TarXz);
+        assert_eq!(detect_archive_kind(&dir.path().join("missing.tgz")), ArchiveKind::TarGz);
+    }
+
+    #[test]
+    fn flattens_every_wrapper_folder() {
+        let dir = TempDir::new();
+        dir.write("Game/game/game-1.0/linux/run.sh", "#!/bin/sh\n");
+        dir.write("Game/game/game-1.0/linux/data.pak", "data");
+        assert_eq!(flatten_if_needed(dir.path().join("Game")), dir.path().join("Game/game/game-1.0/linux"));
+    }
+
+    #[test]
+    fn flattening_ignores_a_macosx_sibling() {
+        let dir = TempDir::new();
+        dir.write("Game/game-1.0/game.x86_64", ELF);
+        dir.write("Game/__MACOSX/game-1.0/._game.x86_64", "resource fork");
+        dir.write("Game/._game-1.0", "resource fork");
+        dir.write("Game/.DS_Store", "");
+        assert_eq!(flatten_if_needed(dir.path().join("Game")), dir.path().join("Game/game-1.0"));
+        assert!(is_junk_entry("__macosx".as_ref()));
+        assert!(!is_junk_entry("game-1.0".as_ref()));
+    }
+
+    #[test]
+    fn flattening_stops_at_layout_folders() {
+        let dir = TempDir::new();
+        dir.write("Game/game-1.0/bin/game", "");
+        assert_eq!(flatten_if_needed(dir.path().join("Game")), dir.path().join("Game/game-1.0"));
+    }
+
+    #[test]
+    fn installs_tar_zst_natively() {
+        let dir = TempDir::new();
+        let zst = tarball(zstd::stream::write::Encoder::new(Vec::new(), 3).unwrap()).finish().unwrap();
+        let archive = dir.write("Game.tar.zst", zst);
+        assert_eq!(detect_archive_kind(&archive), ArchiveKind::TarZst);
+        // Without falling back to the system tar
+        extract_tar_native(&archive, ArchiveKind::TarZst, dir.path(), &Task::start("Extracting files...", None)).unwrap();
+        assert!(dir.path().join("Game/data/level.dat").is_file());
+        assert_installed(&archive, &dir.path().join("games"));
+    }
+
+    #[test]
+    fn copies_a_small_tree_with_permissions_and_links() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new();
+        let binary = dir.write("Game/game.x86_64", ELF);
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+        dir.write("Game/data/level.dat", "level");
+        fs::create_dir(dir.path().join("Game/empty")).unwrap();
+        std::os::unix::fs::symlink("data/level.dat", dir.path().join("Game/level")).unwrap();
+
+        let copied = copy_game_dir(&dir.path().join("Game"), &dir.path().join("games"), &InstallOptions::default()).unwrap();
+        assert_eq!(copied, dir.path().join("games/Game"));
+        assert_eq!(fs::read(copied.join("game.x86_64")).unwrap(), ELF);
+        assert_eq!(fs::metadata(copied.join("game.x86_64")).unwrap().permissions().mode() & 0o777, 0o755);
+        assert_eq!(fs::read(copied.join("data/level.dat")).unwrap(), b"level");
+        assert!(copied.join("empty").is_dir());
+        assert_eq!(fs::read_link(copied.join("level")).unwrap(), Path::new("data/level.dat"));
+        // The original is left alone and no staging folder is left behind
+        assert!(binary.is_file());
+        assert!(!dir.path().join("games/Game.part").exists());
+    }
+
+    #[test]
+    fn refuses_to_copy_a_folder_into_itself() {
+        let dir = TempDir::new();
+        dir.write("Game/game.x86_64", ELF);
+        let error = copy_game_dir(&dir.path().join("Game"), &dir.path().join("Game/games"), &InstallOptions::default()).unwrap_err();
+        assert!(matches!(error, SpawnError::CopyIntoItself { .. }), "{}", error);
+        assert!(!dir.path().join("Game/games").exists());
+    }
+
+    #[test]
+    fn unpacks_a_lone_compressed_binary_and_finds_it() {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(ELF).unwrap();
+        let archive = dir.write("game.x86_64.gz", gz.finish().unwrap());
+
+        let installed = install_archive(&archive, &dir.path().join("games"), &Config::default(), &InstallOptions::default()).unwrap();
+        let binary = dir.path().join("games/game/game.x86_64");
+        assert_eq!(installed.game_dir, dir.path().join("games/game"));
+        assert_eq!(fs::read(&binary).unwrap(), ELF);
+        assert_ne!(fs::metadata(&binary).unwrap().permissions().mode() & 0o111, 0);
+        assert_eq!(installed.launch.unwrap().executable, binary);
     }
 }
@@ -0,0 +1,31 @@
+//! Spawn's install logic, usable without the command-line front end.
+//!
+//! [`install_archive`] installs a game and finds what to launch. Operations report what they're
+//! doing through [`report`]: install a [`Reporter`] with [`set_reporter`] to render progress
+//! yourself. Functions that take `interactive: bool` ask questions through the reporter when it
+//! is `true`; a reporter that doesn't answer them leaves the default answer in place.
+
+pub mod compat;
+pub mod config;
+pub mod discovery;
+pub mod error;
+pub mod history;
+pub mod installation;
+pub mod lock;
+pub mod manifest;
+pub mod platform;
+pub mod report;
+pub mod sandbox;
+pub mod steam;
+pub mod update;
+pub mod utils;
+pub mod zip;
+
+#[cfg(test)]
+mod test_support;
+
+pub use discovery::{LaunchTarget, discover_executable, discover_icon, discover_launch_target};
+pub use error::SpawnError;
+pub use installation::{InstallOptions, InstalledArchive, install_archive};
+pub use report::{Event, Reporter, set_reporter};
+pub use utils::{desktop_entry_content, generate_desktop_entry};
@@ -0,0 +1,61 @@
+//! Advisory locks so Spawn processes running at the same time take turns with shared files
+//! instead of overwriting each other's changes.
+
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use std::fs::{self, File};
+use sha2::{Digest, Sha256};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::error::{IoContext, Result};
+use crate::manifest::data_dir_path;
+use crate::report;
+
+/// Held while Spawn changes the files it guards; released when dropped, or when the process exits.
+pub struct FileLock {
+    _lock: Flock<File>,
+}
+
+/// Takes the lock at `path`, creating the file if needed. Waits, saying so, while another
+/// Spawn instance holds it.
+pub fn lock_file(path: &Path) -> Result<FileLock> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty() && !p.exists()) {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    let file = File::options().create(true).truncate(false).write(true).open(path)
+        .with_context(|| format!("Failed to open lock file {:?}", path))?;
+
+    // Only a lock held elsewhere is worth waiting for; anything else is a real failure
+    let file = match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => return Ok(FileLock { _lock: lock }),
+        Err((file, Errno::EWOULDBLOCK)) => file,
+        Err((_, errno)) => return Err(std::io::Error::from(errno)).with_context(|| format!("Failed to lock {:?}", path)),
+    };
+    report::step("Waiting for another Spawn instance to finish...");
+    let lock = Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, errno)| std::io::Error::from(errno))
+        .with_context(|| format!("Failed to lock {:?}", path))?;
+    Ok(FileLock { _lock: lock })
+}
+
+/// Serializes installs into the same folder. The lock file lives in the user's runtime folder
+/// rather than next to the game, so nothing is left behind in the install location. Without a
+/// runtime folder it goes in Spawn's data folder: a shared one like `/tmp` would let any other
+/// user create the lock first and hold up the install.
+/// The lock file is named after a SHA-256 of the folder's path, which every Spawn build agrees on.
+pub fn lock_install_dir(target_dir: &Path) -> Result<FileLock> {
+    let digest = format!("{:x}", Sha256::digest(target_dir.as_os_str().as_bytes()));
+    let dir = match dirs_next::runtime_dir() {
+        Some(dir) => dir,
+        None => data_dir_path()?.join("locks"),
+    };
+    lock_file(&dir.join(format!("spawn-install-{}.lock", &digest[..16])))
+}
+
+/// The lock file guarding `path`, e.g. `installed.json.lock`.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
@@ -1,27 +1,38 @@
-mod config;
-mod discovery;
-mod installation;
-mod utils;
-mod steam;
+mod doctor;
+mod plan;
+mod terminal;
 
 use anyhow::{Context, Result, anyhow};
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use colored::*;
+use indicatif::HumanBytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::time::Duration;
 use std::fs;
+use std::time::{Duration, Instant};
 
-use crate::config::{load_config, save_config};
-use crate::discovery::{discover_executable, discover_icon};
-use crate::installation::{extract_archive, install_appimage};
-use crate::steam::add_to_steam;
-use crate::utils::{format_game_name, generate_desktop_entry, resolve_fuzzy_path, set_executable_permission};
+use crate::doctor::run_doctor;
+use crate::plan::{InstallPlan, plan_install};
+use crate::terminal::TerminalReporter;
+use spawn::compat::{CompatTool, wrapper_command};
+use spawn::config::{Config, DesktopTarget, MAX_DISCOVERY_DEPTH, OverwritePolicy, load_config, load_stored_config, lock_config, peek_config, save_config, update_cache_path};
+use spawn::SpawnError;
+use spawn::discovery::{LaunchTarget, SearchScope, archive_root, check_dependencies, discover_executable, discover_extra_executables, discover_launch_target, find_bundled_launcher, discover_setup_scripts, download_icon, extract_embedded_icon, preview_executable, preview_icon, preview_windows_executable, warn_on_arch_mismatch};
+use spawn::history::{append_history, load_history};
+use spawn::installation::{ArchiveCleanup, STDIN_INPUT, cleanup_archive, download_game, expected_checksum, find_batch_archives, InstallOptions, install_archive, install_target, is_url, list_archive, pick_install_dir, save_stdin, unpacked_size, run_setup_scripts, verify_checksum};
+use spawn::lock::lock_install_dir;
+use spawn::manifest::{ArchiveFingerprint, ExtraLauncher, LastInstall, Manifest, ManifestEntry, SYSTEM_DATA_DIR, clear_last_install, load_last_install, load_manifest, lock_manifest, record_install, record_last_install, save_manifest, use_system_manifest};
+use spawn::report::{set_reporter, step, success, warning};
+use spawn::sandbox::{SandboxTool, sandbox_command};
+use spawn::steam::{SteamShortcut, add_to_steam, remove_from_steam, rename_in_steam, update_steam_launch_options};
+use spawn::update::{UpdateCheck, check_for_updates, update_spawn};
+use spawn::utils::{DesktopAction, DesktopEntry, LaunchCommand, is_env_assignment, is_spawn_managed, SYSTEM_APPLICATIONS_DIR, applications_dir, default_working_dir, desktop_dir, desktop_entry_content, desktop_file_name, find_fuzzy_matches, find_in_path, find_matches, format_age, is_glob, matches_glob, format_game_name, generate_desktop_entry, guess_wm_class, install_theme_icon, read_desktop_entry, resolve_fuzzy_path, rewrite_desktop_entry, set_desktop_exec, set_executable_permission, theme_icon_files, theme_icon_name, install_dir_name, validate_game_name};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Turns a Linux game archive into a runnable desktop application")]
 struct Args {
-    /// Path to the game folder or .tar.gz archive
+    /// Path to the game folder or archive, an http(s) URL to download it from, or - to read it from stdin (with --name)
     path: Option<PathBuf>,
 
     /// Override the game name
@@ -32,6 +43,26 @@ struct Args {
     #[arg(short, long)]
     icon: Option<PathBuf>,
 
+    /// Use the icon embedded in the executable (an AppImage's .DirIcon) instead of loose image files. Runs the AppImage to unpack it
+    #[arg(long, conflicts_with = "icon")]
+    icon_from_exe: bool,
+
+    /// Download the icon from this URL (PNG, JPEG or SVG). Falls back to the usual icon search if that fails
+    #[arg(long, value_name = "URL", conflicts_with_all = ["icon", "icon_from_exe"])]
+    icon_url: Option<String>,
+
+    /// Install archives into this folder instead of asking (the folder is created if needed)
+    #[arg(long, value_name = "PATH")]
+    output_dir: Option<PathBuf>,
+
+    /// Read and save settings in this file instead of ~/.config/spawn/config.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Install archives into whichever of install_dir and install_dir_candidates has the most free space
+    #[arg(long, conflicts_with_all = ["output_dir", "system"])]
+    auto_install_dir: bool,
+
     /// Set the default search directory
     #[arg(long)]
     set_search_dir: Option<PathBuf>,
@@ -40,253 +71,1848 @@ struct Args {
     #[arg(long)]
     set_install_dir: Option<PathBuf>,
 
+    /// Set where shortcuts are created, e.g. `menu` or `menu,desktop`
+    #[arg(long, value_enum, value_delimiter = ',', num_args = 1.., value_name = "TARGETS")]
+    set_desktop_targets: Option<Vec<DesktopTarget>>,
+
+    /// How many folder levels deep to look for the executable and icon (overrides discovery_depth)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..=MAX_DISCOVERY_DEPTH as u64))]
+    depth: Option<u64>,
+
     /// Show what would happen without making any changes
     #[arg(long)]
     dry_run: bool,
 
+    /// Print the shortcut the install would create to stdout instead of writing it. Implies --dry-run
+    #[arg(long, conflicts_with_all = ["no_desktop", "json", "batch"])]
+    print_desktop_entry: bool,
+
     /// Update Spawn to the latest version from GitHub
     #[arg(long)]
     update: bool,
 
-    /// Uninstall a game and remove its shortcuts
+    /// With --update, install a release binary even when it has no published checksum
+    #[arg(long, requires = "update")]
+    allow_unverified: bool,
+
+    /// Never contact GitHub to look for a newer version of Spawn (also: check_for_updates = false, or SPAWN_NO_UPDATE_CHECK=1)
+    #[arg(long, conflicts_with = "update_timeout")]
+    no_update_check: bool,
+
+    /// Seconds each attempt at the new-version check may take (overrides update_timeout_secs)
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    update_timeout: Option<u64>,
+
+    /// Uninstall a game and remove its shortcuts. Wildcards (`"Final Fantasy*"`) uninstall every match after asking
     #[arg(long)]
     uninstall: Option<String>,
 
+    /// Reverse the last install: remove the folder, shortcuts and Steam entry it created
+    #[arg(long)]
+    undo: bool,
+
     /// Add the game to Steam as a Non-Steam Game (Experimental)
     #[arg(long)]
     steam: bool,
+
+    /// Folder the game starts in (the shortcut's Path=), relative to the install folder. Defaults to the executable's folder
+    #[arg(long, value_name = "PATH")]
+    working_dir: Option<PathBuf>,
+
+    /// Steam collection to sort the shortcut into with --steam (repeatable)
+    #[arg(long = "tag", value_name = "NAME")]
+    tags: Vec<String>,
+
+    /// Add a shortcut for another program in the game, e.g. `Editor=bin/editor` (relative to the install folder; repeatable)
+    #[arg(long = "extra-exec", value_name = "NAME=PATH", value_parser = parse_extra_exec)]
+    extra_execs: Vec<(String, PathBuf)>,
+
+    /// Also add shortcuts for level editors and dedicated servers found next to the game
+    #[arg(long)]
+    extra_launchers: bool,
+
+    /// Add a right-click entry to the shortcut that runs COMMAND from the install folder, e.g. "Launch Windowed:./game -windowed" (repeatable)
+    #[arg(long = "action", value_name = "NAME:COMMAND", value_parser = parse_desktop_action)]
+    actions: Vec<DesktopAction>,
+
+    /// Add an "Open Folder" right-click entry to the shortcut that shows the install folder in the file manager
+    #[arg(long)]
+    open_folder_action: bool,
+
+    /// Window class the game reports (StartupWMClass), for taskbar icon matching. Guessed from the executable name if omitted
+    #[arg(long, value_name = "CLASS")]
+    wm_class: Option<String>,
+
+    /// Extra arguments or KEY=VALUE environment variables to launch the game with
+    #[arg(long, allow_hyphen_values = true)]
+    launch_options: Option<String>,
+
+    /// Set an environment variable for the game, e.g. `DRI_PRIME=1` (repeatable). Applies to the shortcut, Steam and --open, and is remembered on reinstall
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    envs: Vec<String>,
+
+    /// Run a Windows build through a compatibility layer
+    #[arg(long, value_enum)]
+    compat: Option<CompatTool>,
+
+    /// Launch the game inside a sandbox: read-only system, writable game folder, no network
+    #[arg(long, value_enum)]
+    sandbox: Option<SandboxTool>,
+
+    /// Let a sandboxed game use the network
+    #[arg(long, requires = "sandbox")]
+    allow_network: bool,
+
+    /// Password for an encrypted zip archive. Asked for (without echo) when needed and not given
+    #[arg(long, value_name = "PASS")]
+    password: Option<String>,
+
+    /// Show a summary of everything the install will do and ask before starting. Installs to the default folder
+    #[arg(long, conflicts_with_all = ["yes", "dry_run", "batch", "auto_install_dir"])]
+    confirm: bool,
+
+    /// Never prompt: pick the best match, use the default install dir and overwrite existing installs
+    #[arg(short, long, visible_alias = "force")]
+    yes: bool,
+
+    /// Install for every user: into system_install_dir (default /opt/games) with the shortcut in
+    /// /usr/share/applications. Needs root; also applies to --uninstall
+    #[arg(long)]
+    system: bool,
+
+    /// What to do when the game is already installed (overrides overwrite_policy)
+    #[arg(long, value_enum, value_name = "POLICY")]
+    overwrite: Option<OverwritePolicy>,
+
+    /// Replace an existing install without asking, keeping save folders (saves, savegames, userdata...)
+    #[arg(long)]
+    reinstall: bool,
+
+    /// After finding the executable, list any shared libraries it needs that aren't installed
+    #[arg(long)]
+    check_deps: bool,
+
+    /// After installing, check that the executable, shortcut folder and icon exist and the shortcut is valid
+    #[arg(long)]
+    verify_after: bool,
+
+    /// Copy a game folder into the install directory instead of using it where it is, so the original can be deleted
+    #[arg(long)]
+    copy_dir: bool,
+
+    /// Link an AppImage into the install directory instead of copying it (the original must stay where it is)
+    #[arg(long)]
+    symlink: bool,
+
+    /// Before extracting, check whether the archive looks like a game that's already installed and offer to skip it
+    #[arg(long)]
+    dedupe: bool,
+
+    /// With --reinstall, also keep paths matching this pattern, relative to the game folder (repeatable)
+    #[arg(long, value_name = "SUBPATH", requires = "reinstall")]
+    preserve: Vec<String>,
+
+    /// Skip creating .desktop shortcuts (useful for headless installs)
+    #[arg(long)]
+    no_desktop: bool,
+
+    /// Don't remind me to restart Steam after adding a shortcut
+    #[arg(long)]
+    no_steam_restart_hint: bool,
+
+    /// Launch the game right after installing it, to check that it starts
+    #[arg(long, conflicts_with = "batch")]
+    open: bool,
+
+    /// Verify the archive against this SHA-256 before installing (also read from <archive>.sha256)
+    #[arg(long, value_name = "HASH")]
+    sha256: Option<String>,
+
+    /// Steam account ID to add shortcuts to when several exist
+    #[arg(long, value_name = "ID")]
+    steam_user: Option<String>,
+
+    /// List files in the search directory matching a name, without installing
+    #[arg(long, value_name = "QUERY")]
+    search: Option<String>,
+
+    /// Desktop menu category (repeatable, defaults to Game)
+    #[arg(long = "category", value_name = "NAME")]
+    categories: Vec<String>,
+
+    /// Description shown in the menu and matched by menu search (pass "" to leave it out)
+    #[arg(long, value_name = "TEXT", default_value = "Installed with Spawn")]
+    comment: String,
+
+    /// Comma-separated search terms for the menu, e.g. a subtitle or alternate name
+    #[arg(long, value_delimiter = ',', value_name = "CSV")]
+    keywords: Vec<String>,
+
+    /// Run setup scripts bundled with the game (install.sh, setup.sh) without asking
+    #[arg(long, conflicts_with = "no_run_setup")]
+    run_setup: bool,
+
+    /// Never run bundled setup scripts
+    #[arg(long)]
+    no_run_setup: bool,
+
+    /// Print a shell completion script to stdout
+    #[arg(long, value_enum, value_name = "SHELL")]
+    completions: Option<clap_complete::Shell>,
+
+    /// Rename an installed game, its folder, shortcuts and Steam entry
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    rename: Option<Vec<String>>,
+
+    /// Edit an installed game's launch arguments and environment in $EDITOR, then update its shortcuts
+    #[arg(long, value_name = "GAME")]
+    edit: Option<String>,
+
+    /// List installed games and whether they have a shortcut
+    #[arg(long)]
+    list: bool,
+
+    /// Show every install Spawn has done, with timestamps
+    #[arg(long)]
+    history: bool,
+
+    /// Check for the programs, folders and network access Spawn needs, and report what's missing. Changes nothing
+    #[arg(long)]
+    doctor: bool,
+
+    /// Print the settings in effect (config file plus overrides such as --depth) as TOML, or JSON with --json. Changes nothing
+    #[arg(long, conflicts_with_all = ["print_install_dir", "print_search_dir"])]
+    print_config: bool,
+
+    /// Print the folder games are installed into (system_install_dir with --system). Changes nothing
+    #[arg(long, conflicts_with = "print_search_dir")]
+    print_install_dir: bool,
+
+    /// Print the folder searched for downloaded games. Changes nothing
+    #[arg(long)]
+    print_search_dir: bool,
+
+    /// Print machine-readable JSON instead of formatted text (for installs and --list)
+    #[arg(long)]
+    json: bool,
+
+    /// Install every archive in the directory given as PATH
+    #[arg(long, conflicts_with_all = ["name", "icon", "sha256"])]
+    batch: bool,
+
+    /// Once an archive is installed, move it into an `installed` folder next to it (the default) or delete it. AppImages are moved instead of copied
+    #[arg(long, value_enum, value_name = "ACTION", num_args = 0..=1, default_missing_value = "move", conflicts_with = "symlink")]
+    cleanup_archive: Option<ArchiveCleanup>,
+
+    /// Keep the downloaded file after installing from a URL, or the file saved from stdin
+    #[arg(long)]
+    keep_download: bool,
+
+    /// Show debug output (-v) or trace every path considered (-vv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only print warnings and errors (to stderr) and requested output such as --json; no colors or progress bars
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+/// Sends Spawn's log output to stderr; silent unless `-v` is given or `RUST_LOG` is set.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => log::LevelFilter::Off,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_module("spawn", level).format_timestamp(None).parse_default_env().init();
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
-    let mut config = load_config();
+    // Errors are plain text; the color is added once, here
+    run().map_err(|e| anyhow!("{} {:#}", "✖".red(), e))
+}
+
+fn run() -> Result<()> {
+    let mut args = Args::parse();
+    args.dry_run |= args.print_desktop_entry;
+
+    if let Some(shell) = args.completions {
+        clap_complete::generate(shell, &mut Args::command(), "spawn", &mut std::io::stdout());
+        return Ok(());
+    }
+
+    init_logging(args.verbose);
+    if args.quiet {
+        colored::control::set_override(false);
+    }
+    set_reporter(Box::new(TerminalReporter::new(args.quiet)));
+
+    // Printing settings or checking the setup must not create or rewrite the config file
+    let printing = args.print_config || args.print_install_dir || args.print_search_dir;
+    let mut config = if printing || args.doctor { peek_config(args.config.as_deref()) } else { load_config(args.config.as_deref()) };
+    if let Some(depth) = args.depth {
+        config.discovery_depth = depth as usize;
+    }
+    if let Some(policy) = args.overwrite {
+        config.overwrite_policy = policy;
+    }
+    if printing {
+        return print_settings(&args, config);
+    }
+    if args.doctor {
+        if args.system {
+            use_system_manifest();
+            config.install_dir = config.system_install_dir.clone();
+        }
+        run_doctor(&args, &config)?;
+        return Ok(());
+    }
+    log::debug!("Config: search_dir={:?} install_dir={:?} proton_path={:?} {:?}", config.search_dir, config.install_dir, config.proton_path, config.search_scope());
 
+    // Settings are changed in what the file says, so overrides from flags or the environment aren't saved with them
     if let Some(new_dir) = args.set_search_dir {
         let abs_dir = new_dir.canonicalize().context("Failed to resolve new search directory")?;
-        config.search_dir = abs_dir;
-        save_config(&config)?;
-        println!("✔ Search directory updated to: {:?}", config.search_dir);
+        let _lock = lock_config(args.config.as_deref())?;
+        let mut stored = load_stored_config(args.config.as_deref());
+        stored.search_dir = abs_dir;
+        save_config(&stored, args.config.as_deref())?;
+        success(format!("Search directory updated to: {:?}", stored.search_dir));
+        warn_if_env_override("SPAWN_SEARCH_DIR");
+        return Ok(());
+    }
+
+    if let Some(mut targets) = args.set_desktop_targets {
+        targets.sort();
+        targets.dedup();
+        let _lock = lock_config(args.config.as_deref())?;
+        let mut stored = load_stored_config(args.config.as_deref());
+        stored.desktop_targets = targets;
+        save_config(&stored, args.config.as_deref())?;
+        let names = stored.desktop_targets.iter().map(|t| format!("{:?}", t).to_lowercase()).collect::<Vec<_>>();
+        success(format!("Shortcuts will be created in: {}", names.join(", ")));
         return Ok(());
     }
 
     if let Some(new_dir) = args.set_install_dir {
         let abs_dir = new_dir.canonicalize().context("Failed to resolve new install directory")?;
-        config.install_dir = abs_dir;
-        save_config(&config)?;
-        println!("{} Install directory updated to: {:?}", "✔".green(), config.install_dir);
+        let _lock = lock_config(args.config.as_deref())?;
+        let mut stored = load_stored_config(args.config.as_deref());
+        stored.install_dir = abs_dir;
+        save_config(&stored, args.config.as_deref())?;
+        success(format!("Install directory updated to: {:?}", stored.install_dir));
+        warn_if_env_override("SPAWN_INSTALL_DIR");
         return Ok(());
     }
 
     if args.update {
-        return update_spawn();
+        update_spawn(args.allow_unverified)?;
+        return Ok(());
+    }
+
+    if args.system {
+        use_system_manifest();
+        config.install_dir = config.system_install_dir.clone();
+        let read_only = args.dry_run || args.list || args.history || args.search.is_some();
+        if !read_only {
+            check_system_access(&config)?;
+        }
     }
 
     if let Some(game_to_uninstall) = args.uninstall {
-        return uninstall_game(&game_to_uninstall, &config.install_dir, args.dry_run);
+        if is_glob(&game_to_uninstall) {
+            return uninstall_matching(&game_to_uninstall, &config.install_dir, args.system, !args.yes, args.dry_run);
+        }
+        return uninstall_game(&game_to_uninstall, &config.install_dir, args.system, !args.yes, args.dry_run);
+    }
+
+    if args.undo {
+        return undo_last_install(!args.yes, args.dry_run);
+    }
+
+    if let Some(names) = args.rename {
+        validate_game_name(&names[1])?;
+        return rename_game(&names[0], &names[1], &config, !args.yes, args.dry_run);
+    }
+
+    if let Some(game) = args.edit {
+        return edit_launch_settings(&game, !args.yes);
     }
 
-    let input = args.path.ok_or_else(|| anyhow!("{} No path provided\nHint: Use 'spawn <PATH>' or 'spawn <PARTIAL_NAME>'", "✖".red()))?;
+    if let Some(query) = args.search {
+        return search_downloads(&query, &config.search_dir);
+    }
+
+    if args.list {
+        return list_games(&config.install_dir, args.system, args.json);
+    }
+
+    if args.history {
+        return print_history();
+    }
+
+    let input = args.path.clone().ok_or(SpawnError::NoPath)?;
+    if let Some(name) = &args.name {
+        validate_game_name(name)?;
+    }
+
+    if let Some(dir) = &args.output_dir {
+        config.install_dir = std::path::absolute(dir).context("Failed to resolve output directory")?;
+    }
+
+    // A JSON plan replaces all of the dry run's progress output so stdout stays parseable
+    if args.dry_run && args.json && !args.batch {
+        let plan = plan_install(&input, &args, &config)?;
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
 
-    println!("{} {} v{}", "▶".cyan(), "Spawn".bold(), env!("CARGO_PKG_VERSION"));
+    step(format!("{} v{}", "Spawn".bold(), env!("CARGO_PKG_VERSION")));
 
     if args.dry_run {
-        println!("{} Running in DRY RUN mode. No changes will be made.", "⚠".yellow().bold());
+        warning("Running in DRY RUN mode. No changes will be made.");
     }
 
-    let input_path = resolve_fuzzy_path(&input, &config.search_dir)?;
+    if args.batch {
+        return install_batch(&input, &args, &config);
+    }
+
+    let mut downloaded = None;
+    let input_path = if input.as_os_str() == STDIN_INPUT {
+        let name = args.name.as_deref().ok_or(SpawnError::StdinNeedsName)?;
+        if args.dry_run {
+            step("Would read the archive from stdin and install it");
+            return Ok(());
+        }
+        let path = save_stdin(&config.search_dir, &install_dir_name(&format_game_name(name)))?;
+        let linked = args.symlink && path.to_string_lossy().ends_with(".AppImage");
+        if !args.keep_download && !linked {
+            downloaded = Some(path.clone());
+        }
+        path
+    } else if let Some(url) = input.to_str().filter(|s| is_url(s)) {
+        match download_game(url, &config.search_dir, args.dry_run)? {
+            Some((path, created)) => {
+                // A linked AppImage runs from the downloaded file, so that one has to stay
+                let linked = args.symlink && path.to_string_lossy().ends_with(".AppImage");
+                if created && !args.keep_download && !linked {
+                    downloaded = Some(path.clone());
+                }
+                path
+            }
+            None => {
+                step("Would install the downloaded file");
+                return Ok(());
+            }
+        }
+    } else {
+        resolve_fuzzy_path(&input, &config.search_dir, !args.yes)?
+    };
     let input_path = input_path.canonicalize().context("Failed to resolve input path")?;
 
     if !input_path.exists() {
-        return Err(anyhow!("{} Path does not exist: {:?}\nHint: Ensure the path is correct and accessible", "✖".red(), input_path));
+        return Err(SpawnError::PathNotFound(input_path.to_path_buf()).into());
     }
 
-    println!("{} Installing game from: {:?}", "▶".cyan(), input_path);
+    if args.confirm && !confirm_plan(&plan_install(&input_path, &args, &config)?)? {
+        step("Nothing was changed");
+        return Ok(());
+    }
 
-    let game_dir = if input_path.is_file() {
-        println!("{} Where should I install this? [Default: {:?}]", "▶".cyan(), config.install_dir);
-        println!("  (Press Enter to use default, or type a new path)");
-        
-        let mut input_dir = String::new();
-        std::io::stdin().read_line(&mut input_dir).context("Failed to read input")?;
-        let input_dir = input_dir.trim();
-        
-        let target_parent = if input_dir.is_empty() {
-            config.install_dir.clone()
-        } else {
-            PathBuf::from(input_dir)
-        };
+    let Some(entry) = install_game(&input_path, &args, &config)? else {
+        return Ok(());
+    };
+
+    if let Some(download) = downloaded {
+        match fs::remove_file(&download) {
+            Ok(()) => success("Removed downloaded file (use --keep-download to keep it)"),
+            Err(e) => warning(format!("Failed to remove downloaded file {:?}: {}", download, e)),
+        }
+    } else if let Some(cleanup) = args.cleanup_archive {
+        clean_up_after_install(&input_path, cleanup, args.dry_run);
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entry)?);
+        return Ok(());
+    }
+
+    let cache = update_cache_path(args.config.as_deref());
+    let update_check = UpdateCheck {
+        timeout: Duration::from_secs(args.update_timeout.unwrap_or(config.update_timeout_secs)),
+        interval: Duration::from_secs(config.update_check_interval_hours * 60 * 60),
+        cache: cache.as_deref(),
+    };
+    let allowed = config.check_for_updates && !args.no_update_check
+        && std::env::var_os("SPAWN_NO_UPDATE_CHECK").is_none_or(|v| v.is_empty() || v == "0");
+    if allowed && !args.quiet && let Some(new_version) = check_for_updates(&update_check) {
+        eprintln!("\n✨ A new version of Spawn (v{}) is available!", new_version.bold().yellow());
+        eprintln!("   Run 'spawn --update' to update.");
+    }
+
+    Ok(())
+}
 
-        if !args.dry_run && !target_parent.exists() {
-            fs::create_dir_all(&target_parent).context("Failed to create install directory")?;
+/// Runs the install pipeline for one archive, AppImage or game folder. Returns `None` when
+/// `--dedupe` found the game already installed and it was skipped.
+fn install_game(input_path: &Path, args: &Args, config: &Config) -> Result<Option<ManifestEntry>> {
+    step(format!("Installing game from: {:?}", input_path));
+
+    if input_path.is_file() {
+        if let Some(expected) = expected_checksum(input_path, args.sha256.as_deref()) {
+            verify_checksum(input_path, &expected)?;
+        }
+    } else {
+        if args.sha256.is_some() {
+            warning(format!("Ignoring --sha256: {:?} is a directory", input_path));
+        }
+        if args.output_dir.is_some() {
+            warning(format!("Ignoring --output-dir: {:?} is a directory and is used in place", input_path));
         }
+    }
+    if args.symlink && input_path.is_file() && !input_path.to_string_lossy().ends_with(".AppImage") {
+        warning("Ignoring --symlink: only AppImages can be linked; archives are always extracted");
+    }
+
+    if args.dedupe && input_path.is_file() && let Some(existing) = find_duplicate_install(input_path, config)?
+        && !confirm_duplicate_install(&existing, args)?
+    {
+        return Ok(None);
+    }
 
-        if input_path.to_string_lossy().ends_with(".AppImage") {
-            install_appimage(&input_path, &target_parent, args.dry_run)?
+    // Held until the install is finished, so a second Spawn installing into the same folder
+    // waits instead of extracting over this one while it's being set up
+    let mut install_lock = None;
+    let (mut game_dir, install_root, launch) = if input_path.is_file() || (args.copy_dir && input_path.is_dir()) {
+        let target_parent = if args.auto_install_dir {
+            auto_install_dir(input_path, config)
+        } else if args.yes || args.confirm || args.output_dir.is_some() {
+            config.install_dir.clone()
         } else {
-            extract_archive(&input_path, &target_parent, args.dry_run)?
+            eprintln!("{} Where should I install this? [Default: {:?}]", "▶".cyan(), config.install_dir);
+            eprintln!("  (Press Enter to use default, or type a new path)");
+
+            let mut input_dir = String::new();
+            std::io::stdin().read_line(&mut input_dir).context("Failed to read input")?;
+            let input_dir = input_dir.trim();
+
+            if input_dir.is_empty() {
+                config.install_dir.clone()
+            } else {
+                PathBuf::from(input_dir)
+            }
+        };
+
+        let dir_name = args.name.as_deref().map(|name| named_install_dir(name, &target_parent)).transpose()?;
+        if !args.dry_run {
+            let target_dir = match &dir_name {
+                Some(name) => target_parent.join(name),
+                None => install_target(input_path, &target_parent)?,
+            };
+            install_lock = Some(lock_install_dir(&target_dir)?);
         }
+        let options = InstallOptions {
+            dry_run: args.dry_run,
+            interactive: !args.yes,
+            preserve: args.reinstall.then_some(args.preserve.as_slice()),
+            symlink: args.symlink,
+            overwrite: config.overwrite_policy,
+            dir_name: dir_name.as_deref(),
+            password: args.password.as_deref(),
+            move_appimage: args.cleanup_archive.is_some(),
+            name: args.name.as_deref(),
+            compat: args.compat,
+        };
+        let installed = install_archive(input_path, &target_parent, config, &options)?;
+        (installed.game_dir, installed.install_root, installed.launch)
     } else {
-        input_path
+        (input_path.to_path_buf(), None, None)
     };
 
-    let (executable, icon) = if args.dry_run && !game_dir.exists() {
-        println!("{} Would discover executable and icon inside the archive", "▶".cyan());
-        (PathBuf::from("would_be_executable"), None)
+    // What the game is called, so a binary with the same name wins over helpers and tools
+    let mut expected_names = args.name.iter().cloned().collect::<Vec<_>>();
+    expected_names.extend([install_root.as_deref(), Some(game_dir.as_path())].into_iter()
+        .flatten()
+        .filter_map(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned())));
+
+    // A .desktop file shipped with the game knows its name and launch arguments; flags still win
+    let bundled = find_bundled_launcher(&game_dir);
+    if let Some(launcher) = &bundled {
+        success(format!("Using the game's bundled shortcut: {:?}", launcher.source.file_name().unwrap_or_default()));
+    }
+
+    if let Some(url) = args.icon_url.as_deref().filter(|_| args.dry_run) {
+        step(format!("Would download the icon from {}", url));
+    }
+    let (executable, icon, compat) = if args.dry_run && !game_dir.exists() && input_path.is_file() {
+        let (executable, icon, root) = preview_archive(input_path, &game_dir, args.compat, config.search_scope(), &expected_names);
+        // Extraction unwraps the archive's top folders, and the game is named after the one it runs from
+        game_dir.push(root);
+        (executable.unwrap_or_else(|| PathBuf::from("would_be_executable")), icon, args.compat)
+    } else if args.dry_run && !game_dir.exists() {
+        // A folder that would be copied is searched where it is, with the results moved into the copy
+        let LaunchTarget { executable, compat, icon } = discover_launch_target(input_path, args.compat, config, !args.yes, &expected_names)?;
+        let icon = args.icon.clone().or(icon);
+        let rebase = |p: PathBuf| p.strip_prefix(input_path).map(|rel| game_dir.join(rel)).unwrap_or(p);
+        step(format!("Would use executable: {:?}", executable.file_name().unwrap_or_default()));
+        (rebase(executable), icon.map(rebase), compat)
     } else {
-        let executable = discover_executable(&game_dir)?;
-        println!("{} Discovered executable: {:?}", "✔".green(), executable.file_name().unwrap_or_default());
+        // Installed games were searched while installing; a folder used in place is searched now
+        let LaunchTarget { executable, compat, icon: discovered_icon } = match launch {
+            Some(target) => target,
+            None => discover_launch_target(&game_dir, args.compat, config, !args.yes, &expected_names)?,
+        };
+        success(format!("Discovered executable: {:?}", executable.file_name().unwrap_or_default()));
+        if let Some(tool) = compat {
+            success(format!("Will launch through {}", tool.label()));
+        } else {
+            warn_on_arch_mismatch(&executable);
+            if args.check_deps {
+                check_dependencies(&executable);
+            }
+        }
 
-        let icon = if let Some(icon_path) = args.icon {
-            Some(icon_path)
+        // A failed download falls back to the icon discovery would find
+        let downloaded_icon = args.icon_url.as_deref().filter(|_| !args.dry_run).and_then(|url| match download_icon(url, &game_dir) {
+            Ok(icon) => Some(icon),
+            Err(e) => {
+                warning(format!("Failed to download the icon, looking for one in the game instead: {}", e));
+                None
+            }
+        });
+        let icon = if let Some(icon_path) = &args.icon {
+            Some(icon_path.clone())
+        } else if downloaded_icon.is_some() {
+            downloaded_icon
+        } else if args.icon_from_exe {
+            extract_embedded_icon(&executable, &game_dir, true)
         } else {
-            discover_icon(&game_dir)
+            let icon = discovered_icon.or_else(|| extract_embedded_icon(&executable, &game_dir, false));
+            if icon.is_none() && executable.extension().is_some_and(|e| e.eq_ignore_ascii_case("appimage")) {
+                step("Pass --icon-from-exe to use the icon inside the AppImage (this runs the AppImage to unpack it)");
+            }
+            icon
         };
         if let Some(ref i) = icon {
             let name = i.file_name().unwrap_or_else(|| std::ffi::OsStr::new(""));
-            println!("{} Found icon: {:?}", "✔".green(), name);
+            success(format!("Found icon: {:?}", name));
+        }
+        (executable, icon, compat)
+    };
+
+    let wrapper = match compat {
+        Some(tool) => wrapper_command(tool, &game_dir, config)?,
+        None => Vec::new(),
+    };
+    let mut launch = LaunchCommand::new(args.launch_options.as_deref(), wrapper)?;
+    if args.launch_options.is_none() && let Some(launcher) = &bundled {
+        launch.env.extend(launcher.env.iter().cloned());
+        launch.args = launcher.args.clone();
+    }
+    // What was stored when the game was installed before, which a reinstall keeps unless it's given again
+    let previous = load_manifest()?.games.into_iter().find(|e| e.game_dir == game_dir);
+    if args.launch_options.is_none() && let Some(previous) = &previous {
+        for (key, value) in &previous.env {
+            if !launch.env.iter().any(|e| e.split_once('=').is_some_and(|(k, _)| k == key)) {
+                launch.env.push(format!("{}={}", key, value));
+            }
         }
-        (executable, icon)
+        launch.args = previous.launch_args.clone();
+    }
+    for assignment in &args.envs {
+        let key = assignment.split_once('=').map_or(assignment.as_str(), |(k, _)| k);
+        launch.env.retain(|e| e.split_once('=').is_none_or(|(k, _)| k != key));
+        launch.env.push(assignment.clone());
+    }
+    // Games that ship their own libraries sometimes need to be told where they are
+    let bundled_libs = executable.parent().filter(|dir| !dir.as_os_str().is_empty()).map(|dir| dir.join("lib")).filter(|lib| lib.is_dir());
+    if let Some(lib) = bundled_libs && !launch.env.iter().any(|e| e.starts_with("LD_LIBRARY_PATH=")) {
+        step(format!("The game ships libraries in {:?}. If it fails to start, reinstall with --env LD_LIBRARY_PATH={}", lib, lib.display()));
+    }
+    // A working folder, extra launchers, right-click actions and Steam tags are kept on reinstall, like launch settings
+    let working_dir_override = match &args.working_dir {
+        Some(dir) => Some(game_dir.join(dir).components().collect()),
+        None => previous.as_ref().and_then(|e| e.working_dir.clone()),
+    };
+    let mut extras: Vec<(String, PathBuf)> = args.extra_execs.iter()
+        .map(|(label, path)| (label.clone(), game_dir.join(path).components().collect()))
+        .collect();
+    if args.extra_launchers && game_dir.exists() {
+        for (label, path) in discover_extra_executables(&game_dir, &executable) {
+            if !extras.iter().any(|(l, p)| *l == label || *p == path) {
+                extras.push((label, path));
+            }
+        }
+    }
+    if extras.is_empty() && let Some(previous) = &previous {
+        extras = previous.extra_launchers.iter().map(|l| (l.label.clone(), l.executable.clone())).collect();
+    }
+    let mut actions = args.actions.clone();
+    if args.open_folder_action {
+        actions.insert(0, DesktopAction::open_folder());
+    }
+    if actions.is_empty() && let Some(previous) = &previous {
+        actions = previous.actions.clone();
+    }
+    let tags = match previous {
+        Some(previous) if args.tags.is_empty() => previous.tags,
+        _ => args.tags.clone(),
     };
+    let working_dir = working_dir_override.clone().unwrap_or_else(|| default_working_dir(&executable, &game_dir));
+    if args.working_dir.is_some() && !args.dry_run && !working_dir.is_dir() {
+        warning(format!("Working folder {:?} does not exist; the game may fail to start", working_dir));
+    }
+
+    if let Some(tool) = args.sandbox {
+        launch.wrap_with(sandbox_command(tool, &game_dir, args.allow_network)?);
+        success(format!("Will launch inside {}{}", tool.label(), if args.allow_network { "" } else { " (no network)" }));
+    }
+
+    let scripts = if game_dir.exists() { discover_setup_scripts(&game_dir) } else { Vec::new() };
+    if args.dry_run {
+        for script in &scripts {
+            step(format!("Would offer to run setup script: {:?}", script.file_name().unwrap_or_default()));
+        }
+    } else {
+        let policy = if args.run_setup { Some(true) } else if args.no_run_setup { Some(false) } else { None };
+        run_setup_scripts(&game_dir, &scripts, policy, !args.yes)?;
+    }
 
     if !args.dry_run {
         set_executable_permission(&executable)?;
-        println!("{} Fixed executable permissions", "✔".green());
+        success("Fixed executable permissions");
     } else if game_dir.exists() {
-        println!("{} Would fix executable permissions", "▶".cyan());
+        step("Would fix executable permissions");
     }
 
-    let game_name = args.name.as_deref().unwrap_or_else(|| {
-        game_dir.file_name().and_then(|n| n.to_str()).unwrap_or("Unknown Game")
-    });
-    let game_name = format_game_name(&game_name);
+    let game_name = match (args.name.as_deref(), bundled.and_then(|l| l.name)) {
+        (Some(name), _) => format_game_name(name),
+        // Bundled names are already written for display, e.g. "Dead Cells"
+        (None, Some(name)) => name,
+        (None, None) => format_game_name(game_dir.file_name().and_then(|n| n.to_str()).unwrap_or("Unknown Game")),
+    };
 
-    if !args.dry_run {
-        let desktop_files = generate_desktop_entry(&game_dir, &executable, &game_name, icon.as_deref())?;
-        for df in desktop_files {
-            println!("{} Shortcut created: {:?}", "✔".green(), df.file_name().unwrap_or_default());
+    // The per-user icon theme isn't visible to other accounts, so system installs use the file
+    // directly. A printed entry puts nothing in the theme, so it points at the icon file too.
+    let icon_name = if args.no_desktop || args.dry_run || args.system {
+        None
+    } else {
+        icon.as_deref().and_then(|i| install_theme_icon(i, &game_name))
+    };
+    let icon_value = icon_name.clone().or_else(|| icon.as_ref().map(|i| i.to_string_lossy().into_owned()));
+    let wm_class = args.wm_class.clone().or_else(|| guess_wm_class(&executable));
+    let desktop_entry = DesktopEntry {
+        game_dir: &game_dir,
+        working_dir: &working_dir,
+        executable: &executable,
+        name: &game_name,
+        icon: icon_value.as_deref(),
+        launch: &launch,
+        categories: &args.categories,
+        comment: &args.comment,
+        keywords: &args.keywords,
+        wm_class: wm_class.as_deref(),
+        actions: &actions,
+    };
+
+    let mut extra_launchers = Vec::new();
+    let desktop_files = if args.no_desktop {
+        step("Skipping desktop shortcuts (--no-desktop)");
+        Vec::new()
+    } else if !args.dry_run {
+        let desktop_files = generate_desktop_entry(&desktop_entry, &config.desktop_targets, args.system)?;
+        for df in &desktop_files {
+            success(format!("Shortcut created: {:?}", df.file_name().unwrap_or_default()));
+        }
+        if desktop_files.is_empty() {
+            warning("No shortcut was created, as desktop_targets leaves out the menu and there's no Desktop folder\nHint: Add it with --set-desktop-targets menu,desktop");
         }
+        extra_launchers = create_extra_launchers(&extras, &desktop_entry, &config.desktop_targets, args.system);
+        desktop_files
+    } else if args.print_desktop_entry {
+        print!("{}", desktop_entry_content(&desktop_entry));
+        Vec::new()
     } else {
-        println!("{} Would create desktop shortcuts for {}", "▶".cyan(), game_name.bold());
+        step(format!("Would create desktop shortcuts for {}", game_name.bold()));
+        for (label, path) in &extras {
+            step(format!("Would create a shortcut for {} {} ({:?})", game_name, label, path.file_name().unwrap_or_default()));
+        }
+        Vec::new()
+    };
+
+    let mut entry = ManifestEntry {
+        name: game_name.clone(),
+        install_dir: install_root,
+        game_dir: game_dir.clone(),
+        executable: executable.clone(),
+        icon: icon.clone(),
+        icon_name,
+        desktop_files,
+        env: BTreeMap::new(),
+        wrapper: Vec::new(),
+        launch_args: Vec::new(),
+        source: if input_path.is_file() { ArchiveFingerprint::of(input_path) } else { None },
+        working_dir: working_dir_override,
+        tags: tags.clone(),
+        extra_launchers,
+        actions,
+    };
+    entry.set_launch_command(&launch);
+    if !args.dry_run && let Err(e) = record_install(entry.clone()) {
+        warning(format!("Failed to update install manifest: {:?}", e));
     }
 
+    let mut added_to_steam = false;
     if args.steam {
-        if let Err(e) = add_to_steam(&game_name, &executable, icon.as_deref()) {
-            println!("{} Failed to add to Steam: {:?}", "⚠".yellow(), e);
+        let shortcut = SteamShortcut {
+            name: &game_name,
+            executable: &executable,
+            start_dir: &working_dir,
+            icon: icon.as_deref(),
+            launch: &launch,
+            tags: &tags,
+        };
+        match add_to_steam(&shortcut, args.steam_user.as_deref(), !args.yes) {
+            Ok(added) => {
+                added_to_steam = added;
+                if added && !args.no_steam_restart_hint && !args.quiet {
+                    eprintln!("  (Restart Steam to see changes)");
+                }
+            }
+            Err(e) => warning(format!("Failed to add to Steam: {}", e)),
         }
     }
 
-    println!("\n🎮 {} is ready to play!", game_name.bold().green());
+    if !args.dry_run && let Err(e) = append_history(&game_name, input_path, &game_dir, added_to_steam) {
+        warning(format!("Failed to update install history: {:?}", e));
+    }
+    let last = LastInstall { name: game_name.clone(), game_dir: game_dir.clone(), added_to_steam };
+    if !args.dry_run && let Err(e) = record_last_install(&last) {
+        warning(format!("Failed to record the install for --undo: {:?}", e));
+    }
+
+    if !args.json && !args.quiet {
+        if args.no_desktop {
+            eprintln!("\n🎮 {} is installed (no shortcut created). Launch it with: {:?}", game_name.bold().green(), executable);
+        } else {
+            eprintln!("\n🎮 {} is ready to play!", game_name.bold().green());
+        }
+    }
+
+    if args.verify_after && !args.dry_run {
+        verify_install(&entry)?;
+    }
+
+    drop(install_lock);
+
+    if args.open && !args.dry_run {
+        open_game(&game_name, &executable, &working_dir, &launch)?;
+    }
+    Ok(Some(entry))
+}
 
-    if let Some(new_version) = check_for_updates() {
-        println!("\n✨ A new version of Spawn (v{}) is available!", new_version.bold().yellow());
-        println!("   Run 'spawn --update' to update.");
+/// Points out that a setting just saved is still overridden in this environment.
+fn warn_if_env_override(key: &str) {
+    if std::env::var_os(key).is_some_and(|v| !v.is_empty()) {
+        warning(format!("{} is set, so it still takes precedence over the config file", key));
     }
+}
 
+/// Answers `--print-config`, `--print-install-dir` and `--print-search-dir` on stdout.
+fn print_settings(args: &Args, mut config: Config) -> Result<()> {
+    if args.system {
+        config.install_dir = config.system_install_dir.clone();
+    }
+    if args.print_install_dir {
+        println!("{}", config.install_dir.display());
+    } else if args.print_search_dir {
+        println!("{}", config.search_dir.display());
+    } else if args.json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+    } else {
+        print!("{}", toml::to_string(&config).context("Failed to serialize config")?);
+    }
     Ok(())
 }
 
-fn check_for_updates() -> Option<String> {
-    let url = "https://raw.githubusercontent.com/Anayo-Anyafulu/Spawn/master/Cargo.toml";
-    let agent = ureq::AgentBuilder::new()
-        .timeout_read(Duration::from_secs(1))
-        .timeout_connect(Duration::from_secs(1))
-        .build();
+/// Parses `--extra-exec NAME=PATH`.
+fn parse_extra_exec(value: &str) -> Result<(String, PathBuf), String> {
+    match value.split_once('=') {
+        Some((name, path)) if !name.trim().is_empty() && !path.is_empty() => Ok((name.trim().to_string(), PathBuf::from(path))),
+        _ => Err(format!("expected NAME=PATH, e.g. Editor=bin/editor, got {:?}", value)),
+    }
+}
 
-    let response = match agent.get(url).call() {
-        Ok(r) => r,
-        Err(_) => return None,
-    };
-    let body = response.into_string().ok()?;
+/// Parses `--env KEY=VALUE`.
+fn parse_env_var(value: &str) -> Result<String, String> {
+    if is_env_assignment(value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("expected KEY=VALUE, e.g. DRI_PRIME=1, got {:?}", value))
+    }
+}
 
-    for line in body.lines() {
-        if line.trim().starts_with("version =") {
-            let version = line.split('"').nth(1)?;
-            if version != env!("CARGO_PKG_VERSION") {
-                return Some(version.to_string());
-            }
-            break;
+/// Parses `--action NAME:COMMAND`.
+fn parse_desktop_action(value: &str) -> Result<DesktopAction, String> {
+    match value.split_once(':') {
+        Some((name, command)) if !name.trim().is_empty() && !command.trim().is_empty() => {
+            Ok(DesktopAction { name: name.trim().to_string(), command: command.trim().to_string() })
         }
+        _ => Err(format!("expected NAME:COMMAND, e.g. \"Launch Windowed:./game -windowed\", got {:?}", value)),
     }
-    None
 }
 
-fn update_spawn() -> Result<()> {
-    println!("{} Updating Spawn...", "▶".cyan());
-    let status = Command::new("git")
-        .arg("pull")
-        .status()
-        .context("Failed to execute git pull")?;
+/// Creates shortcuts named "<game> <label>" for the game's other programs, sharing the game's
+/// icon, categories and environment but not its arguments. Programs that can't be set up are
+/// skipped with a warning, since the game itself is already installed.
+fn create_extra_launchers(extras: &[(String, PathBuf)], game: &DesktopEntry, targets: &[DesktopTarget], system: bool) -> Vec<ExtraLauncher> {
+    let mut launchers = Vec::new();
+    for (label, executable) in extras {
+        let name = format!("{} {}", game.name, label);
+        if !executable.is_file() {
+            warning(format!("Skipping the shortcut for {}: {:?} does not exist", name, executable));
+            continue;
+        }
+        if let Err(e) = set_executable_permission(executable) {
+            warning(format!("Failed to make {:?} executable: {}", executable, e));
+        }
 
-    if !status.success() {
-        return Err(anyhow!("{} git pull failed", "✖".red()));
+        let launch = LaunchCommand { args: Vec::new(), ..game.launch.clone() };
+        let working_dir = default_working_dir(executable, game.game_dir);
+        let wm_class = guess_wm_class(executable);
+        let entry = DesktopEntry {
+            working_dir: &working_dir,
+            executable,
+            name: &name,
+            launch: &launch,
+            wm_class: wm_class.as_deref(),
+            // The right-click actions belong to the game's own shortcut
+            actions: &[],
+            ..*game
+        };
+        match generate_desktop_entry(&entry, targets, system) {
+            Ok(desktop_files) => {
+                for df in &desktop_files {
+                    success(format!("Shortcut created: {:?}", df.file_name().unwrap_or_default()));
+                }
+                launchers.push(ExtraLauncher { label: label.clone(), executable: executable.clone(), desktop_files });
+            }
+            Err(e) => warning(format!("Failed to create the shortcut for {}: {}", name, e)),
+        }
     }
+    launchers
+}
 
-    let status = Command::new("cargo")
-        .arg("install")
-        .arg("--path")
-        .arg(".")
-        .status()
-        .context("Failed to execute cargo install")?;
+/// The install folder for `--name`: the game's display name with spaces as underscores, as uninstall expects,
+/// plus `_2`, `_3`... when that folder holds something else. A folder recorded for this same
+/// game is reused, so a reinstall replaces it.
+fn named_install_dir(name: &str, parent: &Path) -> Result<String, SpawnError> {
+    let game_name = format_game_name(name);
+    let base = install_dir_name(&game_name);
+    let manifest = load_manifest()?;
+    Ok((1..)
+        .map(|n| if n == 1 { base.clone() } else { format!("{}_{}", base, n) })
+        .find(|dir_name| {
+            let dir = parent.join(dir_name);
+            !dir.exists() || manifest.games.iter().any(|e| e.install_dir.as_ref() == Some(&dir) && e.name.eq_ignore_ascii_case(&game_name))
+        })
+        .unwrap_or(base))
+}
 
-    if !status.success() {
-        return Err(anyhow!("{} cargo install failed", "✖".red()));
+/// For `--auto-install-dir`: the candidate folder with the most free space that fits the game.
+fn auto_install_dir(archive: &Path, config: &Config) -> PathBuf {
+    let mut candidates = vec![config.install_dir.clone()];
+    candidates.extend(config.install_dir_candidates.iter().filter(|d| **d != config.install_dir).cloned());
+    if candidates.len() == 1 {
+        warning(format!("No install_dir_candidates configured, using {:?}", config.install_dir));
+        return config.install_dir.clone();
     }
 
-    println!("{} Spawn has been updated successfully!", "✔".green().bold());
-    Ok(())
+    let needed = unpacked_size(archive).unwrap_or(0);
+    match pick_install_dir(&candidates, needed) {
+        Some((dir, free)) => {
+            success(format!("Installing to {:?}: {} free, the most of {} candidates (the game needs about {})", dir, HumanBytes(free), candidates.len(), HumanBytes(needed)));
+            dir
+        }
+        None => {
+            warning(format!("None of the {} candidate folders has {} free, using {:?}", candidates.len(), HumanBytes(needed), config.install_dir));
+            config.install_dir.clone()
+        }
+    }
 }
 
-fn uninstall_game(game_name: &str, install_dir: &Path, dry_run: bool) -> Result<()> {
-    println!("{} Uninstalling {}...", "▶".cyan(), game_name.bold());
-    
+/// For `--dedupe`: finds an installed game made from the same archive, or whose executable has
+/// the name a quick listing of this archive would pick.
+fn find_duplicate_install(archive: &Path, config: &Config) -> Result<Option<ManifestEntry>> {
+    let executable_name = if archive.to_string_lossy().ends_with(".AppImage") {
+        archive.file_name().map(|n| n.to_string_lossy().into_owned())
+    } else {
+        // Named after the folder it would be extracted to, as in a dry run
+        let names = install_target(archive, &config.install_dir).ok()
+            .and_then(|dir| dir.file_name().map(|n| vec![n.to_string_lossy().into_owned()]))
+            .unwrap_or_default();
+        list_archive(archive).ok()
+            .and_then(|entries| preview_executable(&entries, config.search_scope(), &names))
+            .and_then(|e| e.file_name().map(|n| n.to_string_lossy().into_owned()))
+    };
+    // Generic launcher scripts say nothing about which game it is
+    let executable_name = executable_name.filter(|n| !matches!(n.as_str(), "start.sh" | "run.sh" | "launcher.sh"));
+
+    let manifest = load_manifest()?;
+    Ok(manifest.find_duplicate(ArchiveFingerprint::of(archive).as_ref(), executable_name.as_deref()).cloned())
+}
+
+/// Asks whether to install a game that looks already installed. `--reinstall` goes ahead,
+/// `--yes` skips it.
+fn confirm_duplicate_install(existing: &ManifestEntry, args: &Args) -> Result<bool> {
+    warning(format!("This looks like {}, which is already installed in {:?}", existing.name.bold(), existing.game_dir));
+    if args.reinstall {
+        step("Reinstalling (--reinstall)");
+        return Ok(true);
+    }
+    if args.yes {
+        step("Skipping it (pass --reinstall to install it anyway)");
+        return Ok(false);
+    }
+
+    eprintln!("  Reinstall or skip? [r/S]");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).context("Failed to read input")?;
+    let reinstall = answer.trim().eq_ignore_ascii_case("r") || answer.trim().eq_ignore_ascii_case("reinstall");
+    if !reinstall {
+        step(format!("Skipped {}", existing.name));
+    }
+    Ok(reinstall)
+}
+
+/// For `--confirm`: lists what the install will do and asks whether to go ahead.
+fn confirm_plan(plan: &InstallPlan) -> Result<bool> {
+    let name = plan.name.as_deref().unwrap_or("the game");
+    eprintln!("{} About to install {}:", "▶".cyan(), name.bold());
+    for action in &plan.actions {
+        eprintln!("  • {}", action);
+    }
+    if let Some(executable) = &plan.executable {
+        eprintln!("  Executable: {:?}", executable);
+    }
+    eprintln!("  Proceed? [Y/n] (pass --output-dir to install somewhere else)");
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).context("Failed to read input")?;
+    Ok(!answer.trim().eq_ignore_ascii_case("n"))
+}
+
+/// For `--verify-after`: checks that what the shortcuts point at is really there, printing a line
+/// per check. Fails if any check did.
+fn verify_install(entry: &ManifestEntry) -> Result<()> {
+    step("Verifying the install...");
+    let mut checks: Vec<(String, Result<(), String>)> = Vec::new();
+
+    let executable = &entry.executable;
+    let runnable = if !executable.is_file() {
+        Err(format!("{:?} does not exist", executable))
+    } else if !is_executable(executable) {
+        Err(format!("{:?} is not marked executable", executable))
+    } else {
+        Ok(())
+    };
+    checks.push(("Executable".to_string(), runnable));
+
+    if entry.desktop_files.is_empty() && let Some(icon) = &entry.icon {
+        checks.push(("Icon".to_string(), if icon.exists() { Ok(()) } else { Err(format!("{:?} does not exist", icon)) }));
+    }
+
+    let validator = find_in_path("desktop-file-validate");
+    for desktop_file in entry.all_desktop_files() {
+        // The menu and Desktop copies share a file name, so the folder tells them apart
+        let folder = desktop_file.parent().and_then(|p| p.file_name()).unwrap_or_default();
+        let label = format!("Shortcut {:?}", Path::new(folder).join(desktop_file.file_name().unwrap_or_default()));
+        let Some(fields) = read_desktop_entry(desktop_file) else {
+            checks.push((label, Err(format!("{:?} could not be read", desktop_file))));
+            continue;
+        };
+
+        let path = fields.get("Path").map(PathBuf::from);
+        checks.push((format!("{} working folder", label), match path {
+            Some(path) if path.is_dir() => Ok(()),
+            Some(path) => Err(format!("{:?} does not exist", path)),
+            None => Err("no Path= line".to_string()),
+        }));
+
+        if let Some(icon) = fields.get("Icon") {
+            let found = if Path::new(icon).is_absolute() { Path::new(icon).exists() } else { !theme_icon_files(icon).is_empty() };
+            checks.push((format!("{} icon", label), if found { Ok(()) } else { Err(format!("{:?} was not found", icon)) }));
+        }
+
+        if let Some(validator) = &validator {
+            let result = match std::process::Command::new(validator).arg(desktop_file).output() {
+                Ok(output) if output.status.success() => Ok(()),
+                Ok(output) => Err(String::from_utf8_lossy(&output.stdout).trim().replace('\n', "; ")),
+                Err(e) => Err(format!("could not run desktop-file-validate: {}", e)),
+            };
+            checks.push((format!("{} passes desktop-file-validate", label), result));
+        }
+    }
+
+    let failed = checks.iter().filter(|(_, result)| result.is_err()).count();
+    for (label, result) in &checks {
+        match result {
+            Ok(()) => eprintln!("  {} {}", "✔".green(), label),
+            Err(reason) => eprintln!("  {} {}: {}", "✖".red(), label, reason),
+        }
+    }
+    if validator.is_none() && !entry.desktop_files.is_empty() {
+        eprintln!("  {} desktop-file-validate is not installed; shortcut syntax was not checked", "▶".cyan());
+    }
+
+    if failed > 0 {
+        return Err(SpawnError::VerifyFailed { failed, total: checks.len() }.into());
+    }
+    success(format!("All {} install checks passed", checks.len()));
+    Ok(())
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+/// How long a freshly launched game is watched for an immediate crash.
+const OPEN_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Starts the game the way its shortcut would and reports it if it exits with an error right away.
+fn open_game(game_name: &str, executable: &Path, working_dir: &Path, launch: &LaunchCommand) -> Result<()> {
+    step(format!("Launching {}...", game_name.bold()));
+    let mut child = launch.command(executable, working_dir).spawn()
+        .map_err(|error| SpawnError::LaunchFailed { executable: executable.to_path_buf(), error })?;
+
+    let started = Instant::now();
+    while started.elapsed() < OPEN_GRACE_PERIOD {
+        if let Some(status) = child.try_wait().context("Failed to check on the game")? {
+            if status.success() {
+                warning(format!("{} exited right away without an error", game_name));
+                return Ok(());
+            }
+            return Err(SpawnError::GameCrashed { name: game_name.to_string(), status }.into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    success(format!("{} is running", game_name));
+    Ok(())
+}
+
+pub fn print_history() -> Result<()> {
+    let records = load_history();
+    if records.is_empty() {
+        eprintln!("{} No installs recorded yet", "▶".cyan());
+        return Ok(());
+    }
+
+    eprintln!("{} Install history:", "▶".cyan());
+    for record in &records {
+        let steam = if record.steam { " +Steam".cyan().to_string() } else { String::new() };
+        println!("  {}  {}{}", record.time.format("%Y-%m-%d %H:%M").to_string().dimmed(), record.name.bold(), steam);
+        println!("      from {:?}", record.source);
+        println!("      to   {:?}", record.install_dir);
+    }
+    Ok(())
+}
+
+/// Applies `--cleanup-archive` to an installed archive. The game is already installed, so
+/// failures only warn. Folders are never touched, and a moved AppImage is already gone.
+fn clean_up_after_install(input_path: &Path, cleanup: ArchiveCleanup, dry_run: bool) {
+    if input_path.is_dir() {
+        warning("Ignoring --cleanup-archive: it applies to archives and AppImages, not folders");
+    } else if !input_path.to_string_lossy().ends_with(".AppImage") && let Err(e) = cleanup_archive(input_path, cleanup, dry_run) {
+        warning(format!("Failed to clean up {:?}: {}", input_path.file_name().unwrap_or_default(), e));
+    }
+}
+
+/// Installs every archive in a directory, reporting a summary and failing if any install failed.
+fn install_batch(dir: &Path, args: &Args, config: &Config) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(SpawnError::BatchNeedsDir(dir.to_path_buf()).into());
+    }
+
+    let dir = dir.canonicalize().context("Failed to resolve batch directory")?;
+    let archives = find_batch_archives(&dir);
+    if archives.is_empty() {
+        warning(format!("No archives found in {:?}", dir));
+        return Ok(());
+    }
+
+    let mut installed = Vec::new();
+    let mut skipped = Vec::new();
+    let mut failed = Vec::new();
+    for (i, archive) in archives.iter().enumerate() {
+        let file_name = archive.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        step(format!("[{}/{}] {}", i + 1, archives.len(), file_name.bold()));
+        match install_game(archive, args, config) {
+            Ok(Some(entry)) => {
+                if let Some(cleanup) = args.cleanup_archive {
+                    clean_up_after_install(archive, cleanup, args.dry_run);
+                }
+                installed.push(entry);
+            }
+            Ok(None) => skipped.push(file_name),
+            Err(e) => {
+                eprintln!("{:#}", e);
+                failed.push(file_name);
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&installed)?);
+    } else {
+        eprintln!("\n{} Installed {} of {} game(s)", "▶".cyan(), installed.len(), archives.len());
+        for entry in &installed {
+            eprintln!("  {} {}", "✔".green(), entry.name);
+        }
+        for file_name in &skipped {
+            eprintln!("  {} {} (already installed)", "▶".cyan(), file_name);
+        }
+        for file_name in &failed {
+            eprintln!("  {} {}", "✖".red(), file_name);
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(SpawnError::BatchFailed { failed: failed.len(), total: archives.len() }.into());
+    }
+    Ok(())
+}
+
+/// Reports what discovery would pick from an archive's file listing, for dry runs that
+/// can't look inside an extracted folder. Returned paths are where the files would land, followed
+/// by the folder within the archive that extraction would unwrap down to.
+fn preview_archive(archive: &Path, game_dir: &Path, compat: Option<CompatTool>, scope: SearchScope, expected_names: &[String]) -> (Option<PathBuf>, Option<PathBuf>, PathBuf) {
+    if archive.to_string_lossy().ends_with(".AppImage") {
+        let executable = game_dir.join(archive.file_name().unwrap_or_default());
+        step(format!("Would use executable: {:?}", executable.file_name().unwrap_or_default()));
+        return (Some(executable), None, PathBuf::new());
+    }
+
+    let entries = match list_archive(archive) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warning(format!("Could not list archive contents ({}); skipping discovery", e));
+            return (None, None, PathBuf::new());
+        }
+    };
+
+    let executable = if compat.is_some() {
+        preview_windows_executable(&entries, scope, expected_names)
+    } else {
+        preview_executable(&entries, scope, expected_names).or_else(|| {
+            let windows_exe = preview_windows_executable(&entries, scope, expected_names)?;
+            warning("No Linux executable in the archive, but this looks like a Windows build");
+            Some(windows_exe)
+        })
+    };
+    match &executable {
+        Some(e) => step(format!("Would use executable: {:?}", e)),
+        None => warning("No executable found in the archive listing\nHint: This archive may not be a Linux build"),
+    }
+
+    let mut icon_names = expected_names.to_vec();
+    icon_names.extend(executable.as_ref().and_then(|e| e.file_name()).map(|n| n.to_string_lossy().into_owned()));
+    let icon = preview_icon(&entries, scope, &icon_names);
+    if let Some(i) = &icon {
+        step(format!("Would use icon: {:?}", i));
+    }
+
+    (executable.map(|e| game_dir.join(e)), icon.map(|i| game_dir.join(i)), archive_root(&entries))
+}
+
+/// Uninstalls every game whose name or install folder matches a wildcard pattern, after
+/// listing them and asking. `--yes`/`--force` skips the question.
+fn uninstall_matching(pattern: &str, install_dir: &Path, system: bool, interactive: bool, dry_run: bool) -> Result<()> {
+    let manifest = load_manifest()?;
+    let folder_name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut names = manifest.games.iter()
+        .filter(|e| matches_glob(&e.name, pattern) || e.install_dir.as_deref().is_some_and(|d| matches_glob(&folder_name(d), pattern)))
+        .map(|e| e.name.clone())
+        .collect::<Vec<_>>();
+    // Folders from before the manifest, which the name-based fallback can still remove
+    let mut folders = fs::read_dir(install_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    folders.sort();
+    for folder in folders {
+        let name = folder_name(&folder);
+        let known = manifest.games.iter().any(|e| e.install_dir.as_ref() == Some(&folder)) || manifest.find(&name).is_some();
+        if matches_glob(&name, pattern) && !known {
+            names.push(name);
+        }
+    }
+
+    if names.is_empty() {
+        warning(format!("No installed games match {:?}", pattern));
+        return Ok(());
+    }
+
+    eprintln!("{} {} game(s) match {:?}:", "▶".cyan(), names.len(), pattern);
+    for name in &names {
+        eprintln!("  {}", name);
+    }
+    if interactive && !dry_run {
+        eprintln!("  Uninstall all of them? [y/N] (pass --force to skip this question)");
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).context("Failed to read input")?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            step("Nothing was uninstalled");
+            return Ok(());
+        }
+    }
+
+    for name in &names {
+        uninstall_game(name, install_dir, system, interactive, dry_run)?;
+    }
+    Ok(())
+}
+
+fn uninstall_game(game_name: &str, install_dir: &Path, system: bool, interactive: bool, dry_run: bool) -> Result<()> {
+    step(format!("Uninstalling {}...", game_name.bold()));
+
+    let _lock = lock_manifest()?;
+    let mut manifest = load_manifest()?;
+    if let Some(entry) = manifest.find(game_name).cloned() {
+        return uninstall_from_manifest(&entry, &mut manifest, true, interactive, dry_run);
+    }
+    
     let formatted_name = format_game_name(game_name);
-    let dir_name = game_name.replace(' ', "_");
+    let dir_name = install_dir_name(game_name);
     let game_path = install_dir.join(&dir_name);
     
     let mut found = false;
     if game_path.exists() {
         found = true;
         if dry_run {
-            println!("{} Would remove directory: {:?}", "▶".cyan(), game_path);
+            step(format!("Would remove directory: {:?}", game_path));
         } else {
-            println!("{} Removing directory: {:?}", "▶".cyan(), game_path);
+            step(format!("Removing directory: {:?}", game_path));
             fs::remove_dir_all(&game_path).context("Failed to remove game directory")?;
         }
     }
 
-    let desktop_file_name = format!("{}.desktop", formatted_name.to_lowercase().replace(' ', "-"));
+    let desktop_file_name = desktop_file_name(&formatted_name);
     
-    let app_dir = dirs_next::home_dir().map(|h| h.join(".local/share/applications"));
-    if let Some(path) = app_dir.map(|d| d.join(&desktop_file_name)) {
-        if path.exists() {
-            found = true;
-            if dry_run {
-                println!("{} Would remove shortcut: {:?}", "▶".cyan(), path);
-            } else {
-                fs::remove_file(&path).context("Failed to remove application shortcut")?;
-                println!("{} Removed shortcut: {:?}", "✔".green(), path.file_name().unwrap());
-            }
+    let app_dir = if system { Some(PathBuf::from(SYSTEM_APPLICATIONS_DIR)) } else { applications_dir() };
+    if let Some(path) = app_dir.map(|d| d.join(&desktop_file_name)) && path.exists() && created_by_spawn(&path, install_dir) {
+        found = true;
+        if dry_run {
+            step(format!("Would remove shortcut: {:?}", path));
+        } else {
+            fs::remove_file(&path).context("Failed to remove application shortcut")?;
+            success(format!("Removed shortcut: {:?}", path.file_name().unwrap()));
         }
     }
 
-    let desktop_dir = dirs_next::home_dir().map(|h| h.join("Desktop"));
-    if let Some(path) = desktop_dir.map(|d| d.join(&desktop_file_name)) {
-        if path.exists() {
-            found = true;
-            if dry_run {
-                println!("{} Would remove desktop shortcut: {:?}", "▶".cyan(), path);
-            } else {
-                fs::remove_file(&path).context("Failed to remove desktop shortcut")?;
-                println!("{} Removed desktop shortcut: {:?}", "✔".green(), path.file_name().unwrap());
-            }
+    let desktop_dir = if system { None } else { desktop_dir() };
+    if let Some(path) = desktop_dir.map(|d| d.join(&desktop_file_name)) && path.exists() && created_by_spawn(&path, install_dir) {
+        found = true;
+        if dry_run {
+            step(format!("Would remove desktop shortcut: {:?}", path));
+        } else {
+            fs::remove_file(&path).context("Failed to remove desktop shortcut")?;
+            success(format!("Removed desktop shortcut: {:?}", path.file_name().unwrap()));
         }
     }
 
+    let icon_files = if system { Vec::new() } else { theme_icon_files(&theme_icon_name(&formatted_name)) };
+    if !icon_files.is_empty() {
+        found = true;
+        remove_theme_icons(&icon_files, dry_run)?;
+    }
+
+    found |= remove_steam_shortcut(&formatted_name, interactive, dry_run);
+
     if !found {
-        println!("{} No installation found for {}", "⚠".yellow(), game_name);
+        warning(format!("No installation found for {}", game_name));
     } else {
-        println!("{} {} has been uninstalled.", "✔".green().bold(), formatted_name);
+        success(format!("{} has been uninstalled.", formatted_name));
+    }
+
+    Ok(())
+}
+
+fn rename_game(old_name: &str, new_name: &str, config: &Config, interactive: bool, dry_run: bool) -> Result<()> {
+    let new_name = format_game_name(new_name);
+    step(format!("Renaming {} to {}...", old_name.bold(), new_name.bold()));
+
+    let _lock = lock_manifest()?;
+    let mut manifest = load_manifest()?;
+    if manifest.games.iter().any(|e| e.name.eq_ignore_ascii_case(&new_name)) {
+        return Err(SpawnError::NameTaken(new_name).into());
+    }
+
+    let old = match manifest.find(old_name).cloned() {
+        Some(entry) => entry,
+        None => heuristic_entry(old_name, config)?,
+    };
+    let mut entry = old.clone();
+    entry.name = new_name.clone();
+
+    // Move the folder Spawn created, keeping any nested layout beneath it
+    let mut relocate = None;
+    if let Some(dir) = &old.install_dir {
+        let new_dir = dir.with_file_name(install_dir_name(&new_name));
+        if new_dir.exists() {
+            return Err(SpawnError::AlreadyExists(new_dir).into());
+        }
+
+        if dry_run {
+            step(format!("Would move {:?} to {:?}", dir, new_dir));
+        } else {
+            fs::rename(dir, &new_dir).context("Failed to move game directory")?;
+            success(format!("Moved game directory to {:?}", new_dir));
+        }
+
+        let moved = |p: &Path| p.strip_prefix(dir).map(|rel| new_dir.join(rel)).unwrap_or_else(|_| p.to_path_buf());
+        entry.game_dir = moved(&old.game_dir);
+        entry.executable = moved(&old.executable);
+        entry.icon = old.icon.as_deref().map(moved);
+        entry.working_dir = old.working_dir.as_deref().map(moved);
+        entry.extra_launchers.iter_mut().for_each(|l| l.executable = moved(&l.executable));
+        entry.install_dir = Some(new_dir.clone());
+        // Launch settings can point into the game folder too (e.g. Proton's prefix)
+        let (old_str, new_str) = (dir.to_string_lossy(), new_dir.to_string_lossy());
+        entry.env.values_mut().chain(entry.wrapper.iter_mut()).for_each(|v| *v = v.replace(old_str.as_ref(), new_str.as_ref()));
+        relocate = Some((dir.clone(), new_dir));
+    }
+
+    // Rewrite the shortcuts in place so launch options and categories survive the rename
+    let relocate = relocate.as_ref().map(|(o, n)| (o.as_path(), n.as_path()));
+    entry.desktop_files = rename_shortcuts(&old.desktop_files, &new_name, relocate, dry_run)?;
+    for launcher in &mut entry.extra_launchers {
+        let name = format!("{} {}", new_name, launcher.label);
+        launcher.desktop_files = rename_shortcuts(&launcher.desktop_files, &name, relocate, dry_run)?;
+    }
+
+    // Entries from before launch settings were recorded leave Steam's launch options alone
+    let launch = entry.launch_command();
+    let has_launch_settings = !(launch.env.is_empty() && launch.wrapper.is_empty() && launch.args.is_empty());
+    if let Err(e) = rename_in_steam(&old.name, &new_name, &entry.executable, &entry.working_dir(), has_launch_settings.then_some(&launch), interactive, dry_run) {
+        warning(format!("Failed to rename Steam shortcut: {}", e));
+    }
+
+    if !dry_run {
+        manifest.remove(&old.name);
+        manifest.upsert(entry);
+        save_manifest(&manifest)?;
+    }
+
+    success(format!("Renamed {} to {}", old.name, new_name.bold()));
+    Ok(())
+}
+
+/// Renames a game's shortcuts to match `name`, returning their new paths.
+fn rename_shortcuts(paths: &[PathBuf], name: &str, relocate: Option<(&Path, &Path)>, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let mut renamed = Vec::new();
+    for path in paths.iter().filter(|p| p.exists()) {
+        let new_path = path.with_file_name(desktop_file_name(name));
+        if dry_run {
+            step(format!("Would rename shortcut {:?} to {:?}", path.file_name().unwrap_or_default(), new_path.file_name().unwrap_or_default()));
+        } else {
+            let content = fs::read_to_string(path).context("Failed to read shortcut")?;
+            let content = rewrite_desktop_entry(&content, name, relocate);
+            fs::write(&new_path, content).context("Failed to write shortcut")?;
+            if &new_path != path {
+                fs::remove_file(path).context("Failed to remove old shortcut")?;
+            }
+            success(format!("Shortcut updated: {:?}", new_path.file_name().unwrap_or_default()));
+        }
+        renamed.push(new_path);
+    }
+    Ok(renamed)
+}
+
+/// The launch settings `--edit` opens in the editor.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct LaunchSettings {
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+/// Opens a game's stored launch arguments and environment in `$VISUAL`/`$EDITOR`, then rewrites
+/// its shortcuts and Steam entry with the result.
+fn edit_launch_settings(game_name: &str, interactive: bool) -> Result<()> {
+    let _lock = lock_manifest()?;
+    let mut manifest = load_manifest()?;
+    let mut entry = manifest.find(game_name).cloned().ok_or_else(|| SpawnError::NotInstalled(game_name.to_string()))?;
+
+    let current = LaunchSettings { args: entry.launch_args.clone(), env: entry.env.clone() };
+    let content = format!(
+        "# Launch settings for {}. Save and close the editor to apply.\n# Example: args = [\"-windowed\"], and under [env]: DRI_PRIME = \"1\"\n\n{}",
+        entry.name,
+        toml::to_string(&current).context("Failed to serialize launch settings")?
+    );
+
+    // A fresh private folder, so no one else can plant a symlink where the settings are written
+    let work_dir = nix::unistd::mkdtemp(&std::env::temp_dir().join("spawn-edit-XXXXXX")).context("Failed to create a temporary folder")?;
+    let path = work_dir.join("launch.toml");
+    let result = fs::write(&path, content).context("Failed to write launch settings").and_then(|_| run_editor(&path)).and_then(|_| {
+        let edited = fs::read_to_string(&path).context("Failed to read launch settings")?;
+        toml::from_str::<LaunchSettings>(&edited)
+            .map_err(|e| SpawnError::InvalidLaunchSettings(e.message().to_string()).into())
+    });
+    let _ = fs::remove_dir_all(&work_dir);
+    let edited = result?;
+
+    if edited == current {
+        step(format!("No changes to {}", entry.name));
+        return Ok(());
+    }
+
+    entry.launch_args = edited.args;
+    entry.env = edited.env;
+    let launch = entry.launch_command();
+
+    let exec = launch.desktop_exec(&entry.executable);
+    for shortcut in entry.desktop_files.iter().filter(|p| p.exists()) {
+        let content = fs::read_to_string(shortcut).context("Failed to read shortcut")?;
+        fs::write(shortcut, set_desktop_exec(&content, &exec)).context("Failed to write shortcut")?;
+        success(format!("Shortcut updated: {:?}", shortcut.file_name().unwrap_or_default()));
+    }
+    // Extra launchers share the game's environment but not its arguments
+    let extra_launch = LaunchCommand { args: Vec::new(), ..launch.clone() };
+    for launcher in &entry.extra_launchers {
+        let exec = extra_launch.desktop_exec(&launcher.executable);
+        for shortcut in launcher.desktop_files.iter().filter(|p| p.exists()) {
+            let content = fs::read_to_string(shortcut).context("Failed to read shortcut")?;
+            fs::write(shortcut, set_desktop_exec(&content, &exec)).context("Failed to write shortcut")?;
+            success(format!("Shortcut updated: {:?}", shortcut.file_name().unwrap_or_default()));
+        }
+    }
+
+    match update_steam_launch_options(&entry.name, &launch, interactive) {
+        Ok(true) => success("Steam shortcut updated"),
+        Ok(false) => {}
+        Err(e) => warning(format!("Failed to update Steam shortcut: {:?}", e)),
+    }
+
+    let name = entry.name.clone();
+    manifest.upsert(entry);
+    save_manifest(&manifest)?;
+    success(format!("Saved launch settings for {}", name.bold()));
+    Ok(())
+}
+
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+    let words = shlex::split(&editor).filter(|w| !w.is_empty())
+        .ok_or_else(|| SpawnError::InvalidEditor(editor.clone()))?;
+
+    let status = std::process::Command::new(&words[0])
+        .args(&words[1..])
+        .arg(path)
+        .status()
+        .map_err(|error| SpawnError::EditorFailed { editor: words[0].clone(), error })?;
+    if !status.success() {
+        return Err(SpawnError::EditorExited(status).into());
+    }
+    Ok(())
+}
+
+/// Reconstructs a manifest entry for games installed before the manifest existed,
+/// using the same naming conventions as the uninstall fallback.
+fn heuristic_entry(game_name: &str, config: &Config) -> Result<ManifestEntry> {
+    let name = format_game_name(game_name);
+    let game_dir = config.install_dir.join(install_dir_name(game_name));
+    let desktop_files: Vec<PathBuf> = [applications_dir(), desktop_dir()].into_iter()
+        .flatten()
+        .map(|d| d.join(desktop_file_name(&name)))
+        .filter(|p| p.exists())
+        .collect();
+
+    if !game_dir.exists() && desktop_files.is_empty() {
+        return Err(SpawnError::NotInstalled(game_name.to_string()).into());
+    }
+
+    let executable = discover_executable(&game_dir, false, config.search_scope(), std::slice::from_ref(&name)).unwrap_or_default();
+    let icon_name = Some(theme_icon_name(&name)).filter(|n| !theme_icon_files(n).is_empty());
+    Ok(ManifestEntry {
+        name,
+        install_dir: game_dir.exists().then(|| game_dir.clone()),
+        game_dir,
+        executable,
+        icon: None,
+        icon_name,
+        desktop_files,
+        env: BTreeMap::new(),
+        wrapper: Vec::new(),
+        launch_args: Vec::new(),
+        source: None,
+        working_dir: None,
+        tags: Vec::new(),
+        extra_launchers: Vec::new(),
+        actions: Vec::new(),
+    })
+}
+
+fn search_downloads(query: &str, search_dir: &Path) -> Result<()> {
+    let mut matches = find_matches(query, search_dir);
+    matches.sort();
+    if matches.is_empty() {
+        matches = find_fuzzy_matches(query, search_dir).into_iter().map(|(m, _)| m).collect();
+    }
+    if matches.is_empty() {
+        eprintln!("{} No file or directory found matching \"{}\" in {:?}", "⚠".yellow(), query, search_dir);
+        return Ok(());
+    }
+
+    eprintln!("{} {} match(es) for \"{}\" in {:?}:", "▶".cyan(), matches.len(), query, search_dir);
+    let names = matches.iter().map(|m| m.file_name().unwrap_or_default().to_string_lossy().into_owned()).collect::<Vec<_>>();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+
+    for (path, name) in matches.iter().zip(&names) {
+        let metadata = fs::metadata(path).ok();
+        let size = match &metadata {
+            Some(m) if m.is_dir() => "dir".to_string(),
+            Some(m) => HumanBytes(m.len()).to_string(),
+            None => "?".to_string(),
+        };
+        let modified = metadata.and_then(|m| m.modified().ok()).map(format_age).unwrap_or_default();
+        println!("  {:<width$}  {:>10}  {}", name, size, modified.dimmed(), width = width);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct InstalledGame {
+    name: String,
+    path: PathBuf,
+    shortcut: Option<PathBuf>,
+}
+
+/// Removes exactly what was recorded for an install, leaving anything Spawn didn't create untouched.
+fn uninstall_from_manifest(entry: &ManifestEntry, manifest: &mut Manifest, remove_steam: bool, interactive: bool, dry_run: bool) -> Result<()> {
+    if let Some(dir) = entry.install_dir.as_ref().filter(|d| d.exists()) {
+        if dry_run {
+            step(format!("Would remove directory: {:?}", dir));
+        } else {
+            step(format!("Removing directory: {:?}", dir));
+            fs::remove_dir_all(dir).context("Failed to remove game directory")?;
+        }
+    }
+
+    for path in entry.all_desktop_files().filter(|p| p.exists()) {
+        if dry_run {
+            step(format!("Would remove shortcut: {:?}", path));
+        } else {
+            fs::remove_file(path).context("Failed to remove shortcut")?;
+            success(format!("Removed shortcut: {:?}", path.file_name().unwrap_or_default()));
+        }
+    }
+
+    let icon_files = entry.icon_name.as_deref().map(theme_icon_files).unwrap_or_default();
+    remove_theme_icons(&icon_files, dry_run)?;
+    if remove_steam {
+        remove_steam_shortcut(&entry.name, interactive, dry_run);
+    }
+
+    if !dry_run {
+        manifest.remove(&entry.name);
+        save_manifest(manifest)?;
     }
 
+    success(format!("{} has been uninstalled.", entry.name));
     Ok(())
 }
+
+/// Whether a shortcut found by name is Spawn's to remove: it carries Spawn's marker, or (from
+/// before the marker) it runs from inside the install directory. Anything else is left alone.
+fn created_by_spawn(shortcut: &Path, install_dir: &Path) -> bool {
+    let Some(fields) = read_desktop_entry(shortcut) else { return false };
+    if is_spawn_managed(&fields) || fields.get("Path").is_some_and(|p| Path::new(p).starts_with(install_dir)) {
+        return true;
+    }
+    warning(format!("Leaving {:?}: it wasn't created by Spawn", shortcut));
+    false
+}
+
+/// Reverses the most recent install, using its manifest entry so only what Spawn created is removed.
+fn undo_last_install(interactive: bool, dry_run: bool) -> Result<()> {
+    let Some(last) = load_last_install() else {
+        step("Nothing to undo");
+        return Ok(());
+    };
+
+    let _lock = lock_manifest()?;
+    let mut manifest = load_manifest()?;
+    let Some(entry) = manifest.games.iter().find(|e| e.game_dir == last.game_dir).cloned() else {
+        step(format!("{} is no longer installed where Spawn put it; nothing to undo", last.name));
+        if !dry_run {
+            clear_last_install()?;
+        }
+        return Ok(());
+    };
+
+    eprintln!("{} Undoing the install of {}. This removes:", "▶".cyan(), entry.name.bold());
+    match &entry.install_dir {
+        Some(dir) => eprintln!("  {:?}", dir),
+        None => eprintln!("  (the game folder stays: Spawn didn't create it)"),
+    }
+    for path in entry.all_desktop_files() {
+        eprintln!("  {:?}", path);
+    }
+    if last.added_to_steam {
+        eprintln!("  the Steam shortcut");
+    }
+
+    if interactive && !dry_run {
+        eprintln!("  Continue? [y/N]");
+        let mut confirm = String::new();
+        std::io::stdin().read_line(&mut confirm).context("Failed to read input")?;
+        if !confirm.trim().eq_ignore_ascii_case("y") {
+            step("Nothing was removed");
+            return Ok(());
+        }
+    }
+
+    uninstall_from_manifest(&entry, &mut manifest, last.added_to_steam, interactive, dry_run)?;
+    if !dry_run {
+        clear_last_install()?;
+    }
+    Ok(())
+}
+
+/// Removes the game's non-Steam shortcut, if it has one. A failure is reported but doesn't stop the uninstall.
+fn remove_steam_shortcut(game_name: &str, interactive: bool, dry_run: bool) -> bool {
+    match remove_from_steam(game_name, interactive, dry_run) {
+        Ok(removed) => removed,
+        Err(e) => {
+            warning(format!("Failed to remove the Steam shortcut: {}", e));
+            false
+        }
+    }
+}
+
+/// Fails early with a sudo hint when a system-wide install can't write where it needs to.
+fn check_system_access(config: &Config) -> Result<()> {
+    let dirs = [config.system_install_dir.as_path(), Path::new(SYSTEM_APPLICATIONS_DIR), Path::new(SYSTEM_DATA_DIR)];
+    for dir in dirs {
+        if !is_writable(dir) {
+            return Err(SpawnError::NeedsRoot(dir.to_path_buf()).into());
+        }
+    }
+    Ok(())
+}
+
+/// Whether files can be created in `dir`, or in the closest parent that exists yet.
+fn is_writable(dir: &Path) -> bool {
+    let Some(existing) = dir.ancestors().find(|d| d.exists()) else {
+        return false;
+    };
+    let probe = existing.join(format!(".spawn-write-test-{}", std::process::id()));
+    let writable = fs::File::create(&probe).is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
+
+fn remove_theme_icons(files: &[PathBuf], dry_run: bool) -> Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    if dry_run {
+        step(format!("Would remove {} theme icon(s)", files.len()));
+        return Ok(());
+    }
+    for file in files {
+        fs::remove_file(file).context("Failed to remove theme icon")?;
+    }
+    success(format!("Removed {} theme icon(s)", files.len()));
+    Ok(())
+}
+
+fn list_games(install_dir: &Path, system: bool, json: bool) -> Result<()> {
+    let mut shortcuts = Vec::new();
+    let app_dir = if system { Some(PathBuf::from(SYSTEM_APPLICATIONS_DIR)) } else { applications_dir() };
+    if let Some(app_dir) = app_dir
+        && let Ok(entries) = fs::read_dir(app_dir)
+    {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "desktop")
+                && let Some(fields) = read_desktop_entry(&path)
+            {
+                shortcuts.push((path, fields));
+            }
+        }
+    }
+
+    let mut games = Vec::new();
+    if let Ok(entries) = fs::read_dir(install_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+
+            // The shortcut's folder may be nested if the archive was flattened. Spawn's own shortcuts
+            // are preferred; Path= alone also finds ones made before they were marked.
+            let points_here = |fields: &HashMap<String, String>, key| fields.get(key).is_some_and(|p| Path::new(p).starts_with(&path));
+            let shortcut = shortcuts.iter().find(|(_, fields)| is_spawn_managed(fields) && points_here(fields, "X-Spawn-Install-Dir"))
+                .or_else(|| shortcuts.iter().find(|(_, fields)| points_here(fields, "Path")));
+            let name = shortcut
+                .and_then(|(_, fields)| fields.get("Name").cloned())
+                .unwrap_or_else(|| format_game_name(&entry.file_name().to_string_lossy()));
+
+            games.push(InstalledGame {
+                name,
+                path,
+                shortcut: shortcut.map(|(p, _)| p.clone()),
+            });
+        }
+    }
+    games.sort_by_key(|g| g.name.to_lowercase());
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&games)?);
+        return Ok(());
+    }
+
+    if games.is_empty() {
+        eprintln!("{} No games installed in {:?}", "⚠".yellow(), install_dir);
+        return Ok(());
+    }
+
+    eprintln!("{} Installed games in {:?}:", "▶".cyan(), install_dir);
+    let width = games.iter().map(|g| g.name.chars().count()).max().unwrap_or(0);
+    for game in &games {
+        let padded = format!("{:<width$}", game.name, width = width);
+        if game.shortcut.is_some() {
+            println!("  {} {}  {}", "✔".green(), padded.bold(), game.path.display());
+        } else {
+            println!("  {} {}  {} {}", "⚠".yellow(), padded.bold(), game.path.display(), "(no shortcut)".yellow());
+        }
+    }
+
+    let missing = games.iter().filter(|g| g.shortcut.is_none()).count();
+    if missing > 0 {
+        eprintln!("\n{} {} game(s) have no shortcut. Run 'spawn <PATH>' on them to create one.", "⚠".yellow(), missing);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    #[test]
+    fn generates_completions_for_every_shell() {
+        for shell in clap_complete::Shell::value_variants() {
+            let mut script = Vec::new();
+            clap_complete::generate(*shell, &mut Args::command(), "spawn", &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("completions"), "{} completions don't offer --completions", shell);
+        }
+    }
+}
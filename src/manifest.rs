@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{IoContext, Result, SpawnError};
+use crate::lock::{FileLock, lock_file, lock_path};
+use crate::report;
+use crate::utils::{DesktopAction, LaunchCommand, default_working_dir, format_game_name, write_atomic};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub name: String,
+    /// Directory Spawn created for the game. `None` when Spawn was pointed at an existing folder,
+    /// in which case uninstall must leave it alone.
+    pub install_dir: Option<PathBuf>,
+    /// Directory the game runs from (may be nested inside `install_dir`)
+    pub game_dir: PathBuf,
+    pub executable: PathBuf,
+    pub icon: Option<PathBuf>,
+    /// Name of the copy installed into the hicolor icon theme, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_name: Option<String>,
+    pub desktop_files: Vec<PathBuf>,
+    /// Environment variables set when the game is launched
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Commands the game runs through, such as Wine or a sandbox
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wrapper: Vec<String>,
+    /// Arguments passed to the game
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub launch_args: Vec<String>,
+    /// The archive or AppImage the game was installed from, for `--dedupe`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ArchiveFingerprint>,
+    /// Folder the game starts in, when set with `--working-dir`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<PathBuf>,
+    /// Steam collections the game's shortcut was sorted into with `--tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Shortcuts for the game's other programs, such as a level editor or dedicated server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_launchers: Vec<ExtraLauncher>,
+    /// Right-click menu entries on the game's shortcuts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<DesktopAction>,
+}
+
+/// A secondary program shipped with a game, with its own shortcuts named "<game> <label>".
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExtraLauncher {
+    pub label: String,
+    pub executable: PathBuf,
+    pub desktop_files: Vec<PathBuf>,
+}
+
+/// A cheap way to recognize an archive again: its file name and size, without hashing it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveFingerprint {
+    pub file_name: String,
+    pub size: u64,
+}
+
+impl ArchiveFingerprint {
+    pub fn of(path: &Path) -> Option<ArchiveFingerprint> {
+        let size = fs::metadata(path).ok()?.len();
+        let file_name = path.file_name()?.to_string_lossy().into_owned();
+        Some(ArchiveFingerprint { file_name, size })
+    }
+}
+
+impl ManifestEntry {
+    /// The folder the game starts in: the `--working-dir` override, or the executable's folder.
+    pub fn working_dir(&self) -> PathBuf {
+        self.working_dir.clone().unwrap_or_else(|| default_working_dir(&self.executable, &self.game_dir))
+    }
+
+    /// The game's shortcuts followed by those of its extra launchers.
+    pub fn all_desktop_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.desktop_files.iter().chain(self.extra_launchers.iter().flat_map(|l| &l.desktop_files))
+    }
+
+    /// The stored launch settings, as used for the game's shortcuts.
+    pub fn launch_command(&self) -> LaunchCommand {
+        LaunchCommand {
+            env: self.env.iter().map(|(key, value)| format!("{}={}", key, value)).collect(),
+            wrapper: self.wrapper.clone(),
+            args: self.launch_args.clone(),
+        }
+    }
+
+    pub fn set_launch_command(&mut self, launch: &LaunchCommand) {
+        self.env = launch.env.iter()
+            .map(|assignment| {
+                let (key, value) = assignment.split_once('=').unwrap_or((assignment, ""));
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+        self.wrapper = launch.wrapper.clone();
+        self.launch_args = launch.args.clone();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    #[serde(default)]
+    pub games: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Finds an entry by exact (case-insensitive) name, falling back to a unique partial match.
+    pub fn find(&self, query: &str) -> Option<&ManifestEntry> {
+        let query = format_game_name(query).to_lowercase();
+        if let Some(entry) = self.games.iter().find(|e| e.name.to_lowercase() == query) {
+            return Some(entry);
+        }
+
+        let mut partial = self.games.iter().filter(|e| e.name.to_lowercase().contains(&query));
+        match (partial.next(), partial.next()) {
+            (Some(entry), None) => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Adds an entry, replacing any previous install with the same name.
+    pub fn upsert(&mut self, entry: ManifestEntry) {
+        self.games.retain(|e| !e.name.eq_ignore_ascii_case(&entry.name));
+        self.games.push(entry);
+    }
+
+    /// Finds an install that looks like the same game: one made from an identical archive,
+    /// or failing that, one whose executable has the given file name.
+    pub fn find_duplicate(&self, fingerprint: Option<&ArchiveFingerprint>, executable_name: Option<&str>) -> Option<&ManifestEntry> {
+        if let Some(fingerprint) = fingerprint && let Some(entry) = self.games.iter().find(|e| e.source.as_ref() == Some(fingerprint)) {
+            return Some(entry);
+        }
+
+        let executable_name = executable_name?;
+        self.games.iter().find(|e| e.executable.file_name().is_some_and(|n| n.to_string_lossy() == executable_name))
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.games.retain(|e| !e.name.eq_ignore_ascii_case(name));
+    }
+}
+
+/// Where system-wide installs are recorded, shared by every user.
+pub const SYSTEM_DATA_DIR: &str = "/var/lib/spawn";
+
+static SYSTEM_WIDE: AtomicBool = AtomicBool::new(false);
+
+/// Switches this run to the system-wide manifest, for `--system`.
+pub fn use_system_manifest() {
+    SYSTEM_WIDE.store(true, Ordering::Relaxed);
+}
+
+/// Where Spawn keeps its records: per user, or shared for `--system`. Nothing is created.
+pub fn data_dir_path() -> Result<PathBuf> {
+    if SYSTEM_WIDE.load(Ordering::Relaxed) {
+        return Ok(PathBuf::from(SYSTEM_DATA_DIR));
+    }
+    Ok(dirs_next::data_dir()
+        .ok_or(SpawnError::NoDataDir)?
+        .join("spawn"))
+}
+
+/// [`data_dir_path`], created if needed.
+fn data_dir() -> Result<PathBuf> {
+    let data_dir = data_dir_path()?;
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).with_context(|| format!("Failed to create {:?}", data_dir))?;
+    }
+    Ok(data_dir)
+}
+
+pub fn get_manifest_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("installed.json"))
+}
+
+pub fn load_manifest() -> Result<Manifest> {
+    read_manifest(&get_manifest_path()?)
+}
+
+pub fn save_manifest(manifest: &Manifest) -> Result<()> {
+    write_manifest(&get_manifest_path()?, manifest)
+}
+
+/// Reads the manifest at `path`; a missing file is an empty manifest. One that doesn't parse
+/// is moved aside with a warning, so saving afterwards can't erase the installs it lists.
+fn read_manifest(path: &Path) -> Result<Manifest> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Manifest::default()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    let error = match serde_json::from_str(&content) {
+        Ok(manifest) => return Ok(manifest),
+        Err(e) => e,
+    };
+
+    let mut aside = path.as_os_str().to_os_string();
+    aside.push(format!(".corrupt-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let aside = PathBuf::from(aside);
+    fs::rename(path, &aside).map_err(|_| SpawnError::CorruptManifest { path: path.to_path_buf(), reason: error.to_string() })?;
+    report::warning(format!(
+        "{:?} couldn't be read ({}), so it was moved to {:?}. Games installed before won't be listed until it's fixed and moved back.",
+        path, error, aside
+    ));
+    Ok(Manifest::default())
+}
+
+/// Writes `manifest` to `path`, unless the file there doesn't parse: it may be the only record
+/// of what's installed, so it's left for the user to fix.
+fn write_manifest(path: &Path, manifest: &Manifest) -> Result<()> {
+    if let Ok(content) = fs::read_to_string(path) && let Err(e) = serde_json::from_str::<Manifest>(&content) {
+        return Err(SpawnError::CorruptManifest { path: path.to_path_buf(), reason: e.to_string() });
+    }
+    let s = serde_json::to_string_pretty(manifest).map_err(|e| SpawnError::Serialize { what: "manifest", reason: e.to_string() })?;
+    write_atomic(path, s).context("Failed to write manifest file")
+}
+
+/// Locks the manifest so another Spawn instance can't change it between loading and saving.
+/// Hold it across a `load_manifest`/`save_manifest` pair; `record_install` takes it itself.
+pub fn lock_manifest() -> Result<FileLock> {
+    lock_file(&lock_path(&get_manifest_path()?))
+}
+
+pub fn record_install(entry: ManifestEntry) -> Result<()> {
+    let _lock = lock_manifest()?;
+    let mut manifest = load_manifest()?;
+    manifest.upsert(entry);
+    save_manifest(&manifest)
+}
+
+/// The most recent install, for `--undo`. Only identifies the game; what to remove comes from
+/// its manifest entry, which lists exactly what Spawn created.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LastInstall {
+    pub name: String,
+    pub game_dir: PathBuf,
+    /// Whether this install added the Steam shortcut
+    pub added_to_steam: bool,
+}
+
+fn last_install_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("last_install.json"))
+}
+
+pub fn record_last_install(last: &LastInstall) -> Result<()> {
+    let s = serde_json::to_string_pretty(last).map_err(|e| SpawnError::Serialize { what: "last install", reason: e.to_string() })?;
+    write_atomic(&last_install_path()?, s).context("Failed to write last install file")
+}
+
+pub fn load_last_install() -> Option<LastInstall> {
+    let content = fs::read_to_string(last_install_path().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn clear_last_install() -> Result<()> {
+    match fs::remove_file(last_install_path()?) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e).context("Failed to remove last install file"),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn entry(name: &str) -> ManifestEntry {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "install_dir": null,
+            "game_dir": "/games/x",
+            "executable": "/games/x/run.sh",
+            "icon": null,
+            "desktop_files": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn missing_manifest_is_empty_and_saves_round_trip() {
+        let dir = TempDir::new();
+        let path = dir.path().join("installed.json");
+        assert!(read_manifest(&path).unwrap().games.is_empty());
+
+        let mut manifest = Manifest::default();
+        manifest.upsert(entry("Celeste"));
+        write_manifest(&path, &manifest).unwrap();
+        assert_eq!(read_manifest(&path).unwrap().games[0].name, "Celeste");
+    }
+
+    #[test]
+    fn corrupt_manifest_is_moved_aside_not_overwritten() {
+        let dir = TempDir::new();
+        let path = dir.write("installed.json", r#"{"games": [{"name": "Celeste""#);
+
+        let mut manifest = Manifest::default();
+        manifest.upsert(entry("Hollow Knight"));
+        assert!(matches!(write_manifest(&path, &manifest).unwrap_err(), SpawnError::CorruptManifest { .. }));
+
+        assert!(read_manifest(&path).unwrap().games.is_empty());
+        assert!(!path.exists());
+        let aside = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect::<Vec<_>>();
+        assert_eq!(aside.len(), 1);
+        assert!(aside[0].starts_with("installed.json.corrupt-"), "{:?}", aside);
+        assert_eq!(fs::read_to_string(dir.path().join(&aside[0])).unwrap(), r#"{"games": [{"name": "Celeste""#);
+
+        write_manifest(&path, &manifest).unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+use colored::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::{Args, named_install_dir};
+use spawn::config::{Config, DesktopTarget, OverwritePolicy};
+use spawn::discovery::{discover_executable, discover_icon, discover_windows_executable, find_bundled_launcher, preview_executable, preview_icon, preview_windows_executable};
+use spawn::error::{IoContext, Result, SpawnError};
+use spawn::installation::{STDIN_INPUT, ArchiveEntry, ArchiveKind, detect_archive_kind, download_file_name, expected_checksum, file_sha256, install_target, is_junk_entry, is_url, list_archive};
+use spawn::utils::{SYSTEM_APPLICATIONS_DIR, applications_dir, desktop_dir, desktop_file_name, find_fuzzy_matches, find_matches, format_game_name};
+
+/// What `--dry-run --json` reports: everything an install would do, without doing it.
+#[derive(Serialize, Debug)]
+pub struct InstallPlan {
+    pub input: String,
+    /// `None` when the input is a folder or a download that hasn't happened yet
+    pub archive_kind: Option<ArchiveKind>,
+    pub install_dir: Option<PathBuf>,
+    pub name: Option<String>,
+    pub executable: Option<PathBuf>,
+    pub icon: Option<PathBuf>,
+    pub actions: Vec<PlannedAction>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlannedAction {
+    ReadStdin,
+    Download { url: String, to: PathBuf },
+    VerifyChecksum { expected: String, matches: bool },
+    RemoveExisting { path: PathBuf },
+    Extract { archive: PathBuf, to: PathBuf },
+    CopyAppimage { from: PathBuf, to: PathBuf },
+    CopyFolder { from: PathBuf, to: PathBuf },
+    CreateShortcut { path: PathBuf },
+    AddToSteam { name: String },
+    RecordInstall,
+}
+
+impl std::fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file_name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match self {
+            PlannedAction::ReadStdin => write!(f, "Read the archive from stdin"),
+            PlannedAction::Download { url, to } => write!(f, "Download {} to {:?}", url, to),
+            PlannedAction::VerifyChecksum { matches: true, .. } => write!(f, "Verify the SHA-256 checksum (it matches)"),
+            PlannedAction::VerifyChecksum { matches: false, .. } => write!(f, "Verify the SHA-256 checksum ({})", "it does NOT match".red()),
+            PlannedAction::RemoveExisting { path } => write!(f, "Replace the existing {:?}", path),
+            PlannedAction::Extract { archive, to } => write!(f, "Extract {:?} to {:?}", file_name(archive), to),
+            PlannedAction::CopyAppimage { from, to } => write!(f, "Copy {:?} to {:?}", file_name(from), to),
+            PlannedAction::CopyFolder { from, to } => write!(f, "Copy the folder {:?} to {:?}", from, to),
+            PlannedAction::CreateShortcut { path } => write!(f, "Create the shortcut {:?}", path),
+            PlannedAction::AddToSteam { name } => write!(f, "Add {} to Steam", name),
+            PlannedAction::RecordInstall => write!(f, "Record the install so it can be listed and uninstalled"),
+        }
+    }
+}
+
+/// Works out what installing `input` would do, reading archives but never writing anything
+/// or printing progress, so the result can go to stdout as JSON.
+pub fn plan_install(input: &Path, args: &Args, config: &Config) -> Result<InstallPlan> {
+    let mut plan = InstallPlan {
+        input: input.to_string_lossy().into_owned(),
+        archive_kind: None,
+        install_dir: None,
+        name: None,
+        executable: None,
+        icon: None,
+        actions: Vec::new(),
+    };
+
+    if input.as_os_str() == STDIN_INPUT {
+        plan.actions.push(PlannedAction::ReadStdin);
+        plan.name = args.name.as_deref().map(format_game_name);
+        return Ok(plan);
+    }
+    if let Some(url) = input.to_str().filter(|s| is_url(s)) {
+        plan.actions.push(PlannedAction::Download { url: url.to_string(), to: config.search_dir.join(download_file_name(url)) });
+        return Ok(plan);
+    }
+
+    let input_path = resolve_input(input, &config.search_dir)?;
+    plan.input = input_path.to_string_lossy().into_owned();
+
+    let scope = config.search_scope();
+    let game_dir = if input_path.is_file() {
+        if let Some(expected) = expected_checksum(&input_path, args.sha256.as_deref()) {
+            let matches = file_sha256(&input_path)? == expected;
+            plan.actions.push(PlannedAction::VerifyChecksum { expected, matches });
+        }
+
+        let game_dir = match &args.name {
+            Some(name) => config.install_dir.join(named_install_dir(name, &config.install_dir)?),
+            None => install_target(&input_path, &config.install_dir)?,
+        };
+        if game_dir.exists() && config.overwrite_policy != OverwritePolicy::Never {
+            plan.actions.push(PlannedAction::RemoveExisting { path: game_dir.clone() });
+        }
+
+        let is_appimage = input_path.to_string_lossy().ends_with(".AppImage");
+        if is_appimage {
+            plan.actions.push(PlannedAction::CopyAppimage { from: input_path.clone(), to: game_dir.clone() });
+            plan.executable = input_path.file_name().map(|n| game_dir.join(n));
+        } else {
+            plan.archive_kind = Some(detect_archive_kind(&input_path));
+            plan.actions.push(PlannedAction::Extract { archive: input_path.clone(), to: game_dir.clone() });
+
+            // Preview paths are relative to the archive, which is unpacked as-is into the target folder
+            if let Ok(entries) = list_archive(&input_path) {
+                let names = game_dir.file_name().map(|n| vec![n.to_string_lossy().into_owned()]).unwrap_or_default();
+                plan.executable = if args.compat.is_some() {
+                    preview_windows_executable(&entries, scope, &names)
+                } else {
+                    preview_executable(&entries, scope, &names).or_else(|| preview_windows_executable(&entries, scope, &names))
+                }.map(|e| game_dir.join(e));
+                let mut icon_names = names.clone();
+                icon_names.extend(plan.executable.as_ref().and_then(|e| e.file_name()).map(|n| n.to_string_lossy().into_owned()));
+                plan.icon = preview_icon(&entries, scope, &icon_names).map(|i| game_dir.join(i));
+                // The game is named after the folder it runs from, which is nested when the archive has a single top folder
+                if args.name.is_none() && let Some(root) = single_top_folder(&entries) {
+                    plan.name = root.to_str().map(format_game_name);
+                }
+            }
+        }
+        game_dir
+    } else {
+        let bundled = find_bundled_launcher(&input_path);
+        let names = input_path.file_name().map(|n| vec![n.to_string_lossy().into_owned()]).unwrap_or_default();
+        plan.executable = if args.compat.is_some() {
+            discover_windows_executable(&input_path, scope).ok()
+        } else {
+            discover_executable(&input_path, false, scope, &names).ok()
+        };
+        let mut icon_names = names.clone();
+        icon_names.extend(plan.executable.as_ref().and_then(|e| e.file_name()).map(|n| n.to_string_lossy().into_owned()));
+        plan.icon = discover_icon(&input_path, scope, &icon_names);
+        plan.name = bundled.and_then(|l| l.name);
+        if args.copy_dir {
+            let copy = match &args.name {
+                Some(name) => config.install_dir.join(named_install_dir(name, &config.install_dir)?),
+                None => install_target(&input_path, &config.install_dir)?,
+            };
+            if copy.exists() && config.overwrite_policy != OverwritePolicy::Never {
+                plan.actions.push(PlannedAction::RemoveExisting { path: copy.clone() });
+            }
+            plan.actions.push(PlannedAction::CopyFolder { from: input_path.clone(), to: copy.clone() });
+            let rebase = |p: PathBuf| p.strip_prefix(&input_path).map(|rel| copy.join(rel)).unwrap_or(p);
+            plan.executable = plan.executable.take().map(rebase);
+            plan.icon = plan.icon.take().map(rebase);
+            copy
+        } else {
+            input_path.clone()
+        }
+    };
+
+    if let Some(icon) = &args.icon {
+        plan.icon = Some(icon.clone());
+    }
+    let name = match (&args.name, plan.name.take()) {
+        (Some(name), _) => format_game_name(name),
+        (None, Some(name)) => name,
+        (None, None) => format_game_name(game_dir.file_name().and_then(|n| n.to_str()).unwrap_or("Unknown Game")),
+    };
+
+    if !args.no_desktop {
+        plan.actions.extend(shortcut_paths(&name, &config.desktop_targets, args.system).into_iter().map(|path| PlannedAction::CreateShortcut { path }));
+    }
+    if args.steam {
+        plan.actions.push(PlannedAction::AddToSteam { name: name.clone() });
+    }
+    plan.actions.push(PlannedAction::RecordInstall);
+
+    plan.install_dir = Some(game_dir);
+    plan.name = Some(name);
+    Ok(plan)
+}
+
+/// The one folder every file in the archive sits under, if there is one. Junk such as
+/// `__MACOSX` doesn't count, as extraction ignores it too.
+fn single_top_folder(entries: &[ArchiveEntry]) -> Option<&std::ffi::OsStr> {
+    let mut tops = entries.iter().filter_map(|e| {
+        let mut components = e.path.components().filter(|c| !matches!(c, std::path::Component::CurDir));
+        let first = components.next();
+        if first.is_some_and(|c| is_junk_entry(c.as_os_str())) {
+            return None;
+        }
+        Some((first, components.next().is_some()))
+    });
+    let (Some(first), true) = tops.next()? else {
+        return None;
+    };
+    tops.all(|top| top == (Some(first), true)).then_some(first.as_os_str())
+}
+
+/// Resolves the install argument like a `--yes` run would, without printing.
+fn resolve_input(input: &Path, search_dir: &Path) -> Result<PathBuf> {
+    if input.exists() {
+        return input.canonicalize().with_context(|| format!("Failed to resolve {:?}", input));
+    }
+
+    let query = input.to_string_lossy();
+    find_matches(&query, search_dir).into_iter().next()
+        .or_else(|| find_fuzzy_matches(&query, search_dir).into_iter().next().map(|(m, _)| m))
+        .ok_or_else(|| SpawnError::NoMatch { query: input.display().to_string(), dir: search_dir.to_path_buf() })
+}
+
+/// Where the shortcuts would be written, mirroring `generate_desktop_entry`.
+fn shortcut_paths(name: &str, targets: &[DesktopTarget], system: bool) -> Vec<PathBuf> {
+    let file_name = desktop_file_name(name);
+    if system {
+        return vec![Path::new(SYSTEM_APPLICATIONS_DIR).join(file_name)];
+    }
+
+    let mut paths = Vec::new();
+    if targets.contains(&DesktopTarget::Menu) && let Some(dir) = applications_dir() {
+        paths.push(dir.join(&file_name));
+    }
+    if targets.contains(&DesktopTarget::Desktop) && let Some(dir) = desktop_dir().filter(|d| d.exists()) {
+        paths.push(dir.join(&file_name));
+    }
+    paths
+}
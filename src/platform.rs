@@ -0,0 +1,52 @@
+//! Where desktop and Steam files live on each OS Spawn supports. Linux is the reference layout;
+//! FreeBSD shares the per-user XDG folders but keeps system data under `/usr/local` and runs
+//! Steam through linux-steam-utils.
+
+use std::path::{Path, PathBuf};
+
+/// Where shortcuts for every user live, for `--system` installs.
+#[cfg(not(target_os = "freebsd"))]
+pub const SYSTEM_APPLICATIONS_DIR: &str = "/usr/share/applications";
+
+/// Where shortcuts for every user live, for `--system` installs. Ports install under `/usr/local`.
+#[cfg(target_os = "freebsd")]
+pub const SYSTEM_APPLICATIONS_DIR: &str = "/usr/local/share/applications";
+
+/// `$XDG_DATA_HOME/applications`, where desktop environments look for menu entries.
+pub fn applications_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|d| d.join("applications"))
+}
+
+/// The user's (possibly localized) Desktop folder, from `XDG_DESKTOP_DIR` or `user-dirs.dirs`.
+pub fn desktop_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DESKTOP_DIR")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(dirs_next::desktop_dir)
+        .or_else(|| dirs_next::home_dir().map(|h| h.join("Desktop")))
+}
+
+/// `$XDG_DATA_HOME/icons/hicolor`, the fallback icon theme every desktop searches.
+pub fn hicolor_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|d| d.join("icons").join("hicolor"))
+}
+
+/// Steam's own folder, holding `userdata` and `steamapps`: the first known location that exists,
+/// or the usual one so error messages can name it.
+pub fn steam_root() -> PathBuf {
+    let home = dirs_next::home_dir().unwrap_or_default();
+    let candidates = steam_root_candidates(&home);
+    candidates.iter().find(|dir| dir.exists()).unwrap_or(&candidates[0]).clone()
+}
+
+#[cfg(not(target_os = "freebsd"))]
+fn steam_root_candidates(home: &Path) -> Vec<PathBuf> {
+    vec![home.join(".steam/steam")]
+}
+
+/// linux-steam-utils keeps Steam in `~/.steam/steam` like Linux, but depending on how it was
+/// bootstrapped the data may only be in `~/.local/share/Steam`.
+#[cfg(target_os = "freebsd")]
+fn steam_root_candidates(home: &Path) -> Vec<PathBuf> {
+    vec![home.join(".steam/steam"), home.join(".local/share/Steam")]
+}
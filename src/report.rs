@@ -0,0 +1,141 @@
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::error::SpawnError;
+
+/// Something that happened while Spawn was working, for a frontend to render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Work that is starting, e.g. extracting an archive
+    Step(String),
+    Success(String),
+    /// Something the user should know about; the operation carries on
+    Warning(String),
+    /// A long-running task began. `total` is in bytes, or `None` when the size isn't known.
+    TaskStarted { label: &'static str, total: Option<u64> },
+    /// Bytes processed so far by the running task
+    TaskProgress { done: u64 },
+    TaskFinished,
+    /// Longer text to show as-is, such as a setup script the user asked to read
+    Listing(String),
+}
+
+/// Receives everything Spawn reports. The command-line tool renders events as colored lines
+/// and progress bars; other frontends install their own with [`set_reporter`].
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: Event);
+
+    /// Asks a yes/no question. Frontends that can't ask keep `default`.
+    fn confirm(&self, _question: &str, default: bool) -> Result<bool, SpawnError> {
+        Ok(default)
+    }
+
+    /// Lets the user pick one of `items`; `None` when they made no choice.
+    fn choose(&self, _question: &str, _items: &[String]) -> Result<Option<usize>, SpawnError> {
+        Ok(None)
+    }
+
+    /// Asks for a password without showing it; `None` when there's no one to ask.
+    fn password(&self, _prompt: &str) -> Result<Option<String>, SpawnError> {
+        Ok(None)
+    }
+}
+
+static REPORTER: OnceLock<Box<dyn Reporter>> = OnceLock::new();
+
+/// Sends every event from now on to `reporter`. Only the first call takes effect;
+/// returns whether this one did. Without a reporter, events are dropped.
+pub fn set_reporter(reporter: Box<dyn Reporter>) -> bool {
+    REPORTER.set(reporter).is_ok()
+}
+
+pub fn emit(event: Event) {
+    if let Some(reporter) = REPORTER.get() {
+        reporter.report(event);
+    }
+}
+
+/// Asks through the reporter; without one, `default` is the answer.
+pub fn confirm(question: &str, default: bool) -> Result<bool, SpawnError> {
+    match REPORTER.get() {
+        Some(reporter) => reporter.confirm(question, default),
+        None => Ok(default),
+    }
+}
+
+pub fn choose(question: &str, items: &[String]) -> Result<Option<usize>, SpawnError> {
+    match REPORTER.get() {
+        Some(reporter) => reporter.choose(question, items),
+        None => Ok(None),
+    }
+}
+
+pub fn password(prompt: &str) -> Result<Option<String>, SpawnError> {
+    match REPORTER.get() {
+        Some(reporter) => reporter.password(prompt),
+        None => Ok(None),
+    }
+}
+
+pub fn step(message: impl Into<String>) {
+    emit(Event::Step(message.into()));
+}
+
+pub fn success(message: impl Into<String>) {
+    emit(Event::Success(message.into()));
+}
+
+pub fn warning(message: impl Into<String>) {
+    emit(Event::Warning(message.into()));
+}
+
+/// A running long task. Reports [`Event::TaskFinished`] when dropped.
+pub struct Task {
+    done: Arc<AtomicU64>,
+}
+
+impl Task {
+    pub fn start(label: &'static str, total: Option<u64>) -> Task {
+        emit(Event::TaskStarted { label, total });
+        Task { done: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// Counts bytes read through `reader` towards the task's progress.
+    pub fn wrap_read<R: Read>(&self, reader: R) -> TaskReader<R> {
+        TaskReader { inner: reader, done: Arc::clone(&self.done) }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        emit(Event::TaskFinished);
+    }
+}
+
+pub struct TaskReader<R> {
+    inner: R,
+    done: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for TaskReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let done = self.done.fetch_add(read as u64, Ordering::Relaxed) + read as u64;
+        emit(Event::TaskProgress { done });
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn questions_without_a_reporter_get_the_default_answer() {
+        assert!(confirm("Overwrite?", true).unwrap());
+        assert!(!confirm("Overwrite?", false).unwrap());
+        assert_eq!(choose("Which one?", &["a".to_string(), "b".to_string()]).unwrap(), None);
+        assert_eq!(password("Password:").unwrap(), None);
+    }
+}
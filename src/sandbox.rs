@@ -0,0 +1,67 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+use crate::error::{Result, SpawnError};
+use crate::utils::find_in_path;
+
+/// Sandboxing tools that can confine a game's launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SandboxTool {
+    Bwrap,
+    Firejail,
+}
+
+impl SandboxTool {
+    pub fn label(self) -> &'static str {
+        match self {
+            SandboxTool::Bwrap => "bubblewrap",
+            SandboxTool::Firejail => "Firejail",
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            SandboxTool::Bwrap => "bwrap",
+            SandboxTool::Firejail => "firejail",
+        }
+    }
+}
+
+/// Builds the command words that confine a game: the system is read-only, only the game
+/// folder is writable, and the network is cut off unless `allow_network` is set.
+pub fn sandbox_command(tool: SandboxTool, game_dir: &Path, allow_network: bool) -> Result<Vec<String>> {
+    if find_in_path(tool.program()).is_none() {
+        return Err(SpawnError::ProgramMissing { label: tool.label(), program: tool.program() });
+    }
+
+    let game_dir = game_dir.to_string_lossy().into_owned();
+    let mut words = vec![tool.program().to_string()];
+    match tool {
+        SandboxTool::Bwrap => {
+            // /dev and /tmp stay shared so the GPU and the X11/Wayland sockets keep working
+            words.extend([
+                "--ro-bind", "/", "/",
+                "--dev-bind", "/dev", "/dev",
+                "--proc", "/proc",
+                "--bind", "/tmp", "/tmp",
+                "--bind", &game_dir, &game_dir,
+                "--die-with-parent",
+            ].map(String::from));
+            if !allow_network {
+                words.push("--unshare-net".to_string());
+            }
+            words.push("--".to_string());
+        }
+        SandboxTool::Firejail => {
+            let home = dirs_next::home_dir().map(|h| h.to_string_lossy().into_owned()).unwrap_or_else(|| "~".to_string());
+            words.push("--noprofile".to_string());
+            words.push(format!("--read-only={}", home));
+            words.push(format!("--read-write={}", game_dir));
+            if !allow_network {
+                words.push("--net=none".to_string());
+            }
+            words.push("--".to_string());
+        }
+    }
+    Ok(words)
+}
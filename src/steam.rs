@@ -1,75 +1,371 @@
-use anyhow::{Context, Result, anyhow};
 use std::path::{Path, PathBuf};
 use std::fs;
-use steam_shortcuts_util::{parse_shortcuts, shortcuts_to_bytes, Shortcut};
-use colored::Colorize;
+use steam_shortcuts_util::app_id_generator::calculate_app_id;
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+use steam_shortcuts_util::{parse_shortcuts, shortcuts_to_bytes};
 
-pub fn add_to_steam(game_name: &str, executable: &Path, icon: Option<&Path>) -> Result<()> {
-    let shortcuts_path = find_shortcuts_vdf()?;
-    println!("{} Found Steam shortcuts at: {:?}", "▶".cyan(), shortcuts_path);
+use crate::error::{IoContext, Result, SpawnError};
+use crate::platform::steam_root;
+use crate::utils::{LaunchCommand, write_atomic};
+use crate::report;
 
-    let content = fs::read(&shortcuts_path).context("Failed to read shortcuts.vdf")?;
-    let mut shortcuts = parse_shortcuts(&content)
-        .map_err(|e| anyhow!("Failed to parse shortcuts.vdf: {:?}", e))?;
+/// What goes into a game's non-Steam shortcut.
+pub struct SteamShortcut<'a> {
+    pub name: &'a str,
+    pub executable: &'a Path,
+    /// Folder the game starts in
+    pub start_dir: &'a Path,
+    pub icon: Option<&'a Path>,
+    pub launch: &'a LaunchCommand,
+    /// Collections the shortcut is sorted into, e.g. "Installed via Spawn"
+    pub tags: &'a [String],
+}
+
+pub fn add_to_steam(shortcut: &SteamShortcut, steam_user: Option<&str>, interactive: bool) -> Result<bool> {
+    let game_name = shortcut.name;
+    let shortcuts_path = find_shortcuts_vdf(steam_user, interactive)?;
+    report::step(format!("Found Steam shortcuts at: {:?}", shortcuts_path));
+
+    if !confirm_steam_closed(interactive)? {
+        report::step(format!("Skipped adding {} to Steam. Close Steam completely and run Spawn again.", game_name));
+        return Ok(false);
+    }
+
+    let mut shortcuts = read_shortcuts(&shortcuts_path)?;
 
     // Check if already exists
     if shortcuts.iter().any(|s| s.app_name == game_name) {
-        println!("{} Game already exists in Steam shortcuts.", "⚠".yellow());
-        return Ok(());
+        report::warning("Game already exists in Steam shortcuts.");
+        return Ok(false);
     }
 
-    let new_shortcut = Shortcut {
-        app_name: game_name,
-        exe: executable.to_str().unwrap_or_default(),
-        start_dir: executable.parent().and_then(|p| p.to_str()).unwrap_or_default(),
-        icon: icon.and_then(|p| p.to_str()).unwrap_or_default(),
-        shortcut_path: "",
-        launch_options: "",
+    let new_shortcut = new_shortcut(shortcut);
+    let app_id = new_shortcut.app_id;
+    shortcuts.push(new_shortcut);
+    write_shortcuts(&shortcuts_path, &shortcuts)?;
+
+    report::success(format!("Added {} to Steam!", game_name));
+
+    if let Some(config_dir) = shortcuts_path.parent() {
+        let grid_dir = config_dir.join("grid");
+        if let Some(icon) = shortcut.icon && let Err(e) = install_grid_artwork(&grid_dir, app_id, icon) {
+            report::warning(format!("Failed to add Steam artwork: {}", e));
+        }
+        let files = GRID_ARTWORK.iter()
+            .map(|(suffix, role)| format!("\n    {}{}.png  ({})", app_id, suffix, role))
+            .collect::<String>();
+        report::step(format!("Steam app id: {}. For custom artwork, put images (PNG or JPG) in {:?}:{}", app_id, grid_dir, files));
+    }
+
+    Ok(true)
+}
+
+/// True when a Steam client is running. Steam rewrites `shortcuts.vdf` when it exits,
+/// discarding any shortcuts added in the meantime.
+fn steam_is_running() -> bool {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return false;
+    };
+    entries.filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().chars().all(|c| c.is_ascii_digit()))
+        .any(|e| fs::read_to_string(e.path().join("comm")).is_ok_and(|comm| comm.trim() == "steam"))
+}
+
+/// Warns when Steam is open and asks whether to edit its shortcuts anyway.
+/// Non-interactive runs (`--yes`/`--force`) carry on after the warning.
+fn confirm_steam_closed(interactive: bool) -> Result<bool> {
+    if !steam_is_running() {
+        return Ok(true);
+    }
+
+    report::warning("Steam is running. It overwrites its shortcuts when it exits, so changes made now may be lost.");
+    if !interactive {
+        return Ok(true);
+    }
+
+    report::confirm("Close Steam completely (Steam > Exit) first, then answer. Continue?", false)
+}
+
+/// Steam's `config/grid` file name suffixes, after the app id, and what each image is for.
+pub const GRID_ARTWORK: &[(&str, &str)] = &[
+    ("p", "portrait cover"),
+    ("", "wide cover"),
+    ("_hero", "banner behind the game page"),
+    ("_logo", "logo over the banner"),
+    ("_icon", "icon"),
+];
+
+/// The app id Steam gives a non-Steam shortcut: the CRC32 of its exe and name with the high
+/// bit set. Artwork in `config/grid` is named after it.
+pub fn shortcut_app_id(executable: &Path, name: &str) -> u32 {
+    calculate_app_id(&executable.to_string_lossy(), name)
+}
+
+/// Builds a shortcut for a game, with the app id Steam will expect for it.
+fn new_shortcut(shortcut: &SteamShortcut) -> ShortcutOwned {
+    let exe = shortcut.executable.to_string_lossy().into_owned();
+    ShortcutOwned {
+        order: String::new(),
+        app_id: shortcut_app_id(shortcut.executable, shortcut.name),
+        app_name: shortcut.name.to_string(),
+        start_dir: shortcut.start_dir.to_string_lossy().into_owned(),
+        exe,
+        icon: shortcut.icon.map(|p| p.to_string_lossy().into_owned()).unwrap_or_default(),
+        shortcut_path: String::new(),
+        launch_options: shortcut.launch.steam_launch_options(),
         is_hidden: false,
         allow_desktop_config: true,
         allow_overlay: true,
         open_vr: 0,
         dev_kit: 0,
-        dev_kit_game_id: "",
-        last_play_time: 0,
-        tags: Vec::new(),
-        app_id: 0,
-        order: "",
+        dev_kit_game_id: String::new(),
         dev_kit_overrite_app_id: 0,
-    };
+        last_play_time: 0,
+        tags: shortcut.tags.to_vec(),
+    }
+}
 
-    shortcuts.push(new_shortcut);
+/// Reads every shortcut in a `shortcuts.vdf`, owning the data so it outlives the file contents.
+fn read_shortcuts(path: &Path) -> Result<Vec<ShortcutOwned>> {
+    let content = fs::read(path).context("Failed to read shortcuts.vdf")?;
+    let shortcuts = parse_shortcuts(&content)
+        .map_err(SpawnError::InvalidShortcuts)?;
+    Ok(shortcuts.iter().map(|s| s.to_owned()).collect())
+}
+
+fn write_shortcuts(path: &Path, shortcuts: &[ShortcutOwned]) -> Result<()> {
+    let borrowed = shortcuts.iter().map(ShortcutOwned::borrow).collect();
+    write_atomic(path, shortcuts_to_bytes(&borrowed)).context("Failed to write shortcuts.vdf")
+}
+
+/// Copies the game's icon into Steam's `config/grid` folder so the shortcut isn't blank in the library.
+/// Existing artwork is never overwritten, since users often set custom images.
+fn install_grid_artwork(grid_dir: &Path, app_id: u32, icon: &Path) -> Result<()> {
+    let extension = icon.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if extension != "png" && extension != "jpg" {
+        report::warning(format!("Steam artwork needs a PNG or JPG icon, skipping {:?}", icon.file_name().unwrap_or_default()));
+        return Ok(());
+    }
+
+    fs::create_dir_all(grid_dir).context("Failed to create Steam grid directory")?;
 
-    let new_content = shortcuts_to_bytes(&shortcuts);
-    fs::write(&shortcuts_path, new_content).context("Failed to write shortcuts.vdf")?;
+    let mut installed = 0;
+    for suffix in ["p", "_logo"] {
+        let target = grid_dir.join(format!("{}{}.{}", app_id, suffix, extension));
+        if !target.exists() {
+            fs::copy(icon, &target).context("Failed to copy Steam artwork")?;
+            installed += 1;
+        }
+    }
 
-    println!("{} Added {} to Steam! (Restart Steam to see changes)", "✔".green(), game_name);
+    if installed > 0 {
+        report::success("Added Steam library artwork");
+    }
     Ok(())
 }
 
-fn find_shortcuts_vdf() -> Result<PathBuf> {
-    let steam_dir = dirs_next::home_dir()
-        .map(|h| h.join(".steam/steam/userdata"))
-        .ok_or_else(|| anyhow!("Could not find Steam directory"))?;
+/// Locates the `shortcuts.vdf` to edit. With several Steam accounts on the machine,
+/// `steam_user` pins one; otherwise the user is asked (or the first is used non-interactively).
+fn find_shortcuts_vdf(steam_user: Option<&str>, interactive: bool) -> Result<PathBuf> {
+    let steam_dir = steam_userdata_dir()?;
+    let users = steam_users(&steam_dir)?;
+
+    if let Some(id) = steam_user {
+        let (_, path) = users.iter().find(|(u, _)| u == id)
+            .ok_or_else(|| SpawnError::SteamUserNotFound { id: id.to_string(), dir: steam_dir.clone() })?;
+        let shortcuts_path = path.join("config/shortcuts.vdf");
+        if !shortcuts_path.exists() {
+            return Err(SpawnError::NoSteamShortcuts(id.to_string()));
+        }
+        return Ok(shortcuts_path);
+    }
+
+    let mut candidates: Vec<(String, PathBuf)> = users.into_iter()
+        .map(|(id, path)| (id, path.join("config/shortcuts.vdf")))
+        .filter(|(_, p)| p.exists())
+        .collect();
+
+    match candidates.len() {
+        0 => Err(SpawnError::ShortcutsNotFound(steam_dir)),
+        1 => Ok(candidates.remove(0).1),
+        _ if !interactive => Ok(candidates.remove(0).1),
+        _ => {
+            report::step("Multiple Steam accounts found:");
+            let items = candidates.iter().map(|(id, _)| match persona_name(&steam_dir.join(id)) {
+                Some(name) => format!("{} ({})", name, id),
+                None => id.clone(),
+            }).collect::<Vec<_>>();
+            let index = report::choose("Enter the number of the account to add the game to (or press Enter to cancel):", &items)?
+                .ok_or(SpawnError::Cancelled)?;
+            if index >= candidates.len() {
+                return Err(SpawnError::InvalidSelection((index + 1).to_string()));
+            }
+            Ok(candidates.remove(index).1)
+        }
+    }
+}
 
+fn steam_userdata_dir() -> Result<PathBuf> {
+    let steam_dir = steam_root().join("userdata");
     if !steam_dir.exists() {
-        return Err(anyhow!("Steam userdata directory not found at {:?}", steam_dir));
+        return Err(SpawnError::SteamNotFound(steam_dir));
     }
+    Ok(steam_dir)
+}
 
-    // Find the first numeric directory (User ID)
-    let entries = fs::read_dir(&steam_dir)?;
-    for entry in entries.filter_map(|e| e.ok()) {
+/// Lists `(user ID, directory)` pairs; each numeric directory under userdata is an account.
+fn steam_users(steam_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut users = Vec::new();
+    for entry in fs::read_dir(steam_dir).context("Failed to list Steam accounts")?.filter_map(|e| e.ok()) {
         let path = entry.path();
-        if path.is_dir() {
-            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if file_name.chars().all(|c| c.is_numeric()) {
-                let shortcuts_path = path.join("config/shortcuts.vdf");
-                if shortcuts_path.exists() {
-                    return Ok(shortcuts_path);
-                }
-            }
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        if path.is_dir() && !file_name.is_empty() && file_name.chars().all(|c| c.is_numeric()) {
+            users.push((file_name, path));
         }
     }
+    users.sort();
+    Ok(users)
+}
+
+/// The `shortcuts.vdf` of every Steam account with a shortcut named `game_name`, read in.
+fn accounts_with_shortcut(steam_dir: &Path, game_name: &str) -> Result<Vec<(PathBuf, PathBuf, Vec<ShortcutOwned>)>> {
+    let mut accounts = Vec::new();
+    for (_, user_dir) in steam_users(steam_dir)? {
+        let shortcuts_path = user_dir.join("config/shortcuts.vdf");
+        if !shortcuts_path.exists() {
+            continue;
+        }
+        let shortcuts = read_shortcuts(&shortcuts_path)?;
+        if shortcuts.iter().any(|s| s.app_name == game_name) {
+            accounts.push((user_dir, shortcuts_path, shortcuts));
+        }
+    }
+    Ok(accounts)
+}
+
+/// Renames a shortcut in every Steam account that has it, pointing it at the (possibly moved) executable
+/// and working folder. Artwork is renamed along with it, since the app id depends on the name. Returns whether anything changed.
+pub fn rename_in_steam(old_name: &str, new_name: &str, executable: &Path, start_dir: &Path, launch: Option<&LaunchCommand>, interactive: bool, dry_run: bool) -> Result<bool> {
+    let Ok(steam_dir) = steam_userdata_dir() else {
+        return Ok(false);
+    };
+    let accounts = accounts_with_shortcut(&steam_dir, old_name)?;
+    if accounts.is_empty() {
+        return Ok(false);
+    }
+    if dry_run {
+        for (_, shortcuts_path, _) in &accounts {
+            report::step(format!("Would rename Steam shortcut in {:?}", shortcuts_path));
+        }
+        return Ok(true);
+    }
+    if !confirm_steam_closed(interactive)? {
+        report::step("Left the Steam shortcut unchanged");
+        return Ok(false);
+    }
+
+    let exe = executable.to_string_lossy().into_owned();
+    let start_dir = start_dir.to_string_lossy().into_owned();
+    for (user_dir, shortcuts_path, mut shortcuts) in accounts {
+        let Some(shortcut) = shortcuts.iter_mut().find(|s| s.app_name == old_name) else {
+            continue;
+        };
+        let old_id = shortcut.app_id;
+        shortcut.app_name = new_name.to_string();
+        shortcut.exe = exe.clone();
+        shortcut.start_dir = start_dir.clone();
+        if let Some(launch) = launch {
+            shortcut.launch_options = launch.steam_launch_options();
+        }
+        shortcut.app_id = calculate_app_id(&shortcut.exe, &shortcut.app_name);
+        let new_id = shortcut.app_id;
+
+        write_shortcuts(&shortcuts_path, &shortcuts)?;
+        rename_grid_artwork(&user_dir.join("config/grid"), old_id, new_id);
+        report::success("Renamed Steam shortcut");
+    }
+
+    Ok(true)
+}
+
+/// Rewrites the launch options of a game's shortcut in every Steam account that has it.
+/// Returns whether any shortcut was updated.
+pub fn update_steam_launch_options(game_name: &str, launch: &LaunchCommand, interactive: bool) -> Result<bool> {
+    let Ok(steam_dir) = steam_userdata_dir() else {
+        return Ok(false);
+    };
+    let accounts = accounts_with_shortcut(&steam_dir, game_name)?;
+    if accounts.is_empty() {
+        return Ok(false);
+    }
+    if !confirm_steam_closed(interactive)? {
+        report::step("Left the Steam shortcut's launch options unchanged");
+        return Ok(false);
+    }
+
+    let launch_options = launch.steam_launch_options();
+    for (_, shortcuts_path, mut shortcuts) in accounts {
+        // Launch options aren't part of the app id, so artwork stays attached
+        for shortcut in shortcuts.iter_mut().filter(|s| s.app_name == game_name) {
+            shortcut.launch_options = launch_options.clone();
+        }
+        write_shortcuts(&shortcuts_path, &shortcuts)?;
+    }
+
+    Ok(true)
+}
+
+/// Removes a game's shortcut from every Steam account that has it. Returns whether one was removed
+/// (or, in a dry run, would be).
+pub fn remove_from_steam(game_name: &str, interactive: bool, dry_run: bool) -> Result<bool> {
+    let Ok(steam_dir) = steam_userdata_dir() else {
+        return Ok(false);
+    };
+
+    let accounts = accounts_with_shortcut(&steam_dir, game_name)?;
+    if accounts.is_empty() {
+        return Ok(false);
+    }
+    if dry_run {
+        for (_, shortcuts_path, _) in &accounts {
+            report::step(format!("Would remove Steam shortcut from {:?}", shortcuts_path));
+        }
+        return Ok(true);
+    }
+    if !confirm_steam_closed(interactive)? {
+        report::step("Left the Steam shortcut in place");
+        return Ok(false);
+    }
+
+    for (_, shortcuts_path, mut shortcuts) in accounts {
+        shortcuts.retain(|s| s.app_name != game_name);
+        write_shortcuts(&shortcuts_path, &shortcuts)?;
+    }
+    report::success("Removed Steam shortcut");
+    Ok(true)
+}
+
+fn rename_grid_artwork(grid_dir: &Path, old_id: u32, new_id: u32) {
+    let Ok(entries) = fs::read_dir(grid_dir) else {
+        return;
+    };
+    let old_prefix = old_id.to_string();
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(rest) = file_name.strip_prefix(&old_prefix)
+            && !rest.starts_with(|c: char| c.is_ascii_digit())
+        {
+            let _ = fs::rename(entry.path(), grid_dir.join(format!("{}{}", new_id, rest)));
+        }
+    }
+}
 
-    Err(anyhow!("Could not find shortcuts.vdf in {:?}", steam_dir))
+/// Reads the account's display name from `config/localconfig.vdf`, if present.
+fn persona_name(user_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(user_dir.join("config/localconfig.vdf")).ok()?;
+    content.lines()
+        .find(|l| l.trim_start().starts_with("\"PersonaName\""))
+        .and_then(|l| l.split('"').nth(3))
+        .map(|name| name.to_string())
 }
@@ -0,0 +1,143 @@
+use colored::*;
+use indicatif::style::TemplateError;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use spawn::error::SpawnError;
+use spawn::report::{Event, Reporter};
+
+/// Renders Spawn's events on stderr, keeping stdout for data: a colored line per message, and a
+/// progress bar (or a spinner when the size isn't known) while a long task runs.
+pub struct TerminalReporter {
+    bar: Mutex<Option<ProgressBar>>,
+    /// `--quiet`: only warnings are shown, on stderr
+    quiet: bool,
+}
+
+impl TerminalReporter {
+    pub fn new(quiet: bool) -> TerminalReporter {
+        TerminalReporter { bar: Mutex::new(None), quiet }
+    }
+}
+
+impl Reporter for TerminalReporter {
+    fn report(&self, event: Event) {
+        if self.quiet {
+            // Listings are only shown when asked for, so they aren't quieted
+            match event {
+                Event::Warning(message) => eprintln!("⚠ {}", message),
+                Event::Listing(text) => eprintln!("{}", text.trim_end()),
+                _ => {}
+            }
+            return;
+        }
+
+        let mut bar = self.bar.lock().unwrap_or_else(|e| e.into_inner());
+        let line = match event {
+            Event::Step(message) => format!("{} {}", "▶".cyan(), message),
+            Event::Success(message) => format!("{} {}", "✔".green(), message),
+            Event::Warning(message) => format!("{} {}", "⚠".yellow(), message),
+            Event::Listing(text) => format!("{}\n{}\n{}", "----".dimmed(), text.trim_end(), "----".dimmed()),
+            Event::TaskStarted { label, total } => {
+                *bar = progress_bar(label, total).ok();
+                return;
+            }
+            Event::TaskProgress { done } => {
+                if let Some(pb) = bar.as_ref() {
+                    pb.set_position(done);
+                }
+                return;
+            }
+            Event::TaskFinished => {
+                if let Some(pb) = bar.take() {
+                    pb.finish_and_clear();
+                }
+                return;
+            }
+        };
+
+        // Printed through the bar so it isn't drawn over; a hidden bar (stderr isn't a terminal) would drop it
+        match bar.as_ref().filter(|pb| !pb.is_hidden()) {
+            Some(pb) => pb.println(line),
+            None => eprintln!("{}", line),
+        }
+    }
+
+    fn confirm(&self, question: &str, default: bool) -> Result<bool, SpawnError> {
+        eprintln!("  {} {}", question, if default { "[Y/n]" } else { "[y/N]" });
+        Ok(match read_line()?.to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => default,
+        })
+    }
+
+    /// Prints a numbered list and reads the user's choice. Returns `None` if they just press Enter;
+    /// `q` cancels the whole operation.
+    fn choose(&self, question: &str, items: &[String]) -> Result<Option<usize>, SpawnError> {
+        for (i, item) in items.iter().enumerate() {
+            eprintln!("  {}. {}", i + 1, item);
+        }
+        eprintln!("{} {}", "▶".cyan(), question);
+
+        let choice = read_line()?;
+        if choice.is_empty() {
+            return Ok(None);
+        }
+        if choice.eq_ignore_ascii_case("q") {
+            return Err(SpawnError::Cancelled);
+        }
+        match choice.parse::<usize>() {
+            Ok(index) if (1..=items.len()).contains(&index) => Ok(Some(index - 1)),
+            _ => Err(SpawnError::InvalidSelection(choice)),
+        }
+    }
+
+    /// Reads a line from the terminal without echoing it. Falls back to a plain read when stdin
+    /// isn't a terminal.
+    fn password(&self, prompt: &str) -> Result<Option<String>, SpawnError> {
+        use nix::sys::termios::{LocalFlags, SetArg, tcgetattr, tcsetattr};
+        let failed = |error: std::io::Error| SpawnError::Io { context: "Failed to read the password".to_string(), error };
+        eprint!("  {} ", prompt);
+
+        let stdin = std::io::stdin();
+        let saved = tcgetattr(&stdin).ok();
+        if let Some(saved) = &saved {
+            let mut silent = saved.clone();
+            silent.local_flags.remove(LocalFlags::ECHO);
+            tcsetattr(&stdin, SetArg::TCSANOW, &silent).map_err(|e| failed(e.into()))?;
+        }
+
+        let mut password = String::new();
+        let result = stdin.read_line(&mut password);
+        if let Some(saved) = &saved {
+            tcsetattr(&stdin, SetArg::TCSANOW, saved).map_err(|e| failed(e.into()))?;
+        }
+        eprintln!();
+        result.map_err(failed)?;
+        Ok(Some(password.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+/// One trimmed line typed by the user.
+fn read_line() -> Result<String, SpawnError> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).map_err(|error| SpawnError::Io { context: "Failed to read input".to_string(), error })?;
+    Ok(line.trim().to_string())
+}
+
+fn progress_bar(label: &'static str, total: Option<u64>) -> Result<ProgressBar, TemplateError> {
+    let pb = match total {
+        Some(total) => ProgressBar::new(total).with_style(ProgressStyle::default_bar()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈")
+            .template("{spinner:.cyan} {msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes}")?
+            .progress_chars("=> ")),
+        None => ProgressBar::new_spinner().with_style(ProgressStyle::default_spinner()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈")
+            .template("{spinner:.cyan} {msg}")?),
+    };
+    pb.set_message(label);
+    pb.enable_steady_tick(Duration::from_millis(100));
+    Ok(pb)
+}
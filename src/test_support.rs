@@ -0,0 +1,31 @@
+//! Scratch folders for unit tests.
+
+use std::path::{Path, PathBuf};
+
+/// A uniquely named folder under the system temp dir, removed with everything in it on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let template = std::env::temp_dir().join("spawn-test-XXXXXX");
+        TempDir(nix::unistd::mkdtemp(&template).expect("failed to create a temp dir"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes `contents` to `relative`, creating parent folders, and returns the full path.
+    pub fn write(&self, relative: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
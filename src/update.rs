@@ -0,0 +1,352 @@
+use colored::*;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::discovery::is_elf_binary;
+use crate::error::{IoContext, Result, SpawnError};
+use crate::report::{self, Task};
+use crate::utils::write_atomic;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/Anayo-Anyafulu/Spawn/releases/latest";
+
+/// Where the update check reads the latest version from.
+pub const VERSION_URL: &str = "https://raw.githubusercontent.com/Anayo-Anyafulu/Spawn/master/Cargo.toml";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// How the update check reaches GitHub and how long a result is trusted.
+pub struct UpdateCheck<'a> {
+    /// Limit for each attempt; a failed attempt is retried twice, waiting a little longer each time
+    pub timeout: Duration,
+    /// How long a cached result is used before asking again; zero always asks
+    pub interval: Duration,
+    /// Where the last successful result is kept, if anywhere
+    pub cache: Option<&'a Path>,
+}
+
+/// The last version the update check saw, and when (seconds since the Unix epoch).
+#[derive(Serialize, Deserialize)]
+struct CachedCheck {
+    checked_at: u64,
+    latest: String,
+}
+
+/// Returns the newer version available on GitHub, if any. Gives up silently when offline.
+pub fn check_for_updates(check: &UpdateCheck) -> Option<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let cached = check.cache
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<CachedCheck>(&content).ok());
+    let latest = match cached {
+        Some(cached) if now.saturating_sub(cached.checked_at) < check.interval.as_secs() => {
+            log::debug!("Using cached update check from {}s ago", now - cached.checked_at);
+            Version::parse(&cached.latest).ok()?
+        }
+        _ => {
+            let latest = fetch_latest_version(check.timeout)?;
+            if let Some(path) = check.cache {
+                let cached = CachedCheck { checked_at: now, latest: latest.to_string() };
+                let written = path.parent().map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| write_atomic(path, serde_json::to_string(&cached).unwrap_or_default()));
+                if let Err(e) = written {
+                    log::debug!("Failed to cache the update check in {:?}: {}", path, e);
+                }
+            }
+            latest
+        }
+    };
+    is_newer(&latest).then(|| latest.to_string())
+}
+
+/// Reads the version on GitHub's master branch, trying three times with a growing pause.
+fn fetch_latest_version(timeout: Duration) -> Option<Version> {
+    let agent = check_agent(timeout);
+
+    for attempt in 0..3 {
+        if attempt > 0 {
+            std::thread::sleep(Duration::from_millis(250 << attempt));
+        }
+        match agent.get(VERSION_URL).call() {
+            Ok(response) => return package_version(&response.into_string().ok()?),
+            Err(e) => log::debug!("Update check attempt {} failed: {}", attempt + 1, e),
+        }
+    }
+    None
+}
+
+fn check_agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_read(timeout)
+        .timeout_connect(timeout)
+        .build()
+}
+
+/// Makes one request to [`VERSION_URL`], for `--doctor` to tell whether update checks can work.
+pub fn ping_update_server(timeout: Duration) -> Result<()> {
+    check_agent(timeout).head(VERSION_URL).call()
+        .map_err(|e| SpawnError::Unreachable { url: VERSION_URL, reason: e.to_string() })?;
+    Ok(())
+}
+
+/// Reads `[package].version` from a Cargo.toml, ignoring versions in dependency tables.
+fn package_version(manifest: &str) -> Option<Version> {
+    let table: toml::Table = manifest.parse().ok()?;
+    let version = table.get("package")?.get("version")?.as_str()?;
+    Version::parse(version).ok()
+}
+
+/// True when `remote` is strictly newer than the running build.
+fn is_newer(remote: &Version) -> bool {
+    Version::parse(env!("CARGO_PKG_VERSION")).is_ok_and(|current| *remote > current)
+}
+
+/// Updates Spawn: rebuilds from source when running from a git checkout, otherwise
+/// replaces the running binary with the latest release asset. A release asset without a
+/// published checksum is only installed when `allow_unverified` is set.
+pub fn update_spawn(allow_unverified: bool) -> Result<()> {
+    report::step("Updating Spawn...");
+    match source_checkout() {
+        Some(checkout) => update_from_source(&checkout),
+        None => update_from_release(allow_unverified),
+    }
+}
+
+/// Returns the Spawn source checkout we're running from, if any: the current directory
+/// or one of the running executable's parents (e.g. `target/release/spawn`).
+fn source_checkout() -> Option<PathBuf> {
+    let is_checkout = |dir: &Path| {
+        dir.join(".git").exists()
+            && fs::read_to_string(dir.join("Cargo.toml"))
+                .map(|s| s.lines().any(|l| l.trim().replace(' ', "") == "name=\"spawn\""))
+                .unwrap_or(false)
+    };
+
+    if let Ok(cwd) = std::env::current_dir() && is_checkout(&cwd) {
+        return Some(cwd);
+    }
+
+    let exe = std::env::current_exe().ok()?;
+    exe.ancestors().skip(1).find(|dir| is_checkout(dir)).map(Path::to_path_buf)
+}
+
+fn update_from_source(checkout: &Path) -> Result<()> {
+    let status = Command::new("git")
+        .arg("pull")
+        .current_dir(checkout)
+        .status()
+        .context("Failed to execute git pull")?;
+
+    if !status.success() {
+        return Err(SpawnError::CommandFailed("git pull"));
+    }
+
+    let status = Command::new("cargo")
+        .arg("install")
+        .arg("--path")
+        .arg(".")
+        .current_dir(checkout)
+        .status()
+        .context("Failed to execute cargo install")?;
+
+    if !status.success() {
+        return Err(SpawnError::CommandFailed("cargo install"));
+    }
+
+    report::success("Spawn has been updated successfully!");
+    Ok(())
+}
+
+fn update_from_release(allow_unverified: bool) -> Result<()> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(10))
+        .user_agent(concat!("spawn/", env!("CARGO_PKG_VERSION")))
+        .build();
+
+    let body = agent.get(RELEASES_URL).call()
+        .map_err(|e| SpawnError::ReleaseFetch(e.to_string()))?
+        .into_string()
+        .context("Failed to read release information")?;
+    let release: Release = serde_json::from_str(&body)
+        .map_err(|e| SpawnError::ReleaseFetch(format!("unreadable release information: {}", e)))?;
+
+    let latest = release.tag_name.trim_start_matches('v');
+    if Version::parse(latest).is_ok_and(|v| !is_newer(&v)) {
+        report::success(format!("Spawn is already up to date (v{})", env!("CARGO_PKG_VERSION")));
+        return Ok(());
+    }
+
+    let asset = pick_release_asset(&release.assets).ok_or_else(|| SpawnError::NoReleaseBinary { arch: std::env::consts::ARCH, tag: release.tag_name.clone() })?;
+    report::step(format!("Downloading {} ({})...", asset.name.bold(), release.tag_name));
+    let data = download(&agent, &asset.browser_download_url)?;
+
+    let expected = published_checksum(&agent, &release.assets, &asset.name);
+    verify_release_asset(&data, &asset.name, expected.as_deref(), allow_unverified)?;
+
+    let binary = if asset.name.ends_with(".tar.gz") || asset.name.ends_with(".tgz") {
+        binary_from_tarball(&data)?
+    } else {
+        data
+    };
+
+    replace_current_exe(&binary)?;
+    report::success(format!("Spawn has been updated to v{}!", latest));
+    Ok(())
+}
+
+/// Checks a downloaded asset against its published SHA-256. An asset with no checksum can't be
+/// told apart from a tampered one, so it's refused unless the user opted in.
+fn verify_release_asset(data: &[u8], asset_name: &str, expected: Option<&str>, allow_unverified: bool) -> Result<()> {
+    let Some(expected) = expected else {
+        if !allow_unverified {
+            return Err(SpawnError::UnverifiedRelease(asset_name.to_string()));
+        }
+        report::warning(format!("Release has no published checksum for {}; installing it unverified (--allow-unverified)", asset_name));
+        return Ok(());
+    };
+
+    let actual = format!("{:x}", Sha256::digest(data));
+    if actual != expected {
+        return Err(SpawnError::ChecksumMismatch { file: asset_name.into(), expected: expected.to_string(), actual });
+    }
+    report::success("Checksum verified (SHA-256)");
+    Ok(())
+}
+
+/// Picks the release asset built for this machine (`*linux*<arch>*`), ignoring checksum and signature files.
+fn pick_release_asset(assets: &[ReleaseAsset]) -> Option<&ReleaseAsset> {
+    let arch = std::env::consts::ARCH;
+    assets.iter()
+        .filter(|a| {
+            let name = a.name.to_lowercase();
+            ![".sha256", ".sig", ".asc", ".txt"].iter().any(|ext| name.ends_with(ext))
+        })
+        .find(|a| {
+            let name = a.name.to_lowercase();
+            name.contains("linux") && name.contains(arch)
+        })
+}
+
+/// Looks up the expected SHA-256 for an asset from a `<asset>.sha256` or `SHA256SUMS` release file.
+fn published_checksum(agent: &ureq::Agent, assets: &[ReleaseAsset], asset_name: &str) -> Option<String> {
+    let sidecar = format!("{}.sha256", asset_name);
+    let sums = assets.iter()
+        .find(|a| a.name == sidecar)
+        .or_else(|| assets.iter().find(|a| a.name.eq_ignore_ascii_case("SHA256SUMS") || a.name.eq_ignore_ascii_case("sha256sums.txt")))?;
+
+    let content = agent.get(&sums.browser_download_url).call().ok()?.into_string().ok()?;
+    content.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let hash = fields.next()?;
+        match fields.next() {
+            Some(name) if name.trim_start_matches('*') != asset_name => None,
+            _ => Some(hash.to_lowercase()),
+        }
+    })
+}
+
+fn download(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>> {
+    let response = agent.get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, _) => SpawnError::DownloadStatus(code),
+        e => SpawnError::Download(e.to_string()),
+    })?;
+    let total = response.header("Content-Length").and_then(|l| l.parse().ok());
+
+    let task = Task::start("Downloading...", total);
+    let mut data = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let result = task.wrap_read(response.into_reader()).read_to_end(&mut data);
+    drop(task);
+    result.context("Failed to download release asset")?;
+    Ok(data)
+}
+
+/// Extracts the `spawn` binary from a gzipped release tarball.
+fn binary_from_tarball(data: &[u8]) -> Result<Vec<u8>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(data));
+    for entry in archive.entries().context("Failed to read release archive")? {
+        let mut entry = entry.context("Failed to read release archive")?;
+        let is_binary = entry.path().ok().and_then(|p| p.file_name().map(|n| n == "spawn")).unwrap_or(false);
+        if is_binary && entry.header().entry_type().is_file() {
+            let mut binary = Vec::new();
+            entry.read_to_end(&mut binary).context("Failed to read binary from release archive")?;
+            return Ok(binary);
+        }
+    }
+    Err(SpawnError::NoBinaryInRelease)
+}
+
+/// Writes the new binary next to the running executable, then renames it over the original.
+fn replace_current_exe(binary: &[u8]) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let exe = std::env::current_exe().context("Failed to locate the running executable")?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    let staged = exe.with_file_name(".spawn-update");
+
+    let result = (|| -> std::io::Result<()> {
+        fs::write(&staged, binary)?;
+        if !is_elf_binary(&staged) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "downloaded file is not a Linux executable"));
+        }
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+        fs::rename(&staged, &exe)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&staged);
+        return Err(SpawnError::ReplaceFailed { exe, reason: e.to_string() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unverified_release_assets_need_opting_in() {
+        let data = b"binary";
+        let sha = format!("{:x}", Sha256::digest(data));
+        assert!(verify_release_asset(data, "spawn-linux-x86_64", Some(&sha), false).is_ok());
+        assert!(verify_release_asset(data, "spawn-linux-x86_64", Some(&"0".repeat(64)), true).is_err());
+        let refused = verify_release_asset(data, "spawn-linux-x86_64", None, false).unwrap_err();
+        assert!(refused.to_string().contains("--allow-unverified"), "{}", refused);
+        assert!(verify_release_asset(data, "spawn-linux-x86_64", None, true).is_ok());
+    }
+
+    #[test]
+    fn only_strictly_newer_versions_count() {
+        let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        assert!(!is_newer(&current));
+        assert!(is_newer(&Version::new(current.major, current.minor, current.patch + 1)));
+        assert!(is_newer(&Version::new(current.major + 1, 0, 0)));
+        let older = Version::parse("0.0.1").unwrap();
+        assert!(!is_newer(&older));
+    }
+
+    #[test]
+    fn reads_only_the_package_version() {
+        let manifest = "[dependencies]\nserde = { version = \"9.9.9\" }\n\n[package]\nname = \"spawn\"\nversion = \"1.2.3\"\n";
+        assert_eq!(package_version(manifest), Some(Version::new(1, 2, 3)));
+        assert_eq!(package_version("[dependencies]\nversion = \"9.9.9\"\n"), None);
+        assert_eq!(package_version("[package]\nversion = \"not a version\"\n"), None);
+    }
+
+}
@@ -1,7 +1,17 @@
-use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use colored::*;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::config::DesktopTarget;
+use crate::error::{IoContext, Result, SpawnError};
+use crate::platform::hicolor_dir;
+use crate::report;
+
+pub use crate::platform::{SYSTEM_APPLICATIONS_DIR, applications_dir, desktop_dir};
 
 pub fn format_game_name(name: &str) -> String {
     name.replace('_', " ")
@@ -17,11 +27,31 @@ pub fn format_game_name(name: &str) -> String {
         .join(" ")
 }
 
+/// Writes a file by renaming a fully written temporary file over it, so an interrupted write
+/// or a second Spawn running at the same time never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
 pub fn set_executable_permission(executable: &Path) -> Result<()> {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let metadata = fs::metadata(executable)?;
+        let metadata = fs::metadata(executable).with_context(|| format!("Failed to read {:?}", executable))?;
         let mut perms = metadata.permissions();
         let mode = perms.mode();
         perms.set_mode(mode | 0o111);
@@ -30,86 +60,641 @@ pub fn set_executable_permission(executable: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn resolve_fuzzy_path(input: &Path, search_dir: &Path) -> Result<PathBuf> {
+/// Entries in `search_dir` that can be matched, with their lowercased names, read as they're
+/// needed. Skips partial downloads and checksum files. Only the top level is read, never subfolders, so a deep
+/// tree under `search_dir` costs no more to search than its top-level entries.
+fn search_candidates(search_dir: &Path) -> impl Iterator<Item = (PathBuf, String)> {
+    fs::read_dir(search_dir).into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .map(|path| {
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            (path, file_name)
+        })
+        .filter(|(_, file_name)| ![".aria2", ".part", ".tmp", ".sha256"].iter().any(|ext| file_name.ends_with(ext)))
+}
+
+/// Whether a name contains shell-style wildcards (`*` or `?`), making it a pattern for [`matches_glob`].
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Case-insensitive wildcard match: `*` matches any run of characters and `?` exactly one, both
+/// within a single folder of a path; `**` also crosses folders. Used for game names, discovery
+/// patterns and `--preserve` paths alike.
+pub fn matches_glob(name: &str, pattern: &str) -> bool {
+    let name = name.to_lowercase().chars().collect::<Vec<_>>();
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    glob_match(&pattern, &name)
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => text.first().is_some_and(|&c| c != '/') && glob_match(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Entries in `search_dir` whose name contains `query`, best match first. An entry named exactly
+/// `query` ends the scan early and is the only result.
+pub fn find_matches(query: &str, search_dir: &Path) -> Vec<PathBuf> {
+    let query = query.to_lowercase();
+
+    let mut matches = Vec::new();
+    for (path, file_name) in search_candidates(search_dir) {
+        if file_name == query {
+            return vec![path];
+        }
+        if let Some(rank) = match_rank(&file_name, &query) {
+            matches.push((rank, path));
+        }
+    }
+    matches.sort();
+    matches.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Entries whose name contains the letters of `query` in order (so `hds` finds `Hades`),
+/// with their match score, best first. Only used when [`find_matches`] finds nothing.
+pub fn find_fuzzy_matches(query: &str, search_dir: &Path) -> Vec<(PathBuf, i64)> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut matches = search_candidates(search_dir)
+        .filter_map(|(path, file_name)| matcher.fuzzy_match(&file_name, query).map(|score| (path, score)))
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a_path, a), (b_path, b)| b.cmp(a).then_with(|| a_path.cmp(b_path)));
+    matches
+}
+
+/// Sort key for a lowercased file name matching `query`: an exact name (ignoring extensions)
+/// beats a prefix, which beats a match mid-name; shorter names win ties.
+fn match_rank(file_name: &str, query: &str) -> Option<(u8, usize)> {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let tier = if file_name == query || stem == query {
+        0
+    } else if file_name.starts_with(query) {
+        1
+    } else if file_name.contains(query) {
+        2
+    } else {
+        return None;
+    };
+    Some((tier, file_name.len()))
+}
+
+/// Formats how long ago a timestamp was, e.g. "3 hours ago".
+pub fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now().duration_since(time).map(|d| d.as_secs()).unwrap_or(0);
+    let (value, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{} ago", value, unit, if value == 1 { "" } else { "s" })
+}
+
+/// Resolves a path or partial name against `search_dir`. With several matches the user picks one,
+/// unless `interactive` is false, in which case the best match is used.
+pub fn resolve_fuzzy_path(input: &Path, search_dir: &Path, interactive: bool) -> Result<PathBuf> {
     if input.exists() {
         return Ok(input.to_path_buf());
     }
 
-    let input_str = input.to_string_lossy().to_lowercase();
-    
-    let mut matches = Vec::new();
-    if let Ok(entries) = fs::read_dir(search_dir) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let path = entry.path();
-            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
-            
-            if file_name.ends_with(".aria2") || file_name.ends_with(".part") || file_name.ends_with(".tmp") {
-                continue;
-            }
+    // An exact name needs no scan at all
+    let exact = search_dir.join(input);
+    if input.is_relative() && exact.exists() {
+        report::success(format!("Found matching path in {:?}: {:?}", search_dir.file_name().unwrap_or_default(), exact.file_name().unwrap_or_default()));
+        return Ok(exact);
+    }
 
-            if file_name.contains(&input_str) {
-                matches.push(path);
-            }
-        }
+    let query = input.to_string_lossy();
+    let scan = report::Task::start("Searching for matches...", None);
+    let mut matches = find_matches(&query, search_dir).into_iter().map(|m| (m, None)).collect::<Vec<_>>();
+    let fuzzy = matches.is_empty();
+    if fuzzy {
+        matches = find_fuzzy_matches(&query, search_dir).into_iter().map(|(m, score)| (m, Some(score))).collect();
+    }
+    drop(scan);
+    if fuzzy && !matches.is_empty() {
+        report::step(format!("No names contain \"{}\", trying fuzzy matching", input.display()));
     }
 
     match matches.len() {
-        0 => Err(anyhow!("{} No file or directory found matching \"{}\" in {:?}", "✖".red(), input.display(), search_dir)),
+        0 => Err(SpawnError::NoMatch { query: input.display().to_string(), dir: search_dir.to_path_buf() }),
         1 => {
-            let matched = matches.remove(0);
-            println!("{} Found matching path in {:?}: {:?}", "✔".green(), search_dir.file_name().unwrap_or_default(), matched.file_name().unwrap_or_default());
+            let (matched, _) = matches.remove(0);
+            report::success(format!("Found matching path in {:?}: {:?}", search_dir.file_name().unwrap_or_default(), matched.file_name().unwrap_or_default()));
+            Ok(matched)
+        }
+        _ if !interactive => {
+            let (matched, _) = matches.remove(0);
+            report::success(format!("Picked best of {} matches in {:?}: {:?}", matches.len() + 1, search_dir.file_name().unwrap_or_default(), matched.file_name().unwrap_or_default()));
             Ok(matched)
         }
         _ => {
-            println!("{} Multiple matches found for \"{}\" in {:?}:", "▶".cyan(), input.display(), search_dir);
-            for (i, m) in matches.iter().enumerate() {
-                println!("  {}. {:?}", i + 1, m.file_name().unwrap_or_default());
+            report::step(format!("Multiple matches found for \"{}\" in {:?}:", input.display(), search_dir));
+            let items = matches.iter().map(|(m, score)| match score {
+                Some(score) => format!("{:?} (score {})", m.file_name().unwrap_or_default(), score),
+                None => format!("{:?}", m.file_name().unwrap_or_default()),
+            }).collect::<Vec<_>>();
+            let index = report::choose("Please enter the number of the correct file (Enter for the first, q to cancel):", &items)?.unwrap_or(0);
+            // The answer comes from whichever reporter is installed, so it isn't trusted to be in range
+            if index >= matches.len() {
+                return Err(SpawnError::InvalidSelection((index + 1).to_string()));
             }
-            println!("{} Please enter the number of the correct file (or press Enter to cancel):", "▶".cyan());
 
-            let mut choice = String::new();
-            std::io::stdin().read_line(&mut choice).context("Failed to read input")?;
-            let choice = choice.trim();
+            let (matched, _) = matches.remove(index);
+            report::success(format!("Selected: {:?}", matched.file_name().unwrap_or_default()));
+            Ok(matched)
+        }
+    }
+}
+
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).map(|dir| dir.join(program)).find(|p| p.is_file())
+}
+
+/// How a game is started: environment assignments, an optional wrapper such as `wine`,
+/// then the executable followed by its arguments.
+#[derive(Debug, Default, Clone)]
+pub struct LaunchCommand {
+    pub env: Vec<String>,
+    pub wrapper: Vec<String>,
+    pub args: Vec<String>,
+}
+
+impl LaunchCommand {
+    pub fn new(launch_options: Option<&str>, wrapper: Vec<String>) -> Result<Self> {
+        let (user_env, args) = match launch_options {
+            Some(options) => split_launch_options(options)?,
+            None => (Vec::new(), Vec::new()),
+        };
+
+        // The wrapper may carry its own environment (e.g. Proton's compat data path)
+        let wrapper_env_count = wrapper.iter().take_while(|w| is_env_assignment(w)).count();
+        let mut env = wrapper;
+        let wrapper = env.split_off(wrapper_env_count);
+        env.extend(user_env);
+
+        Ok(Self { env, wrapper, args })
+    }
+
+    /// Runs the wrapper and game inside an outer command such as a sandbox. The environment
+    /// is still set first, so it reaches the game through the outer command.
+    pub fn wrap_with(&mut self, outer: Vec<String>) {
+        self.wrapper.splice(0..0, outer);
+    }
+
+    /// Renders the value of a desktop entry `Exec=` key.
+    pub fn desktop_exec(&self, executable: &Path) -> String {
+        let mut words = Vec::new();
+        if !self.env.is_empty() {
+            words.push("env".to_string());
+            words.extend(self.env.iter().map(|e| quote_exec_arg(e)));
+        }
+        words.extend(self.wrapper.iter().map(|w| quote_exec_arg(w)));
+        words.push(quote_exec_arg(&executable.to_string_lossy()));
+        words.extend(self.args.iter().map(|a| quote_exec_arg(a)));
+        words.join(" ")
+    }
+
+    /// The program the shortcut runs first, for `TryExec=`: the wrapper (Wine, a sandbox...)
+    /// when there is one, since a Windows `.exe` isn't itself executable.
+    pub fn try_exec<'a>(&'a self, executable: &'a Path) -> std::borrow::Cow<'a, str> {
+        match self.wrapper.first() {
+            Some(program) => program.into(),
+            None => executable.to_string_lossy(),
+        }
+    }
+
+    /// Builds a process that starts the game from its folder, as the shortcut would.
+    pub fn command(&self, executable: &Path, working_dir: &Path) -> std::process::Command {
+        let mut words = self.wrapper.iter().map(std::ffi::OsStr::new).collect::<Vec<_>>();
+        words.push(executable.as_os_str());
+        let mut command = std::process::Command::new(words[0]);
+        command.args(&words[1..]).args(&self.args).current_dir(working_dir);
+        for assignment in &self.env {
+            let (key, value) = assignment.split_once('=').unwrap_or((assignment, ""));
+            command.env(key, value);
+        }
+        command
+    }
 
-            if choice.is_empty() {
-                return Err(anyhow!("{} Operation cancelled by user", "✖".red()));
+    /// Renders Steam launch options. Steam substitutes the shortcut's executable for `%command%`,
+    /// which is only needed when something has to run before it.
+    pub fn steam_launch_options(&self) -> String {
+        let mut words = Vec::new();
+        if !self.env.is_empty() || !self.wrapper.is_empty() {
+            for assignment in &self.env {
+                let (key, value) = assignment.split_once('=').unwrap_or((assignment, ""));
+                words.push(format!("{}={}", key, shell_quote(value)));
             }
+            words.extend(self.wrapper.iter().map(|w| shell_quote(w)));
+            words.push("%command%".to_string());
+        }
+        words.extend(self.args.iter().map(|a| shell_quote(a)));
+        words.join(" ")
+    }
+}
+
+/// Single-quotes a word for a POSIX shell when it contains anything beyond a conservative safe set.
+fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Splits user launch options into leading `KEY=VALUE` environment assignments and the remaining arguments.
+fn split_launch_options(options: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let words = shlex::split(options).ok_or_else(|| SpawnError::InvalidLaunchOptions(options.to_string()))?;
+
+    let env_count = words.iter().take_while(|w| is_env_assignment(w)).count();
+    let mut env = words;
+    let args = env.split_off(env_count);
+    Ok((env, args))
+}
 
-            let index: usize = choice.parse::<usize>().map_err(|_| anyhow!("{} Invalid selection", "✖".red()))?;
-            if index == 0 || index > matches.len() {
-                return Err(anyhow!("{} Selection out of range", "✖".red()));
+pub fn is_env_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((key, _)) => !key.is_empty() && !key.starts_with(|c: char| c.is_ascii_digit()) && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+/// Escapes a desktop entry string value, such as a path in `TryExec=`, so a newline in a game's
+/// name or folder can't start a key of its own.
+fn escape_desktop_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t").replace('\r', "\\r")
+}
+
+/// Reverses [`escape_desktop_string`] for a value read from a `.desktop` file.
+fn unescape_desktop_string(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('s') => unescaped.push(' '),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Escapes an argument for a desktop entry `Exec=` line: `%` is doubled so it isn't read as a
+/// field code, and the argument is quoted when it contains spaces or reserved characters.
+fn quote_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`'];
+    let arg = arg.replace('%', "%%");
+    if !arg.is_empty() && !arg.contains(RESERVED) {
+        return arg;
+    }
+
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        // Quoting escapes are themselves subject to the desktop file's string escaping,
+        // so every backslash is written twice
+        match c {
+            '\\' => quoted.push_str("\\\\\\\\"),
+            '"' | '`' | '$' => {
+                quoted.push_str("\\\\");
+                quoted.push(c);
             }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
 
-            let matched = matches.remove(index - 1);
-            println!("{} Selected: {:?}", "✔".green(), matched.file_name().unwrap_or_default());
-            Ok(matched)
+/// The `.desktop` file a game's shortcuts are written to, e.g. `tom-jerry.desktop` for "Tom/Jerry".
+pub fn desktop_file_name(game_name: &str) -> String {
+    format!("{}.desktop", sanitize_desktop_filename(game_name))
+}
+
+/// The folder a game is installed to, e.g. `Tom_Jerry` for "Tom/Jerry": spaces and slashes
+/// become underscores, so the name is always a single folder.
+pub fn install_dir_name(game_name: &str) -> String {
+    game_name.replace([' ', '/'], "_")
+}
+
+/// Checks a name given on the command line: it must have something besides spaces in it and
+/// no control characters, which would end up in file names and `.desktop` values.
+pub fn validate_game_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(SpawnError::InvalidName { name: name.to_string(), reason: "it is empty" });
+    }
+    if name.chars().any(char::is_control) {
+        return Err(SpawnError::InvalidName { name: name.to_string(), reason: "it contains control characters such as newlines or tabs" });
+    }
+    Ok(())
+}
+
+/// Turns a game name into a safe file name stem: lowercase letters and digits (accented ones
+/// included), `_`, and single dashes in place of spaces, slashes, colons and other symbols.
+/// Control characters are dropped. A name with nothing usable in it becomes `game`.
+pub fn sanitize_desktop_filename(game_name: &str) -> String {
+    let mut sanitized = String::new();
+    for c in game_name.to_lowercase().chars().filter(|c| !c.is_control()) {
+        if c.is_alphanumeric() || c == '_' {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    let sanitized = sanitized.trim_end_matches('-');
+    if sanitized.is_empty() { "game".to_string() } else { sanitized.to_string() }
+}
+
+/// Sizes an icon is scaled to inside the hicolor theme.
+const THEME_ICON_SIZES: &[u32] = &[256, 128, 64, 48];
+
+/// Themed icon name for a game, e.g. `spawn-my-game`.
+pub fn theme_icon_name(game_name: &str) -> String {
+    format!("spawn-{}", desktop_file_name(game_name).trim_end_matches(".desktop"))
+}
+
+/// Copies a PNG/JPEG icon into the hicolor theme at the standard sizes it's big enough for and
+/// returns its themed name. SVG and ICO icons can't be resized here, so those keep their
+/// absolute path, as does an icon smaller than every size.
+pub fn install_theme_icon(icon: &Path, game_name: &str) -> Option<String> {
+    let extension = icon.extension()?.to_string_lossy().to_lowercase();
+    if !["png", "jpg", "jpeg"].contains(&extension.as_str()) {
+        return None;
+    }
+
+    let image = match image::open(icon) {
+        Ok(i) => i,
+        Err(e) => {
+            log::debug!("Could not load icon {:?} for resizing: {}", icon, e);
+            return None;
+        }
+    };
+
+    let icons = theme_icon_images(&image);
+    if icons.is_empty() {
+        log::debug!("Icon {:?} is smaller than every theme size, keeping its path", icon);
+        return None;
+    }
+
+    let name = theme_icon_name(game_name);
+    let hicolor = hicolor_dir()?;
+    let mut written = Vec::new();
+    for (size, resized) in icons {
+        let apps_dir = hicolor.join(format!("{0}x{0}", size)).join("apps");
+        let path = apps_dir.join(format!("{}.png", name));
+        let saved = fs::create_dir_all(&apps_dir).is_ok() && resized.save(&path).is_ok();
+        written.push(path);
+        if !saved {
+            log::debug!("Failed to write {}x{} theme icon to {:?}", size, size, apps_dir);
+            // Nothing records the sizes already written, so uninstalling would never remove them
+            for path in &written {
+                let _ = fs::remove_file(path);
+            }
+            return None;
+        }
+    }
+    Some(name)
+}
+
+/// The icon at each theme size it's big enough for, centered on a transparent square first:
+/// theme icons must be square, and a stretched or blurry upscaled icon looks worse than none.
+fn theme_icon_images(image: &image::DynamicImage) -> Vec<(u32, image::RgbaImage)> {
+    let side = image.width().max(image.height());
+    let mut square = image::RgbaImage::new(side, side);
+    let (x, y) = ((side - image.width()) / 2, (side - image.height()) / 2);
+    image::imageops::overlay(&mut square, &image.to_rgba8(), x.into(), y.into());
+
+    THEME_ICON_SIZES.iter()
+        .filter(|&&size| size <= side)
+        .map(|&size| (size, image::imageops::resize(&square, size, size, image::imageops::FilterType::Lanczos3)))
+        .collect()
+}
+
+/// Theme icon files installed under `icon_name`.
+pub fn theme_icon_files(icon_name: &str) -> Vec<PathBuf> {
+    let Some(hicolor) = hicolor_dir() else { return Vec::new() };
+    THEME_ICON_SIZES.iter()
+        .map(|size| hicolor.join(format!("{0}x{0}", size)).join("apps").join(format!("{}.png", icon_name)))
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// Main categories from the freedesktop menu spec, plus the registered game genres.
+const KNOWN_CATEGORIES: &[&str] = &[
+    "AudioVideo", "Audio", "Video", "Development", "Education", "Game", "Graphics", "Network",
+    "Office", "Science", "Settings", "System", "Utility",
+    "ActionGame", "AdventureGame", "ArcadeGame", "BoardGame", "BlocksGame", "CardGame", "Emulator",
+    "KidsGame", "LogicGame", "RolePlaying", "Shooter", "Simulation", "SportsGame", "StrategyGame",
+];
+
+/// Builds the `Categories=` value, defaulting to `Game;`. Unknown names are kept but warned about.
+pub fn desktop_categories(categories: &[String]) -> String {
+    if categories.is_empty() {
+        return "Game;".to_string();
+    }
+
+    let mut value = String::new();
+    for category in categories {
+        let category = category.trim().trim_end_matches(';');
+        if category.is_empty() {
+            continue;
+        }
+        if !KNOWN_CATEGORIES.contains(&category) && !category.starts_with("X-") {
+            report::warning(format!("{:?} is not a registered desktop category; menus may ignore it", category));
         }
+        value.push_str(category);
+        value.push(';');
     }
+    value
+}
+
+/// Builds the `Keywords=` value; `;` separates keywords, so it can't appear inside one.
+fn desktop_keywords(keywords: &[String]) -> String {
+    keywords.iter()
+        .map(|k| k.trim().replace(';', ""))
+        .filter(|k| !k.is_empty())
+        .map(|k| format!("{};", k))
+        .collect()
 }
 
-pub fn generate_desktop_entry(game_dir: &Path, executable: &Path, game_name: &str, icon: Option<&Path>) -> Result<Vec<PathBuf>> {
-    let exec_path = executable.to_string_lossy();
-    let working_dir = game_dir.to_string_lossy();
+/// Everything written into a game's `.desktop` file.
+pub struct DesktopEntry<'a> {
+    pub game_dir: &'a Path,
+    /// `Path=`, the folder the game starts in; see [`default_working_dir`]
+    pub working_dir: &'a Path,
+    pub executable: &'a Path,
+    pub name: &'a str,
+    /// `Icon=` value: a themed icon name or an absolute path
+    pub icon: Option<&'a str>,
+    pub launch: &'a LaunchCommand,
+    pub categories: &'a [String],
+    /// `Comment=`, the tooltip and search text menus show; left out when empty
+    pub comment: &'a str,
+    /// Extra search terms for `Keywords=`, such as a subtitle or alternate name
+    pub keywords: &'a [String],
+    /// `StartupWMClass=`, so the taskbar can match the game's window to this shortcut
+    pub wm_class: Option<&'a str>,
+    /// Entries for the shortcut's right-click menu
+    pub actions: &'a [DesktopAction],
+}
+
+/// A right-click menu entry on a shortcut (a freedesktop Desktop Action). The command runs
+/// through `sh` from the game folder, so `./bin/game -windowed` works.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DesktopAction {
+    pub name: String,
+    pub command: String,
+}
+
+impl DesktopAction {
+    /// Opens the game folder in the file manager.
+    pub fn open_folder() -> Self {
+        DesktopAction { name: "Open Folder".to_string(), command: "xdg-open .".to_string() }
+    }
+
+    /// Renders the action's `Exec=` value.
+    fn desktop_exec(&self, game_dir: &Path) -> String {
+        let script = format!("cd {} && {}", shell_quote(&game_dir.to_string_lossy()), self.command);
+        ["sh", "-c", script.as_str()].map(quote_exec_arg).join(" ")
+    }
+}
+
+/// Group ids for the actions, made of letters, digits and dashes as the spec requires, and
+/// numbered when two names would give the same id.
+fn action_ids(actions: &[DesktopAction]) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for action in actions {
+        let base = action.name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()).collect::<Vec<_>>().join("-");
+        let base = if base.is_empty() { "action".to_string() } else { base };
+        let mut id = base.clone();
+        let mut n = 2;
+        while ids.contains(&id) {
+            id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        ids.push(id);
+    }
+    ids
+}
+
+/// The folder a game starts in unless `--working-dir` says otherwise: the executable's own
+/// folder, since games look for data next to themselves (e.g. `bin/Game` reading `bin/data`).
+/// A launcher script sits in the game root, so it gets the root, which is what scripts expect.
+pub fn default_working_dir(executable: &Path, game_dir: &Path) -> PathBuf {
+    executable.parent()
+        .filter(|dir| !dir.as_os_str().is_empty() && dir.starts_with(game_dir))
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| game_dir.to_path_buf())
+}
+
+/// Guesses the window class a game sets, which is usually its executable name.
+/// Wine reports the lowercased `.exe` name; launcher scripts and AppImages drop their suffix.
+pub fn guess_wm_class(executable: &Path) -> Option<String> {
+    let file_name = executable.file_name()?.to_str()?;
+    let lower = file_name.to_lowercase();
+    if lower.ends_with(".exe") {
+        return Some(lower);
+    }
+
+    let class = [".sh", ".appimage"].iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| &file_name[..file_name.len() - ext.len()])
+        .unwrap_or(file_name);
+    (!class.is_empty()).then(|| class.to_string())
+}
+
+/// The text of the shortcut [`generate_desktop_entry`] writes.
+pub fn desktop_entry_content(entry: &DesktopEntry) -> String {
+    let exec = entry.launch.desktop_exec(entry.executable);
+    let working_dir = escape_desktop_string(&entry.working_dir.to_string_lossy());
 
     let mut content = format!(
         "[Desktop Entry]\n\
         Type=Application\n\
+        Version=1.5\n\
         Name={}\n\
-        Exec=\"{}\"\n\
+        Exec={}\n\
+        TryExec={}\n\
         Path={}\n\
         Terminal=false\n\
-        Categories=Game;\n",
-        game_name, exec_path, working_dir
+        Categories={}\n",
+        escape_desktop_string(entry.name), exec, escape_desktop_string(&entry.launch.try_exec(entry.executable)), working_dir, desktop_categories(entry.categories)
     );
 
-    if let Some(icon_path) = icon {
-        content.push_str(&format!("Icon={}\n", icon_path.to_string_lossy()));
+    if let Some(icon) = entry.icon {
+        content.push_str(&format!("Icon={}\n", escape_desktop_string(icon)));
+    }
+
+    if !entry.comment.trim().is_empty() {
+        content.push_str(&format!("Comment={}\n", escape_desktop_string(entry.comment.trim())));
+    }
+
+    let keywords = desktop_keywords(entry.keywords);
+    if !keywords.is_empty() {
+        content.push_str(&format!("Keywords={}\n", escape_desktop_string(&keywords)));
+    }
+
+    if let Some(wm_class) = entry.wm_class {
+        content.push_str(&format!("StartupWMClass={}\n", escape_desktop_string(wm_class)));
+    }
+
+    let action_ids = action_ids(entry.actions);
+    if !action_ids.is_empty() {
+        content.push_str(&format!("Actions={};\n", action_ids.join(";")));
     }
 
+    content.push_str(&format!("{}=true\n", SPAWN_MANAGED_KEY));
+    content.push_str(&format!("X-Spawn-Install-Dir={}\n", escape_desktop_string(&entry.game_dir.to_string_lossy())));
+    content.push_str(&format!("X-Spawn-Version={}\n", env!("CARGO_PKG_VERSION")));
+
+    for (id, action) in action_ids.iter().zip(entry.actions) {
+        content.push_str(&format!("\n[Desktop Action {}]\nName={}\nExec={}\n", id, escape_desktop_string(&action.name), action.desktop_exec(entry.game_dir)));
+    }
+    content
+}
+
+/// Writes the shortcut to each target. A `system` install only gets a menu entry in
+/// `/usr/share/applications`, since Desktop folders belong to individual users.
+pub fn generate_desktop_entry(entry: &DesktopEntry, targets: &[DesktopTarget], system: bool) -> Result<Vec<PathBuf>> {
+    write_desktop_entry(entry, targets, system, applications_dir(), desktop_dir())
+}
+
+/// [`generate_desktop_entry`] with the user's menu and Desktop folders passed in, `None` where
+/// there's no home folder to find them in.
+fn write_desktop_entry(entry: &DesktopEntry, targets: &[DesktopTarget], system: bool, app_dir: Option<PathBuf>, desktop_dir: Option<PathBuf>) -> Result<Vec<PathBuf>> {
+    let content = desktop_entry_content(entry);
     let mut created_files = Vec::new();
-    let desktop_file_name = format!("{}.desktop", game_name.to_lowercase().replace(' ', "-"));
+    let desktop_file_name = desktop_file_name(entry.name);
+
+    if system {
+        let app_path = Path::new(SYSTEM_APPLICATIONS_DIR).join(&desktop_file_name);
+        fs::create_dir_all(SYSTEM_APPLICATIONS_DIR).context("Failed to create system applications directory")?;
+        fs::write(&app_path, &content).context("Failed to write .desktop file to system applications")?;
+        return Ok(vec![app_path]);
+    }
 
-    if let Some(app_dir) = dirs_next::home_dir().map(|h| h.join(".local/share/applications")) {
+    // A missing Desktop folder is fine, but the menu is where the game is expected to show up
+    if targets.contains(&DesktopTarget::Menu) {
+        let app_dir = app_dir.ok_or(SpawnError::NoHomeDir)?;
         if !app_dir.exists() {
             fs::create_dir_all(&app_dir).context("Failed to create applications directory")?;
         }
@@ -118,13 +703,313 @@ pub fn generate_desktop_entry(game_dir: &Path, executable: &Path, game_name: &st
         created_files.push(app_path);
     }
 
-    if let Some(desktop_dir) = dirs_next::home_dir().map(|h| h.join("Desktop")) {
-        if desktop_dir.exists() {
-            let desktop_path = desktop_dir.join(&desktop_file_name);
-            fs::write(&desktop_path, &content).context("Failed to write .desktop file to Desktop")?;
-            created_files.push(desktop_path);
-        }
+    if targets.contains(&DesktopTarget::Desktop) && let Some(desktop_dir) = desktop_dir && desktop_dir.exists() {
+        let desktop_path = desktop_dir.join(&desktop_file_name);
+        fs::write(&desktop_path, &content).context("Failed to write .desktop file to Desktop")?;
+        created_files.push(desktop_path);
     }
 
     Ok(created_files)
 }
+
+/// Marks shortcuts Spawn wrote, so they can be told apart from ones the user made.
+pub const SPAWN_MANAGED_KEY: &str = "X-Spawn-Managed";
+
+/// Whether a shortcut's fields carry Spawn's marker. Shortcuts from before the marker existed don't.
+pub fn is_spawn_managed(fields: &HashMap<String, String>) -> bool {
+    fields.get(SPAWN_MANAGED_KEY).is_some_and(|v| v == "true")
+}
+
+/// Reads the key/value pairs of the `[Desktop Entry]` group from a `.desktop` file, with string
+/// escapes such as `\n` undone.
+pub fn read_desktop_entry(path: &Path) -> Option<HashMap<String, String>> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut fields = HashMap::new();
+    let mut in_entry = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if in_entry && let Some((key, value)) = line.split_once('=') {
+            fields.insert(key.trim().to_string(), unescape_desktop_string(value.trim()));
+        }
+    }
+
+    Some(fields)
+}
+
+/// Replaces the `Exec=` line of a desktop entry, keeping everything else.
+pub fn set_desktop_exec(content: &str, exec: &str) -> String {
+    let mut rewritten = String::new();
+    let mut in_entry = false;
+    for line in content.lines() {
+        if line.starts_with('[') {
+            in_entry = line.trim() == "[Desktop Entry]";
+        }
+        // Right-click actions have Exec lines of their own
+        if in_entry && line.starts_with("Exec=") {
+            rewritten.push_str(&format!("Exec={}", exec));
+        } else {
+            rewritten.push_str(line);
+        }
+        rewritten.push('\n');
+    }
+    rewritten
+}
+
+/// Rewrites a `.desktop` file's `Name=` and moves any paths under `old_dir` to `new_dir`,
+/// leaving everything else (launch options, categories) untouched. Values are read back
+/// through the same escaping they were written with, so quoted paths move too.
+pub fn rewrite_desktop_entry(content: &str, name: &str, relocate: Option<(&Path, &Path)>) -> String {
+    let mut rewritten = String::new();
+    let mut in_entry = false;
+    for line in content.lines() {
+        if line.starts_with('[') {
+            in_entry = line.trim() == "[Desktop Entry]";
+        }
+        let (key, value) = line.split_once('=').unwrap_or((line, ""));
+        let moved = match relocate {
+            _ if line.starts_with('#') || !line.contains('=') => None,
+            Some((old_dir, new_dir)) if key == "Exec" => relocate_exec(value, old_dir, new_dir),
+            Some((old_dir, new_dir)) => relocate_path(&unescape_desktop_string(value), old_dir, new_dir).map(|path| escape_desktop_string(&path)),
+            None => None,
+        };
+        if in_entry && key == "Name" {
+            rewritten.push_str(&format!("Name={}", escape_desktop_string(name)));
+        } else if let Some(value) = moved {
+            rewritten.push_str(&format!("{}={}", key, value));
+        } else {
+            rewritten.push_str(line);
+        }
+        rewritten.push('\n');
+    }
+    rewritten
+}
+
+/// `path` moved from under `old_dir` to `new_dir`, or `None` when it isn't under `old_dir`.
+/// Whole folder names are compared, so `/games/Foo` never matches `/games/Foobar`.
+fn relocate_path(path: &str, old_dir: &Path, new_dir: &Path) -> Option<String> {
+    let rest = Path::new(path).strip_prefix(old_dir).ok()?;
+    let moved = if rest.as_os_str().is_empty() { new_dir.to_path_buf() } else { new_dir.join(rest) };
+    Some(moved.to_string_lossy().into_owned())
+}
+
+/// An `Exec=` value with the paths under `old_dir` moved, re-quoted the way
+/// [`LaunchCommand::desktop_exec`] writes them, or `None` when nothing in it moved. This covers
+/// the program, `KEY=path` environment assignments and the `cd` of a right-click action.
+fn relocate_exec(value: &str, old_dir: &Path, new_dir: &Path) -> Option<String> {
+    let is_field_code = |word: &str| word.len() == 2 && word.starts_with('%') && word != "%%";
+    let words = shlex::split(&unescape_desktop_string(value))?;
+    let mut changed = false;
+    let words = words.into_iter().map(|word| {
+        if is_field_code(&word) {
+            return word;
+        }
+        let word = word.replace("%%", "%");
+        let moved = if let Some(path) = relocate_path(&word, old_dir, new_dir) {
+            Some(path)
+        } else if is_env_assignment(&word) && let Some((key, path)) = word.split_once('=') {
+            relocate_path(path, old_dir, new_dir).map(|path| format!("{}={}", key, path))
+        } else if let Some((dir, command)) = word.strip_prefix("cd ").and_then(|rest| rest.split_once(" && "))
+            && let Some([dir]) = shlex::split(dir).as_deref()
+        {
+            relocate_path(dir, old_dir, new_dir).map(|dir| format!("cd {} && {}", shell_quote(&dir), command))
+        } else {
+            None
+        };
+        changed |= moved.is_some();
+        quote_exec_arg(&moved.unwrap_or(word))
+    }).collect::<Vec<_>>();
+    changed.then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desktop_entry<'a>(name: &'a str, executable: &'a Path, launch: &'a LaunchCommand) -> DesktopEntry<'a> {
+        let game_dir = executable.parent().unwrap();
+        DesktopEntry {
+            game_dir,
+            working_dir: game_dir,
+            executable,
+            name,
+            icon: None,
+            launch,
+            categories: &[],
+            comment: "",
+            keywords: &[],
+            wm_class: None,
+            actions: &[],
+        }
+    }
+
+    /// Reads an `Exec=` value back into arguments the way launchers do: string escapes first,
+    /// then quoting, then `%%`.
+    fn exec_words(content: &str) -> Vec<String> {
+        let exec = content.lines().find_map(|line| line.strip_prefix("Exec=")).unwrap();
+        shlex::split(&unescape_desktop_string(exec)).unwrap().into_iter().map(|word| word.replace("%%", "%")).collect()
+    }
+
+    #[test]
+    fn exec_round_trips_awkward_paths() {
+        for path in ["/home/me/Games/My Game (2024)/run.sh", "/games/100% Orange Juice/game", r#"/games/a\b "quoted" $HOME `x`/run.sh"#, "/games/plain/run.sh"] {
+            let launch = LaunchCommand { env: vec!["DXVK_HUD=fps 50%".to_string()], wrapper: vec![], args: vec!["--level".to_string(), "it's (1)".to_string()] };
+            let content = desktop_entry_content(&desktop_entry("Game", Path::new(path), &launch));
+            assert_eq!(exec_words(&content), ["env", "DXVK_HUD=fps 50%", path, "--level", "it's (1)"], "{}", content);
+        }
+    }
+
+    #[test]
+    fn string_values_are_escaped() {
+        let launch = LaunchCommand::default();
+        let executable = Path::new("/games/Evil\nGame/run.sh");
+        let mut entry = desktop_entry("Evil\nExec=rm -rf ~", executable, &launch);
+        entry.comment = "line one\nline two";
+        entry.wm_class = Some("class\tname");
+        let content = desktop_entry_content(&entry);
+
+        assert_eq!(content.lines().filter(|line| line.starts_with("Exec=")).count(), 1, "{}", content);
+        assert_eq!(exec_words(&content), ["/games/Evil\nGame/run.sh"]);
+        assert!(content.contains("Name=Evil\\nExec=rm -rf ~\n"), "{}", content);
+        assert!(content.contains("Comment=line one\\nline two\n"), "{}", content);
+        assert!(content.contains("StartupWMClass=class\\tname\n"), "{}", content);
+        assert!(content.contains("Path=/games/Evil\\nGame\n"), "{}", content);
+        assert_eq!(unescape_desktop_string(r"C:\\Games\nNew"), "C:\\Games\nNew");
+    }
+
+    #[test]
+    fn theme_icons_are_square_and_never_upscaled() {
+        let wide = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(300, 100, image::Rgba([255, 0, 0, 255])));
+        let icons = theme_icon_images(&wide);
+        assert_eq!(icons.iter().map(|(size, _)| *size).collect::<Vec<_>>(), [256, 128, 64, 48]);
+        for (size, icon) in &icons {
+            assert_eq!(icon.dimensions(), (*size, *size));
+        }
+        // Padded above and below rather than stretched
+        let (_, icon) = &icons[0];
+        assert_eq!(icon.get_pixel(128, 0)[3], 0);
+        assert_eq!(icon.get_pixel(128, 128), &image::Rgba([255, 0, 0, 255]));
+
+        let small = image::DynamicImage::ImageRgba8(image::RgbaImage::new(50, 100));
+        assert_eq!(theme_icon_images(&small).iter().map(|(size, _)| *size).collect::<Vec<_>>(), [64, 48]);
+        assert!(theme_icon_images(&image::DynamicImage::ImageRgba8(image::RgbaImage::new(32, 32))).is_empty());
+    }
+
+    #[test]
+    fn globs_match_names_and_paths() {
+        assert!(matches_glob("Celeste", "cel*"));
+        assert!(matches_glob("Game_Data", "*_data"));
+        assert!(matches_glob("start.sh", "start.??"));
+        assert!(matches_glob("a*b", "a*b"));
+        assert!(!matches_glob("start.x86_64", "start.??"));
+        assert!(!matches_glob("Celeste", "*hades*"));
+        assert!(!matches_glob("", "?"));
+        // `*` stays within a folder, `**` crosses them
+        assert!(matches_glob("config/settings.ini", "*/settings.ini"));
+        assert!(!matches_glob("profiles/me/settings.ini", "*/settings.ini"));
+        assert!(matches_glob("profiles/me/settings.ini", "**/settings.ini"));
+        assert!(!matches_glob("a/b", "a?b"));
+        assert!(is_glob("*.zip") && is_glob("game?") && !is_glob("game"));
+    }
+
+    #[test]
+    fn desktop_file_names_are_safe() {
+        assert_eq!(desktop_file_name("Tom/Jerry"), "tom-jerry.desktop");
+        assert_eq!(desktop_file_name("Tom/Jerry: Redux"), "tom-jerry-redux.desktop");
+        assert_eq!(desktop_file_name("Café"), "café.desktop");
+        assert_eq!(desktop_file_name("!!! ??? ***"), "game.desktop");
+        assert_eq!(desktop_file_name("Evil\nGame\t2"), "evilgame2.desktop");
+        assert_eq!(theme_icon_name("Tom/Jerry"), "spawn-tom-jerry");
+    }
+
+    #[test]
+    fn install_folders_are_a_single_path_component() {
+        assert_eq!(install_dir_name("Tom/Jerry"), "Tom_Jerry");
+        assert_eq!(install_dir_name("Dead Cells"), "Dead_Cells");
+        assert_eq!(Path::new("/games").join(install_dir_name("A/B/C")).parent(), Some(Path::new("/games")));
+    }
+
+    #[test]
+    fn names_with_control_characters_are_rejected() {
+        for name in ["Tom/Jerry", "Café", "!!!"] {
+            assert!(validate_game_name(name).is_ok(), "{:?}", name);
+        }
+        for name in ["", "   ", "Evil\nExec=rm -rf ~", "Tab\tName", "Bell\u{7}"] {
+            assert!(matches!(validate_game_name(name), Err(SpawnError::InvalidName { .. })), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn shortcuts_need_a_home_folder_for_the_menu() {
+        let launch = LaunchCommand::default();
+        let entry = desktop_entry("Celeste", Path::new("/games/Celeste/Celeste"), &launch);
+        let error = write_desktop_entry(&entry, &[DesktopTarget::Menu, DesktopTarget::Desktop], false, None, None).unwrap_err();
+        assert!(matches!(error, SpawnError::NoHomeDir), "{}", error);
+        // Without a Desktop folder there's just nothing to write
+        assert!(write_desktop_entry(&entry, &[DesktopTarget::Desktop], false, None, None).unwrap().is_empty());
+
+        let dir = crate::test_support::TempDir::new();
+        let (app_dir, desktop_dir) = (dir.path().join("applications"), dir.path().join("Desktop"));
+        std::fs::create_dir(&desktop_dir).unwrap();
+        let written = write_desktop_entry(&entry, &[DesktopTarget::Menu, DesktopTarget::Desktop], false, Some(app_dir.clone()), Some(desktop_dir.clone())).unwrap();
+        assert_eq!(written, [app_dir.join("celeste.desktop"), desktop_dir.join("celeste.desktop")]);
+        assert_eq!(read_desktop_entry(&written[0]).unwrap()["Name"], "Celeste");
+    }
+
+    #[test]
+    fn moving_a_shortcut_rewrites_quoted_paths() {
+        let (old_dir, new_dir) = (Path::new("/games/My Game 100%"), Path::new("/games/Renamed \"Game\""));
+        let shortcut = |dir: &Path, name: &str| {
+            let launch = LaunchCommand {
+                env: vec![format!("WINEPREFIX={}/pfx", dir.display())],
+                wrapper: vec![],
+                // Shares the old folder's name as a prefix, but isn't inside it
+                args: vec!["--mods".to_string(), "/games/My Game 100%2/mods".to_string()],
+            };
+            let executable = dir.join("bin/run game.sh");
+            let icon = dir.join("icon.png").to_string_lossy().into_owned();
+            let actions = [DesktopAction::open_folder()];
+            desktop_entry_content(&DesktopEntry { game_dir: dir, icon: Some(&icon), actions: &actions, ..desktop_entry(name, &executable, &launch) })
+        };
+
+        let rewritten = rewrite_desktop_entry(&shortcut(old_dir, "My Game"), "Renamed", Some((old_dir, new_dir)));
+        assert_eq!(rewritten, shortcut(new_dir, "Renamed"));
+        assert_eq!(exec_words(&rewritten)[4], "/games/My Game 100%2/mods");
+        assert_eq!(rewrite_desktop_entry(&shortcut(old_dir, "My Game"), "My Game", None), shortcut(old_dir, "My Game"));
+    }
+
+    #[test]
+    fn search_matches_are_ranked_by_relevance() {
+        let dir = crate::test_support::TempDir::new();
+        for name in ["Super Celeste.zip", "Celeste Extended Edition.zip", "celeste-dlc.zip", "Celeste.tar.gz", "Hades.zip"] {
+            dir.write(name, "");
+        }
+        let names = find_matches("Celeste", dir.path()).iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect::<Vec<_>>();
+        assert_eq!(names, ["Celeste.tar.gz", "celeste-dlc.zip", "Celeste Extended Edition.zip", "Super Celeste.zip"]);
+    }
+
+    #[test]
+    fn working_dir_is_the_executables_folder_inside_the_game() {
+        let game_dir = Path::new("/games/Game");
+        assert_eq!(default_working_dir(&game_dir.join("bin/x86_64/game"), game_dir), game_dir.join("bin/x86_64"));
+        assert_eq!(default_working_dir(&game_dir.join("start.sh"), game_dir), game_dir);
+        // A linked AppImage lives outside the game folder, which stays the starting point
+        assert_eq!(default_working_dir(Path::new("/home/me/Downloads/Game.AppImage"), game_dir), game_dir);
+        assert_eq!(default_working_dir(Path::new("game"), game_dir), game_dir);
+    }
+
+    #[test]
+    fn try_exec_points_at_what_runs_first() {
+        let executable = Path::new("/games/My Game\\1/run.sh");
+        let content = desktop_entry_content(&desktop_entry("Game", executable, &LaunchCommand::default()));
+        assert!(content.contains("TryExec=/games/My Game\\\\1/run.sh\n"), "{}", content);
+
+        let wine = LaunchCommand { wrapper: vec!["wine".to_string()], ..LaunchCommand::default() };
+        let content = desktop_entry_content(&desktop_entry("Game", Path::new("/games/Game/Game.exe"), &wine));
+        assert!(content.contains("TryExec=wine\n"), "{}", content);
+    }
+}
@@ -0,0 +1,305 @@
+//! Reads zip archives in-process: the central directory, and password-protected entries, so a
+//! password never goes on `unzip`'s command line where other users could see it.
+
+use flate2::CrcReader;
+use flate2::read::DeflateDecoder;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{IoContext, Result, SpawnError};
+
+/// A file or folder listed in the central directory.
+struct Entry {
+    name: String,
+    flags: u16,
+    method: u16,
+    mod_time: u16,
+    crc: u32,
+    compressed_size: u64,
+    local_header: u64,
+    /// Permission and file type bits, when the archive was made on Unix
+    mode: Option<u32>,
+}
+
+impl Entry {
+    fn is_encrypted(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u64_at(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// Marks a size or offset too big for its 32-bit field, found in the ZIP64 records instead.
+const ZIP64_MARKER: u32 = 0xFFFF_FFFF;
+
+/// Reads the central directory alone: the end of central directory record is found in the
+/// archive's last 64 KiB (it follows any comment) and says where the directory is, so the
+/// file data itself is never loaded. Archives over 4 GiB keep the real numbers in ZIP64 records.
+fn read_entries(file: &mut (impl Read + Seek)) -> Option<Vec<Entry>> {
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    let tail_len = len.min(65_557);
+    file.seek(SeekFrom::Start(len - tail_len)).ok()?;
+    let mut tail = vec![0; tail_len as usize];
+    file.read_exact(&mut tail).ok()?;
+    let end = tail.windows(4).rposition(|w| w == b"PK\x05\x06")?;
+
+    let mut size = u32_at(&tail, end + 12)? as u64;
+    let mut offset = u32_at(&tail, end + 16)? as u64;
+    if size == ZIP64_MARKER as u64 || offset == ZIP64_MARKER as u64 {
+        // The ZIP64 locator sits right before the end record and points at the ZIP64 end record
+        let locator = end.checked_sub(20)?;
+        if tail.get(locator..locator + 4)? != b"PK\x06\x07" {
+            return None;
+        }
+        let mut record = [0u8; 56];
+        file.seek(SeekFrom::Start(u64_at(&tail, locator + 8)?)).ok()?;
+        file.read_exact(&mut record).ok()?;
+        if &record[..4] != b"PK\x06\x06" {
+            return None;
+        }
+        size = u64_at(&record, 40)?;
+        offset = u64_at(&record, 48)?;
+    }
+    if offset.checked_add(size)? > len {
+        return None;
+    }
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut directory = vec![0; size as usize];
+    file.read_exact(&mut directory).ok()?;
+
+    let mut entries = Vec::new();
+    let mut at = 0;
+    while directory.get(at..at + 4) == Some(b"PK\x01\x02".as_slice()) {
+        let name_len = u16_at(&directory, at + 28)? as usize;
+        let extra_len = u16_at(&directory, at + 30)? as usize;
+        let skip = name_len + extra_len + u16_at(&directory, at + 32)? as usize;
+        let name = directory.get(at + 46..at + 46 + name_len)?;
+        let extra = directory.get(at + 46 + name_len..at + 46 + name_len + extra_len)?;
+        // The high byte of "version made by" is 3 for Unix, which keeps its mode in the top half of the external attributes
+        let made_on_unix = u16_at(&directory, at + 4)? >> 8 == 3;
+
+        // Each field set to the marker is stored in the ZIP64 extra field instead, in this order
+        let mut sizes = [u32_at(&directory, at + 24)?, u32_at(&directory, at + 20)?, u32_at(&directory, at + 42)?].map(|n| n as u64);
+        if sizes.contains(&(ZIP64_MARKER as u64)) {
+            let mut zip64 = zip64_extra(extra)?.chunks_exact(8).map(|n| u64::from_le_bytes(n.try_into().unwrap()));
+            for size in sizes.iter_mut().filter(|n| **n == ZIP64_MARKER as u64) {
+                *size = zip64.next()?;
+            }
+        }
+        let [_, compressed_size, local_header] = sizes;
+
+        entries.push(Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            flags: u16_at(&directory, at + 8)?,
+            method: u16_at(&directory, at + 10)?,
+            mod_time: u16_at(&directory, at + 12)?,
+            crc: u32_at(&directory, at + 16)?,
+            compressed_size,
+            local_header,
+            mode: made_on_unix.then(|| u32_at(&directory, at + 38).map(|attrs| attrs >> 16)).flatten(),
+        });
+        at += 46 + skip;
+    }
+    Some(entries)
+}
+
+/// The data of the ZIP64 field (id 1) among an entry's extra fields.
+fn zip64_extra(mut extra: &[u8]) -> Option<&[u8]> {
+    while extra.len() >= 4 {
+        let (id, len) = (u16_at(extra, 0)?, u16_at(extra, 2)? as usize);
+        let data = extra.get(4..4 + len)?;
+        if id == 1 {
+            return Some(data);
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+/// Whether any file in the zip is encrypted. Without a password, `unzip` would stop and wait
+/// for one on the terminal.
+pub fn is_encrypted(archive_path: &Path) -> bool {
+    let Ok(mut file) = File::open(archive_path) else {
+        return false;
+    };
+    read_entries(&mut file).is_some_and(|entries| entries.iter().any(Entry::is_encrypted))
+}
+
+/// CRC-32 lookup table, which the ZipCrypto keys are updated with.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32_update(crc: u32, byte: u8) -> u32 {
+    CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+}
+
+/// The traditional PKWARE cipher, keyed by the password.
+struct ZipCrypto {
+    keys: [u32; 3],
+}
+
+impl ZipCrypto {
+    fn new(password: &[u8]) -> ZipCrypto {
+        let mut cipher = ZipCrypto { keys: [0x1234_5678, 0x2345_6789, 0x3456_7890] };
+        for &byte in password {
+            cipher.update(byte);
+        }
+        cipher
+    }
+
+    fn update(&mut self, byte: u8) {
+        self.keys[0] = crc32_update(self.keys[0], byte);
+        self.keys[1] = self.keys[1].wrapping_add(self.keys[0] & 0xFF).wrapping_mul(134_775_813).wrapping_add(1);
+        self.keys[2] = crc32_update(self.keys[2], (self.keys[1] >> 24) as u8);
+    }
+
+    fn decrypt(&mut self, byte: u8) -> u8 {
+        let temp = (self.keys[2] as u16) | 2;
+        let plain = byte ^ (temp.wrapping_mul(temp ^ 1) >> 8) as u8;
+        self.update(plain);
+        plain
+    }
+}
+
+struct Decrypting<R> {
+    inner: R,
+    cipher: ZipCrypto,
+}
+
+impl<R: Read> Read for Decrypting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        for byte in &mut buf[..read] {
+            *byte = self.cipher.decrypt(*byte);
+        }
+        Ok(read)
+    }
+}
+
+/// Where an entry goes under the target folder, or `None` for a name that would land outside
+/// it, such as `../../.bashrc` or an absolute path.
+fn entry_path(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+fn extraction_error(reason: impl Into<String>) -> SpawnError {
+    SpawnError::Extraction { reason: reason.into(), hint: "" }
+}
+
+/// Extracts a zip with `password`, checking it against each encrypted file before writing it.
+/// Files use the traditional ZipCrypto cipher; AES-encrypted archives are left to `7z`.
+/// Symlinks are made last, so no file is ever written through one.
+pub fn extract_with_password(archive_path: &Path, target_dir: &Path, password: &str) -> Result<()> {
+    let mut file = BufReader::new(File::open(archive_path).context("Failed to open archive")?);
+    let entries = read_entries(&mut file).ok_or_else(|| extraction_error("the zip's central directory is damaged"))?;
+    let wrong_password = || SpawnError::WrongPassword(archive_path.to_path_buf());
+
+    let mut symlinks = Vec::new();
+    for entry in &entries {
+        let relative = entry_path(&entry.name).ok_or_else(|| extraction_error(format!("{:?} would be written outside the install folder", entry.name)))?;
+        let target = target_dir.join(&relative);
+        if entry.name.ends_with('/') {
+            fs::create_dir_all(&target).with_context(|| format!("Failed to create {:?}", target))?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+
+        let mut header = [0u8; 30];
+        file.seek(SeekFrom::Start(entry.local_header))
+            .and_then(|_| file.read_exact(&mut header))
+            .context("Failed to read archive")?;
+        if &header[..4] != b"PK\x03\x04" {
+            return Err(extraction_error(format!("the zip's entry for {:?} is damaged", entry.name)));
+        }
+        let name_and_extra = u16_at(&header, 26).unwrap_or(0) as i64 + u16_at(&header, 28).unwrap_or(0) as i64;
+        file.seek(SeekFrom::Current(name_and_extra)).context("Failed to read archive")?;
+        let data = (&mut file).take(entry.compressed_size);
+
+        let data: Box<dyn Read + '_> = if entry.is_encrypted() {
+            if entry.method == 99 {
+                return Err(SpawnError::Extraction {
+                    reason: "the zip uses AES encryption, which Spawn can't decrypt".to_string(),
+                    hint: "\nHint: Extract it with '7z x' and run Spawn on the folder.",
+                });
+            }
+            let mut decrypting = Decrypting { inner: data, cipher: ZipCrypto::new(password.as_bytes()) };
+            let mut check = [0u8; 12];
+            decrypting.read_exact(&mut check).context("Failed to read archive")?;
+            // The last header byte repeats the top of the CRC, or of the modification time when the CRC comes after the data
+            let expected = if entry.flags & 8 != 0 { (entry.mod_time >> 8) as u8 } else { (entry.crc >> 24) as u8 };
+            if check[11] != expected {
+                return Err(wrong_password());
+            }
+            Box::new(decrypting)
+        } else {
+            Box::new(data)
+        };
+        let data: Box<dyn Read + '_> = match entry.method {
+            0 => data,
+            8 => Box::new(DeflateDecoder::new(data)),
+            method => return Err(extraction_error(format!("{:?} uses compression method {}, which Spawn can't unpack", entry.name, method))),
+        };
+
+        let mut data = CrcReader::new(data);
+        let is_symlink = entry.mode.is_some_and(|mode| mode & 0o170000 == 0o120000);
+        if is_symlink {
+            let mut link = String::new();
+            // A wrong password garbles the link target, which then fails the CRC check below
+            let _ = data.read_to_string(&mut link);
+            symlinks.push((link, target.clone()));
+        } else {
+            let mut output = File::create(&target).with_context(|| format!("Failed to create {:?}", target))?;
+            // Data garbled by a wrong password may not even inflate
+            if std::io::copy(&mut data, &mut output).is_err() && entry.is_encrypted() {
+                return Err(wrong_password());
+            }
+        }
+        if data.crc().sum() != entry.crc {
+            return Err(if entry.is_encrypted() { wrong_password() } else { extraction_error(format!("{:?} is corrupted", entry.name)) });
+        }
+
+        if let Some(mode) = entry.mode.map(|mode| mode & 0o777).filter(|mode| *mode != 0 && !is_symlink) {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode)).with_context(|| format!("Failed to set permissions on {:?}", target))?;
+        }
+    }
+
+    for (link, target) in symlinks {
+        std::os::unix::fs::symlink(&link, &target).with_context(|| format!("Failed to create the link {:?}", target))?;
+    }
+    Ok(())
+}
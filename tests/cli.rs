@@ -0,0 +1,69 @@
+//! Runs the `spawn` binary the way a user would, inside a throwaway home folder.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A home folder under the system temp dir, removed with everything in it on drop.
+struct Home(PathBuf);
+
+impl Home {
+    fn new() -> Self {
+        let template = std::env::temp_dir().join("spawn-cli-XXXXXX");
+        Home(nix::unistd::mkdtemp(&template).expect("failed to create a temp dir"))
+    }
+
+    fn spawn(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_spawn"))
+            .arg("--no-update-check")
+            .args(args)
+            .env("HOME", &self.0)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .current_dir(&self.0)
+            .output()
+            .expect("failed to run spawn")
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes a `Game.tar.gz` holding an x86_64 binary at `Game/game.x86_64`.
+fn game_tarball(dir: &Path) -> PathBuf {
+    let path = dir.join("Game.tar.gz");
+    let file = std::fs::File::create(&path).unwrap();
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::fast()));
+    let elf = b"\x7fELF\x02\x01\x01\0";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(elf.len() as u64);
+    header.set_mode(0o755);
+    builder.append_data(&mut header, "Game/game.x86_64", elf.as_slice()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+    path
+}
+
+#[test]
+fn only_data_goes_to_stdout() {
+    let home = Home::new();
+    let archive = game_tarball(&home.0);
+    let archive = archive.to_str().unwrap();
+
+    let install = home.spawn(&[archive, "--yes"]);
+    let stderr = String::from_utf8_lossy(&install.stderr);
+    assert!(install.status.success(), "{}", stderr);
+    assert!(install.stdout.is_empty(), "{}", String::from_utf8_lossy(&install.stdout));
+    assert!(stderr.contains("is ready to play"), "{}", stderr);
+
+    let list = home.spawn(&["--list"]);
+    let stdout = String::from_utf8_lossy(&list.stdout);
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    assert!(stdout.contains("Game"), "{}", stdout);
+
+    let json = home.spawn(&[archive, "--yes", "--json"]);
+    assert!(json.status.success(), "{}", String::from_utf8_lossy(&json.stderr));
+    let entry: serde_json::Value = serde_json::from_slice(&json.stdout).expect("stdout holds only the JSON entry");
+    assert_eq!(entry["name"], "Game");
+}